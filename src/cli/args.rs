@@ -0,0 +1,829 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+
+use crate::config::parse_desktop_list;
+use crate::desktop_entry::exec::{is_env_name, parse_exec};
+use crate::desktop_entry::{DelayStrategy, EntryChanges};
+use crate::operations::bundle::ImportMode;
+use crate::operations::run::RunOptions;
+use crate::operations::systemd::UnitOptions;
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    List {
+        json: bool,
+        /// Compute the state for this desktop instead of the running one.
+        desktop: Option<Vec<String>>,
+    },
+    Show {
+        id: String,
+        json: bool,
+    },
+    Enable {
+        id: String,
+    },
+    Disable {
+        id: String,
+    },
+    Add {
+        id: Option<String>,
+        name: String,
+        exec: String,
+        comment: Option<String>,
+        icon: Option<String>,
+        delay_seconds: u32,
+        delay_strategy: Option<DelayStrategy>,
+        terminal: bool,
+        working_dir: Option<String>,
+        environment: Vec<(String, String)>,
+    },
+    Edit {
+        id: String,
+        changes: EntryChanges,
+        /// Print the diff instead of saving.
+        dry_run: bool,
+    },
+    Remove {
+        id: String,
+    },
+    /// Start an entry now and show what it printed.
+    Run {
+        id: String,
+        options: RunOptions,
+    },
+    Convert {
+        id: String,
+        options: UnitOptions,
+    },
+    /// Write a bundle to the file, or stdout when `None`.
+    Export {
+        path: Option<String>,
+    },
+    Import {
+        path: String,
+        mode: ImportMode,
+        dry_run: bool,
+    },
+    /// Lint the given files or directories, or all autostart entries when
+    /// none are given.
+    Check {
+        paths: Vec<String>,
+        json: bool,
+    },
+    /// Repair the given files in place, or all autostart entries when none
+    /// are given.
+    Fix {
+        paths: Vec<String>,
+        dry_run: bool,
+        absolute_exec: bool,
+    },
+    /// Revert the most recent changes recorded in the journal.
+    Undo {
+        count: usize,
+    },
+    History,
+    /// Check which entries started in this session and record the result;
+    /// `at_login` first waits for delayed entries.
+    SessionReport {
+        at_login: bool,
+    },
+    /// Add or remove the entry that writes a session report at every login.
+    SetSessionReport {
+        installed: bool,
+    },
+    ListProfiles,
+    /// Capture the current entries under `name`.
+    SaveProfile {
+        name: String,
+    },
+    UseProfile {
+        name: String,
+    },
+    RemoveProfile {
+        name: String,
+    },
+    ShowConfig,
+    /// `None` goes back to picking the strategy from the running desktop.
+    SetDelayStrategy {
+        strategy: Option<DelayStrategy>,
+    },
+    Help,
+}
+
+pub const USAGE: &str = "\
+Usage: onset [COMMAND]
+
+Without a command the graphical interface is started.
+
+Commands:
+  list [--json] [--desktop <desktop>]
+                            List autostart entries; with --desktop, the state
+                            is shown as it would be in that session (KDE,
+                            ubuntu:GNOME)
+  show <id> [--json]        Show details of an entry
+  enable <id>               Enable an entry or a systemd unit (<name>.service)
+  disable <id>              Disable an entry or a systemd unit (<name>.service)
+  add --name <name> --exec <command> [--id <id>] [--comment <text>]
+      [--icon <icon>] [--delay <seconds>] [--delay-strategy <strategy>]
+      [--terminal] [--working-dir <dir>] [--env <NAME=value...>]
+                            Create a new autostart entry
+  edit <id> [--name <name>] [--exec <command>] [--comment <text>]
+      [--icon <icon>] [--delay <seconds>] [--delay-strategy <strategy>]
+      [--terminal | --no-terminal] [--working-dir <dir>]
+      [--env <NAME=value...>] [--locale <locale>] [--dry-run]
+                            Modify an existing entry; with --locale, name and
+                            comment are written as translations (Name[de]);
+                            an empty --icon, --working-dir or --env removes it;
+                            --env replaces all variables;
+                            --delay-strategy alone migrates an existing delay;
+                            --dry-run prints the diff without saving
+  rm <id>                   Delete an entry, or revert a user override
+  run <id> [--with-delay] [--timeout <seconds>]
+                            Start an entry now and print its output; programs
+                            still running after the timeout (5s) keep running
+  convert <id> [--restart <policy>] [--after <units>] [--memory-max <size>]
+      [--cpu-quota <percent>]
+                            Replace an entry with a systemd user service
+                            started with the graphical session
+  export [<file>]           Save all user entries to a bundle (stdout by
+                            default)
+  import <file> [--replace] [--dry-run]
+                            Apply a bundle; --replace also removes entries
+                            missing from it, --dry-run only shows the changes
+  check [<path>...] [--json]
+                            Check desktop files (or directories of them)
+                            against the spec; defaults to all autostart
+                            entries and fails if any has errors
+  fix [<path>...] [--dry-run] [--absolute-exec]
+                            Repair common problems found by check, showing
+                            a diff of each file first; --absolute-exec also
+                            replaces program names with their full path
+  undo [<count>]            Revert the last change, or the last <count> changes
+  history                   List the changes that can be undone
+  session-report [--at-login]
+                            Record which enabled entries are running or
+                            exited; --at-login first waits for delayed ones
+  session-report install|uninstall
+                            Write a session report at every login
+  profile [list]            List profiles, marking the active one
+  profile save <name>       Save which entries are enabled and their delays
+  profile use <name>        Switch to a profile and show what changed
+  profile rm <name>         Delete a profile
+  config                    Show preferences
+  config delay-strategy <strategy|auto>
+                            Set the strategy used for new delays
+  help                      Show this help
+
+Delay strategies: shell_wrapper, gnome_key, kde_phase, systemd
+
+Options:
+  -V, --version             Print version";
+
+/// Parse command-line arguments (without the program name). Returns `None`
+/// when no command was given and the GUI should be launched.
+pub fn parse_args(args: &[String]) -> Result<Option<Command>> {
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(None);
+    };
+
+    let command = match command.as_str() {
+        "list" | "ls" => {
+            let mut parsed = parse_flags(rest, &["--desktop"], &["--json"])?;
+            parsed.no_positionals()?;
+            Command::List {
+                json: parsed.switch("--json"),
+                desktop: parsed.value("--desktop").map(|d| parse_desktop_list(&d)),
+            }
+        }
+        "show" => {
+            let mut parsed = parse_flags(rest, &[], &["--json"])?;
+            Command::Show {
+                id: parsed.single_id()?,
+                json: parsed.switch("--json"),
+            }
+        }
+        "enable" => Command::Enable {
+            id: parse_flags(rest, &[], &[])?.single_id()?,
+        },
+        "disable" => Command::Disable {
+            id: parse_flags(rest, &[], &[])?.single_id()?,
+        },
+        "add" => {
+            let mut parsed = parse_flags(
+                rest,
+                &[
+                    "--id",
+                    "--name",
+                    "--exec",
+                    "--comment",
+                    "--icon",
+                    "--delay",
+                    "--delay-strategy",
+                    "--working-dir",
+                    "--env",
+                ],
+                &["--terminal"],
+            )?;
+            parsed.no_positionals()?;
+            Command::Add {
+                id: parsed.value("--id"),
+                name: parsed.required("--name")?,
+                exec: parsed.required("--exec")?,
+                comment: parsed.value("--comment"),
+                icon: parsed.value("--icon").filter(|icon| !icon.is_empty()),
+                delay_seconds: parsed.delay()?.unwrap_or(0),
+                delay_strategy: parsed.delay_strategy()?,
+                terminal: parsed.switch("--terminal"),
+                working_dir: parsed.value("--working-dir"),
+                environment: parsed.environment()?.unwrap_or_default(),
+            }
+        }
+        "edit" => {
+            let mut parsed = parse_flags(
+                rest,
+                &[
+                    "--name",
+                    "--exec",
+                    "--comment",
+                    "--icon",
+                    "--delay",
+                    "--delay-strategy",
+                    "--working-dir",
+                    "--env",
+                    "--locale",
+                ],
+                &["--terminal", "--no-terminal", "--dry-run"],
+            )?;
+            let id = parsed.single_id()?;
+            let terminal = match (parsed.switch("--terminal"), parsed.switch("--no-terminal")) {
+                (true, true) => bail!("--terminal and --no-terminal are mutually exclusive"),
+                (true, false) => Some(true),
+                (false, true) => Some(false),
+                (false, false) => None,
+            };
+            let changes = EntryChanges {
+                locale: parsed.value("--locale"),
+                name: parsed.value("--name"),
+                exec: parsed.value("--exec"),
+                comment: parsed.value("--comment"),
+                icon: parsed.value("--icon"),
+                delay_seconds: parsed.delay()?,
+                delay_strategy: parsed.delay_strategy()?,
+                terminal,
+                working_dir: parsed
+                    .value("--working-dir")
+                    .map(|dir| (!dir.is_empty()).then_some(dir)),
+                environment: parsed.environment()?,
+                ..Default::default()
+            };
+            Command::Edit {
+                id,
+                changes,
+                dry_run: parsed.switch("--dry-run"),
+            }
+        }
+        "run" => {
+            let mut parsed = parse_flags(rest, &["--timeout"], &["--with-delay"])?;
+            let mut options = RunOptions {
+                with_delay: parsed.switch("--with-delay"),
+                ..Default::default()
+            };
+            if let Some(timeout) = parsed.value("--timeout") {
+                match timeout.parse() {
+                    Ok(seconds) => options.timeout = Duration::from_secs(seconds),
+                    Err(_) => bail!("Invalid timeout: {}", timeout),
+                }
+            }
+            Command::Run {
+                id: parsed.single_id()?,
+                options,
+            }
+        }
+        "rm" | "remove" => Command::Remove {
+            id: parse_flags(rest, &[], &[])?.single_id()?,
+        },
+        "convert" => {
+            let mut parsed = parse_flags(
+                rest,
+                &["--restart", "--after", "--memory-max", "--cpu-quota"],
+                &[],
+            )?;
+            let id = parsed.single_id()?;
+            let options = UnitOptions {
+                restart: parsed.value("--restart"),
+                after: parsed
+                    .value("--after")
+                    .map(|a| a.split_whitespace().map(String::from).collect())
+                    .unwrap_or_default(),
+                memory_max: parsed.value("--memory-max"),
+                cpu_quota: parsed.value("--cpu-quota"),
+            };
+            Command::Convert { id, options }
+        }
+        "export" => {
+            let parsed = parse_flags(rest, &[], &[])?;
+            match parsed.positionals.as_slice() {
+                [] => Command::Export { path: None },
+                [path] => Command::Export {
+                    path: Some(path.clone()),
+                },
+                [_, extra, ..] => bail!("Unexpected argument: {}", extra),
+            }
+        }
+        "import" => {
+            let mut parsed = parse_flags(rest, &[], &["--replace", "--dry-run"])?;
+            let mode = if parsed.switch("--replace") {
+                ImportMode::Replace
+            } else {
+                ImportMode::Merge
+            };
+            let dry_run = parsed.switch("--dry-run");
+            match parsed.positionals.len() {
+                0 => bail!("Missing bundle file"),
+                1 => Command::Import {
+                    path: parsed.positionals.remove(0),
+                    mode,
+                    dry_run,
+                },
+                _ => bail!("Unexpected argument: {}", parsed.positionals[1]),
+            }
+        }
+        "check" => {
+            let parsed = parse_flags(rest, &[], &["--json"])?;
+            Command::Check {
+                json: parsed.switch("--json"),
+                paths: parsed.positionals,
+            }
+        }
+        "fix" => {
+            let parsed = parse_flags(rest, &[], &["--dry-run", "--absolute-exec"])?;
+            Command::Fix {
+                dry_run: parsed.switch("--dry-run"),
+                absolute_exec: parsed.switch("--absolute-exec"),
+                paths: parsed.positionals,
+            }
+        }
+        "undo" => {
+            let parsed = parse_flags(rest, &[], &[])?;
+            let count = match parsed.positionals.as_slice() {
+                [] => 1,
+                [count] => match count.parse() {
+                    Ok(count) if count > 0 => count,
+                    _ => bail!("Invalid count: {}", count),
+                },
+                [_, extra, ..] => bail!("Unexpected argument: {}", extra),
+            };
+            Command::Undo { count }
+        }
+        "history" => {
+            parse_flags(rest, &[], &[])?.no_positionals()?;
+            Command::History
+        }
+        "session-report" | "--session-report" => {
+            let parsed = parse_flags(rest, &[], &["--at-login"])?;
+            match parsed.positionals.as_slice() {
+                [] => Command::SessionReport {
+                    at_login: parsed.switch("--at-login"),
+                },
+                [action] if parsed.switch("--at-login") => {
+                    bail!("--at-login cannot be used with {}", action)
+                }
+                [action] if action == "install" => Command::SetSessionReport { installed: true },
+                [action] if action == "uninstall" => Command::SetSessionReport { installed: false },
+                [action, ..] => bail!("Unknown session report action: {}", action),
+            }
+        }
+        "profile" => {
+            let parsed = parse_flags(rest, &[], &[])?;
+            match parsed.positionals.as_slice() {
+                [] => Command::ListProfiles,
+                [action] if action == "list" => Command::ListProfiles,
+                [action, name] => {
+                    let name = name.clone();
+                    match action.as_str() {
+                        "save" => Command::SaveProfile { name },
+                        "use" => Command::UseProfile { name },
+                        "rm" | "remove" => Command::RemoveProfile { name },
+                        _ => bail!("Unknown profile action: {}", action),
+                    }
+                }
+                [action] if ["save", "use", "rm", "remove"].contains(&action.as_str()) => {
+                    bail!("Missing profile name")
+                }
+                [action, ..] => bail!("Unknown profile action: {}", action),
+            }
+        }
+        "config" => {
+            let parsed = parse_flags(rest, &[], &[])?;
+            match parsed.positionals.as_slice() {
+                [] => Command::ShowConfig,
+                [key, value] if key == "delay-strategy" => Command::SetDelayStrategy {
+                    strategy: match value.as_str() {
+                        "auto" => None,
+                        value => Some(parse_delay_strategy(value)?),
+                    },
+                },
+                [key] if key == "delay-strategy" => bail!("Missing value for delay-strategy"),
+                [key, ..] => bail!("Unknown setting: {}", key),
+            }
+        }
+        "help" | "--help" | "-h" => Command::Help,
+        other => bail!("Unknown command: {}", other),
+    };
+
+    Ok(Some(command))
+}
+
+struct ParsedFlags {
+    positionals: Vec<String>,
+    values: HashMap<&'static str, String>,
+    switches: HashSet<&'static str>,
+}
+
+impl ParsedFlags {
+    fn value(&mut self, flag: &str) -> Option<String> {
+        self.values.remove(flag)
+    }
+
+    fn required(&mut self, flag: &str) -> Result<String> {
+        match self.value(flag) {
+            Some(value) => Ok(value),
+            None => bail!("Missing required option {}", flag),
+        }
+    }
+
+    fn switch(&self, flag: &str) -> bool {
+        self.switches.contains(flag)
+    }
+
+    fn delay(&mut self) -> Result<Option<u32>> {
+        self.value("--delay")
+            .map(|d| match d.parse() {
+                Ok(seconds) => Ok(seconds),
+                Err(_) => bail!("Invalid delay: {}", d),
+            })
+            .transpose()
+    }
+
+    fn delay_strategy(&mut self) -> Result<Option<DelayStrategy>> {
+        self.value("--delay-strategy")
+            .map(|s| parse_delay_strategy(&s))
+            .transpose()
+    }
+
+    /// `--env "A=1 B='two words'"`, split like a command line.
+    fn environment(&mut self) -> Result<Option<Vec<(String, String)>>> {
+        let Some(value) = self.value("--env") else {
+            return Ok(None);
+        };
+        if value.trim().is_empty() {
+            return Ok(Some(Vec::new()));
+        }
+
+        let Ok(assignments) = parse_exec(&value) else {
+            bail!("Invalid environment: {}", value);
+        };
+        assignments
+            .iter()
+            .map(|assignment| match assignment.split_once('=') {
+                Some((name, value)) if is_env_name(name) => {
+                    Ok((name.to_string(), value.to_string()))
+                }
+                _ => bail!(
+                    "Invalid environment variable (expected NAME=value): {}",
+                    assignment
+                ),
+            })
+            .collect::<Result<_>>()
+            .map(Some)
+    }
+
+    fn no_positionals(&self) -> Result<()> {
+        if let Some(extra) = self.positionals.first() {
+            bail!("Unexpected argument: {}", extra);
+        }
+        Ok(())
+    }
+
+    fn single_id(&mut self) -> Result<String> {
+        match self.positionals.len() {
+            0 => bail!("Missing entry ID"),
+            1 => Ok(self.positionals.remove(0)),
+            _ => bail!("Unexpected argument: {}", self.positionals[1]),
+        }
+    }
+}
+
+fn parse_delay_strategy(value: &str) -> Result<DelayStrategy> {
+    match DelayStrategy::from_key(value) {
+        Some(strategy) => Ok(strategy),
+        None => bail!("Unknown delay strategy: {}", value),
+    }
+}
+
+fn parse_flags(
+    args: &[String],
+    value_flags: &[&'static str],
+    switch_flags: &[&'static str],
+) -> Result<ParsedFlags> {
+    let mut parsed = ParsedFlags {
+        positionals: Vec::new(),
+        values: HashMap::new(),
+        switches: HashSet::new(),
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            parsed.positionals.extend(iter.by_ref().cloned());
+            break;
+        }
+
+        if !arg.starts_with("--") {
+            parsed.positionals.push(arg.clone());
+            continue;
+        }
+
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+
+        if let Some(&known) = value_flags.iter().find(|&&f| f == flag) {
+            let value = match inline_value {
+                Some(value) => value,
+                None => match iter.next() {
+                    Some(value) => value.clone(),
+                    None => bail!("Option {} requires a value", flag),
+                },
+            };
+            parsed.values.insert(known, value);
+        } else if let Some(&known) = switch_flags.iter().find(|&&f| f == flag) {
+            if inline_value.is_some() {
+                bail!("Option {} does not take a value", flag);
+            }
+            parsed.switches.insert(known);
+        } else {
+            bail!("Unknown option: {}", flag);
+        }
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &[&str]) -> Vec<String> {
+        s.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_no_args_launches_gui() {
+        assert!(parse_args(&[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_add() {
+        let command = parse_args(&args(&[
+            "add",
+            "--name",
+            "Syncthing",
+            "--exec=syncthing serve --no-browser",
+            "--delay",
+            "10",
+        ]))
+        .unwrap()
+        .unwrap();
+
+        match command {
+            Command::Add {
+                id,
+                name,
+                exec,
+                delay_seconds,
+                terminal,
+                ..
+            } => {
+                assert_eq!(id, None);
+                assert_eq!(name, "Syncthing");
+                assert_eq!(exec, "syncthing serve --no-browser");
+                assert_eq!(delay_seconds, 10);
+                assert!(!terminal);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_edit() {
+        let command = parse_args(&args(&[
+            "edit",
+            "foo",
+            "--no-terminal",
+            "--comment",
+            "",
+            "--working-dir=",
+        ]))
+        .unwrap()
+        .unwrap();
+
+        match command {
+            Command::Edit {
+                id,
+                changes,
+                dry_run,
+            } => {
+                assert_eq!(id, "foo");
+                assert!(!dry_run);
+                assert_eq!(changes.delay_strategy, None);
+                assert_eq!(changes.terminal, Some(false));
+                assert_eq!(changes.comment.as_deref(), Some(""));
+                assert_eq!(changes.working_dir, Some(None));
+                assert_eq!(changes.environment, None);
+                assert!(changes.name.is_none());
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        assert!(matches!(
+            parse_args(&args(&["edit", "foo", "--name", "Bar", "--dry-run"])).unwrap(),
+            Some(Command::Edit { dry_run: true, .. })
+        ));
+        assert!(matches!(
+            parse_args(&args(&["edit", "foo", "--env", "QT_QPA_PLATFORM=wayland 'A=b c'"])).unwrap(),
+            Some(Command::Edit { changes, .. }) if changes.environment == Some(vec![
+                ("QT_QPA_PLATFORM".to_string(), "wayland".to_string()),
+                ("A".to_string(), "b c".to_string()),
+            ])
+        ));
+        assert!(parse_args(&args(&["edit", "foo", "--env", "wayland"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_json_flag() {
+        assert!(matches!(
+            parse_args(&args(&["list", "--json"])).unwrap(),
+            Some(Command::List { json: true, .. })
+        ));
+        assert!(matches!(
+            parse_args(&args(&["list", "--desktop", "ubuntu:GNOME"])).unwrap(),
+            Some(Command::List { json: false, desktop: Some(d) }) if d == ["ubuntu", "GNOME"]
+        ));
+        assert!(matches!(
+            parse_args(&args(&["show", "--json", "foo"])).unwrap(),
+            Some(Command::Show { json: true, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_delay_strategy() {
+        let command = parse_args(&args(&["edit", "foo", "--delay-strategy=systemd"]))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            command,
+            Command::Edit { changes, .. } if changes.delay_strategy == Some(DelayStrategy::Systemd)
+        ));
+
+        assert!(matches!(
+            parse_args(&args(&["config", "delay-strategy", "auto"])).unwrap(),
+            Some(Command::SetDelayStrategy { strategy: None })
+        ));
+        assert!(parse_args(&args(&["config", "delay-strategy", "later"])).is_err());
+        assert_eq!(
+            parse_args(&args(&["config", "delay-strategy"]))
+                .unwrap_err()
+                .to_string(),
+            "Missing value for delay-strategy"
+        );
+        assert!(
+            parse_args(&args(&[
+                "add",
+                "--name",
+                "x",
+                "--exec",
+                "y",
+                "--delay-strategy",
+                "x"
+            ]))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_convert() {
+        let command = parse_args(&args(&[
+            "convert",
+            "tray",
+            "--restart=on-failure",
+            "--after",
+            "pipewire.service wireplumber.service",
+        ]))
+        .unwrap()
+        .unwrap();
+
+        match command {
+            Command::Convert { id, options } => {
+                assert_eq!(id, "tray");
+                assert_eq!(options.restart.as_deref(), Some("on-failure"));
+                assert_eq!(
+                    options.after,
+                    vec!["pipewire.service", "wireplumber.service"]
+                );
+                assert!(options.memory_max.is_none());
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_args(&args(&["frobnicate"])).is_err());
+        assert!(parse_args(&args(&["enable"])).is_err());
+        assert!(parse_args(&args(&["enable", "a", "b"])).is_err());
+        assert!(parse_args(&args(&["add", "--name", "x"])).is_err());
+        assert!(
+            parse_args(&args(&[
+                "add", "--name", "x", "--exec", "y", "--delay", "soon"
+            ]))
+            .is_err()
+        );
+        assert!(parse_args(&args(&["list", "--bogus"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_undo() {
+        assert!(matches!(
+            parse_args(&args(&["undo"])).unwrap(),
+            Some(Command::Undo { count: 1 })
+        ));
+        assert!(matches!(
+            parse_args(&args(&["undo", "3"])).unwrap(),
+            Some(Command::Undo { count: 3 })
+        ));
+        assert!(parse_args(&args(&["undo", "0"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_import() {
+        assert!(matches!(
+            parse_args(&args(&["import", "--replace", "setup.json"])).unwrap(),
+            Some(Command::Import {
+                mode: ImportMode::Replace,
+                dry_run: false,
+                ..
+            })
+        ));
+        assert!(parse_args(&args(&["import"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_check() {
+        assert!(matches!(
+            parse_args(&args(&["check", "--json", "a.desktop", "dir"])).unwrap(),
+            Some(Command::Check { paths, json: true }) if paths == ["a.desktop", "dir"]
+        ));
+    }
+
+    #[test]
+    fn test_parse_profile() {
+        assert!(matches!(
+            parse_args(&args(&["profile"])).unwrap(),
+            Some(Command::ListProfiles)
+        ));
+        assert!(matches!(
+            parse_args(&args(&["profile", "use", "work"])).unwrap(),
+            Some(Command::UseProfile { name }) if name == "work"
+        ));
+        assert!(parse_args(&args(&["profile", "use"])).is_err());
+        assert!(parse_args(&args(&["profile", "switch", "work"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_run() {
+        assert!(matches!(
+            parse_args(&args(&["run", "foo", "--timeout", "30", "--with-delay"])).unwrap(),
+            Some(Command::Run { id, options })
+                if id == "foo" && options.with_delay && options.timeout == Duration::from_secs(30)
+        ));
+        assert!(parse_args(&args(&["run", "foo", "--timeout", "soon"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_session_report() {
+        assert!(matches!(
+            parse_args(&args(&["--session-report", "--at-login"])).unwrap(),
+            Some(Command::SessionReport { at_login: true })
+        ));
+        assert!(matches!(
+            parse_args(&args(&["session-report", "uninstall"])).unwrap(),
+            Some(Command::SetSessionReport { installed: false })
+        ));
+        assert!(parse_args(&args(&["session-report", "enable"])).is_err());
+        assert!(parse_args(&args(&["session-report", "install", "--at-login"])).is_err());
+    }
+}
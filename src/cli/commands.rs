@@ -0,0 +1,667 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::Preferences;
+use crate::desktop_entry::CreateOptions;
+use crate::desktop_entry::exec::quote_exec;
+use crate::desktop_entry::fix::Fix;
+use crate::desktop_entry::lint::{Diagnostic, Severity, lint_desktop_file};
+use crate::desktop_entry::writer::id_from_name;
+use crate::discovery::{
+    discover_autostart_entries, discover_broken_entries, discover_systemd_units,
+    find_autostart_entry, find_systemd_unit,
+};
+use crate::model::{AutostartEntry, EntryOrigin, SystemdUnit};
+use crate::operations::bundle::{
+    Bundle, ImportMode, ImportPlan, apply_import, export_bundle, plan_import,
+};
+use crate::operations::delay::preferred_delay_strategy;
+use crate::operations::fix::{FixPlan, apply_fix, plan_autostart_fixes, plan_fix};
+use crate::operations::history::{
+    install_report_entry, last_session_report, login_wait, uninstall_report_entry,
+    write_session_report,
+};
+use crate::operations::journal::{journal_records, undo_last};
+use crate::operations::profile::{
+    apply_profile, capture_profile, delete_profile, list_profiles, load_profile, save_profile,
+};
+use crate::operations::run::{RunOptions, describe_exit, run_entry};
+use crate::operations::systemd::{convert_to_systemd_unit, set_unit_enabled};
+use crate::operations::{
+    create_autostart_entry, delete_autostart_entry, edit_autostart_entry, preview_edit,
+    set_entry_enabled,
+};
+use crate::utils::format_age;
+
+use super::args::{Command, USAGE};
+use super::json::{check_report_to_json, entries_to_json, entry_to_json, unit_to_json};
+
+pub fn execute(command: Command) -> Result<()> {
+    match command {
+        Command::List { json, desktop } => list(json, desktop.as_deref()),
+        Command::Show { id, json } => show(&id, json),
+        Command::Enable { id } => set_enabled(&id, true),
+        Command::Disable { id } => set_enabled(&id, false),
+        Command::Add {
+            id,
+            name,
+            exec,
+            comment,
+            icon,
+            delay_seconds,
+            delay_strategy,
+            terminal,
+            working_dir,
+            environment,
+        } => {
+            let id = id.unwrap_or_else(|| id_from_name(&name));
+            let options = CreateOptions {
+                comment,
+                icon,
+                delay_seconds,
+                delay_strategy,
+                terminal,
+                working_dir,
+                environment,
+                ..Default::default()
+            };
+            let (path, _) = create_autostart_entry(&id, &name, &exec, options)?;
+            println!("Created {}", path.display());
+            Ok(())
+        }
+        Command::Edit {
+            id,
+            changes,
+            dry_run: true,
+        } => {
+            let entry = require_entry(&id)?;
+            let diff = preview_edit(&entry, changes)?;
+            if diff.is_empty() {
+                println!("No changes to {}", entry.path.display());
+            } else {
+                print!("{}", diff);
+            }
+            Ok(())
+        }
+        Command::Edit {
+            id,
+            changes,
+            dry_run: false,
+        } => {
+            let entry = require_entry(&id)?;
+            edit_autostart_entry(&entry, changes)?;
+            println!("Updated {}", entry.user_path().display());
+            Ok(())
+        }
+        Command::Remove { id } => {
+            let entry = require_entry(&id)?;
+            delete_autostart_entry(&entry)?;
+            if entry.origin == EntryOrigin::UserOverridesSystem {
+                println!("Removed user override for {}", entry.id);
+            } else {
+                println!("Moved {} to the trash", entry.path.display());
+            }
+            Ok(())
+        }
+        Command::Run { id, options } => run(&id, &options),
+        Command::Convert { id, options } => {
+            let entry = require_entry(&id)?;
+            let path = convert_to_systemd_unit(&entry, &options)?;
+            println!("Created {} and disabled {}", path.display(), entry.id);
+            println!("Run `systemctl --user daemon-reload` to load it now");
+            Ok(())
+        }
+        Command::Export { path } => {
+            let json = export_bundle()?.to_json()?;
+            match path {
+                Some(path) => {
+                    std::fs::write(&path, json + "\n")
+                        .with_context(|| format!("Failed to write {}", path))?;
+                    println!("Exported to {}", path);
+                }
+                None => println!("{}", json),
+            }
+            Ok(())
+        }
+        Command::Import {
+            path,
+            mode,
+            dry_run,
+        } => import(&path, mode, dry_run),
+        Command::Check { paths, json } => check(&paths, json),
+        Command::Fix {
+            paths,
+            dry_run,
+            absolute_exec,
+        } => fix(&paths, dry_run, absolute_exec),
+        Command::Undo { count } => {
+            for record in undo_last(count)? {
+                println!("Undid: {}", record.description);
+            }
+            Ok(())
+        }
+        Command::History => history(),
+        Command::SessionReport { at_login } => session_report(at_login),
+        Command::SetSessionReport { installed: true } => {
+            let path = install_report_entry()?;
+            println!("Installed {}", path.display());
+            Ok(())
+        }
+        Command::SetSessionReport { installed: false } => {
+            uninstall_report_entry()?;
+            println!("Removed the session report entry");
+            Ok(())
+        }
+        Command::ListProfiles => {
+            let profiles = list_profiles();
+            if profiles.is_empty() {
+                println!("No profiles; create one with `onset profile save <name>`");
+            }
+            let active = Preferences::load().active_profile;
+            for name in profiles {
+                let marker = if active.as_ref() == Some(&name) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{} {}", marker, name);
+            }
+            Ok(())
+        }
+        Command::SaveProfile { name } => {
+            let profile = capture_profile(&name)?;
+            let path = save_profile(&profile)?;
+            println!(
+                "Saved {} entries to {}",
+                profile.entries.len(),
+                path.display()
+            );
+            Ok(())
+        }
+        Command::UseProfile { name } => {
+            let (changes, _) = apply_profile(&load_profile(&name)?)?;
+            if changes.is_empty() {
+                println!("Switched to {}; nothing changed", name);
+            } else {
+                for change in &changes {
+                    println!("{}", change);
+                }
+                println!("Switched to {}; `onset undo` reverts it", name);
+            }
+            Ok(())
+        }
+        Command::RemoveProfile { name } => {
+            delete_profile(&name)?;
+            let mut preferences = Preferences::load();
+            if preferences.active_profile.as_ref() == Some(&name) {
+                preferences.active_profile = None;
+                preferences.save()?;
+            }
+            println!("Deleted profile {}", name);
+            Ok(())
+        }
+        Command::ShowConfig => {
+            let preferences = Preferences::load();
+            println!(
+                "delay-strategy: {}{}",
+                preferred_delay_strategy().key(),
+                if preferences.delay_strategy.is_none() {
+                    " (auto)"
+                } else {
+                    ""
+                }
+            );
+            Ok(())
+        }
+        Command::SetDelayStrategy { strategy } => {
+            let mut preferences = Preferences::load();
+            preferences.delay_strategy = strategy;
+            preferences.save()?;
+            println!("New delays use {}", preferred_delay_strategy());
+            Ok(())
+        }
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+fn require_entry(id: &str) -> Result<AutostartEntry> {
+    find_autostart_entry(id)?.with_context(|| format!("No autostart entry with ID '{}'", id))
+}
+
+fn import(path: &str, mode: ImportMode, dry_run: bool) -> Result<()> {
+    let json = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    let bundle = Bundle::from_json(&json)?;
+    let plan = plan_import(&bundle, mode)?;
+
+    print_import_plan(&plan);
+
+    if !plan.has_changes() {
+        println!("Nothing to import");
+    } else if !dry_run {
+        apply_import(&bundle, &plan)?;
+        println!("Imported {}; `onset undo` reverts it", path);
+    }
+
+    Ok(())
+}
+
+fn check(paths: &[String], json: bool) -> Result<()> {
+    let files: Vec<(PathBuf, Vec<Diagnostic>)> = if paths.is_empty() {
+        let mut files: Vec<(PathBuf, Vec<Diagnostic>)> = discover_autostart_entries()?
+            .into_iter()
+            .map(|e| (e.path, lint_desktop_file(&e.raw_content)))
+            .chain(
+                discover_broken_entries()?
+                    .into_iter()
+                    .map(|b| (b.path, b.diagnostics)),
+            )
+            .collect();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        files
+    } else {
+        let mut files = Vec::new();
+        for path in paths {
+            for file in desktop_files(Path::new(path))? {
+                let content = std::fs::read_to_string(&file)
+                    .with_context(|| format!("Failed to read {}", file.display()))?;
+                let diagnostics = lint_desktop_file(&content);
+                files.push((file, diagnostics));
+            }
+        }
+        files
+    };
+
+    let count = |severity| {
+        files
+            .iter()
+            .flat_map(|(_, diagnostics)| diagnostics)
+            .filter(|d| d.severity == severity)
+            .count()
+    };
+    let errors = count(Severity::Error);
+    let warnings = count(Severity::Warning);
+
+    if json {
+        println!("{}", check_report_to_json(&files)?);
+    } else {
+        for (path, diagnostics) in &files {
+            for diagnostic in diagnostics {
+                let separator = if diagnostic.line.is_some() { ":" } else { ": " };
+                println!("{}{}{}", path.display(), separator, diagnostic);
+            }
+        }
+        if errors + warnings == 0 {
+            println!("No problems found in {} files", files.len());
+        } else {
+            println!(
+                "{} errors and {} warnings in {} files",
+                errors,
+                warnings,
+                files.len()
+            );
+        }
+    }
+
+    if errors > 0 {
+        bail!("{} errors found", errors);
+    }
+
+    Ok(())
+}
+
+fn fix(paths: &[String], dry_run: bool, absolute_exec: bool) -> Result<()> {
+    let mut fixes = Fix::DEFAULT.to_vec();
+    if absolute_exec {
+        fixes.push(Fix::AbsoluteExec);
+    }
+
+    let plans: Vec<FixPlan> = if paths.is_empty() {
+        plan_autostart_fixes(&fixes)?
+    } else {
+        let mut plans = Vec::new();
+        for path in paths {
+            for file in desktop_files(Path::new(path))? {
+                plans.extend(plan_fix(&file, &file, &fixes)?);
+            }
+        }
+        plans
+    };
+
+    if plans.is_empty() {
+        println!("Nothing to fix");
+        return Ok(());
+    }
+
+    for plan in &plans {
+        print!("{}", plan.diff());
+        let applied: Vec<String> = plan.applied.iter().map(ToString::to_string).collect();
+        if dry_run {
+            println!(
+                "Would fix {}: {}\n",
+                plan.target.display(),
+                applied.join(", ")
+            );
+        } else {
+            apply_fix(plan)?;
+            println!("Fixed {}: {}\n", plan.target.display(), applied.join(", "));
+        }
+    }
+
+    if !dry_run {
+        println!("`onset undo {}` reverts the fixes", plans.len());
+    }
+
+    Ok(())
+}
+
+fn run(id: &str, options: &RunOptions) -> Result<()> {
+    let entry = require_entry(id)?;
+    if options.with_delay
+        && let Some(seconds) = entry.delay_seconds()
+    {
+        println!("Waiting {}s before starting", seconds);
+    }
+
+    let output = run_entry(&entry, options)?;
+    println!("$ {}", quote_exec(&output.command));
+    print!("{}", output.stdout);
+    eprint!("{}", output.stderr);
+
+    match output.status {
+        None => {
+            println!(
+                "Still running after {}s (PID {}); further output goes to {}",
+                options.timeout.as_secs(),
+                output.pid,
+                output.stdout_path.display()
+            );
+            Ok(())
+        }
+        Some(status) if status.success() => {
+            println!("Exited successfully");
+            Ok(())
+        }
+        Some(status) => bail!("{} {}", entry.id, describe_exit(status)),
+    }
+}
+
+/// `path` itself, or the `.desktop` files in it when it is a directory.
+fn desktop_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "desktop"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn print_import_plan(plan: &ImportPlan) {
+    let lines = [
+        ("+", "add", &plan.additions),
+        ("~", "overwrite", &plan.conflicts),
+        ("-", "remove", &plan.removals),
+    ];
+
+    for (marker, action, ids) in lines {
+        for id in ids {
+            println!("{} {:<9} {}", marker, action, id);
+        }
+    }
+}
+
+fn history() -> Result<()> {
+    let records = journal_records()?;
+    if records.is_empty() {
+        println!("No changes to undo");
+        return Ok(());
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    // Numbered the way `undo <count>` counts
+    for (index, record) in records.iter().rev().enumerate() {
+        println!(
+            "{:>3}  {:<16}  {}",
+            index + 1,
+            format_age(now.saturating_sub(record.timestamp)),
+            record.description
+        );
+    }
+
+    Ok(())
+}
+
+fn session_report(at_login: bool) -> Result<()> {
+    let entries = discover_autostart_entries()?;
+    if at_login {
+        std::thread::sleep(login_wait(&entries));
+    }
+
+    let report = write_session_report(&entries)?;
+    if report.launches.is_empty() {
+        println!("No enabled entries");
+    }
+    for launch in &report.launches {
+        println!("{:<24}  {}", launch.id, launch);
+    }
+
+    Ok(())
+}
+
+fn require_unit(name: &str) -> Result<SystemdUnit> {
+    find_systemd_unit(name)?.with_context(|| format!("No session systemd unit named '{}'", name))
+}
+
+/// Units are addressed by their file name, entries by desktop-file ID.
+fn is_unit_name(id: &str) -> bool {
+    id.ends_with(".service")
+}
+
+fn list(json: bool, desktop: Option<&[String]>) -> Result<()> {
+    let mut entries = discover_autostart_entries()?;
+    if let Some(desktop) = desktop {
+        for entry in &mut entries {
+            entry.effective_state = entry.compute_effective_state(desktop);
+        }
+    }
+    let units = discover_systemd_units()?;
+
+    if json {
+        println!("{}", entries_to_json(&entries, &units)?);
+        return Ok(());
+    }
+
+    let mut rows: Vec<[String; 5]> = entries
+        .iter()
+        .map(|e| {
+            [
+                e.id.clone(),
+                e.effective_state.to_string(),
+                e.origin.to_string(),
+                match e.desktop_entry.delay {
+                    Some(delay) => delay
+                        .seconds
+                        .map(|s| format!("{}s", s))
+                        .unwrap_or_else(|| "late".to_string()),
+                    None => "-".to_string(),
+                },
+                e.desktop_entry.display_name().to_string(),
+            ]
+        })
+        .collect();
+
+    rows.extend(units.iter().map(|u| {
+        [
+            u.name.clone(),
+            if u.enabled { "Enabled" } else { "Disabled" }.to_string(),
+            "systemd".to_string(),
+            "-".to_string(),
+            u.display_name().to_string(),
+        ]
+    }));
+
+    let header = ["ID", "STATE", "ORIGIN", "DELAY", "NAME"].map(String::from);
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    }
+
+    Ok(())
+}
+
+fn show(id: &str, json: bool) -> Result<()> {
+    if is_unit_name(id) {
+        return show_unit(id, json);
+    }
+
+    let entry = require_entry(id)?;
+
+    if json {
+        println!("{}", entry_to_json(&entry)?);
+        return Ok(());
+    }
+    let desktop = &entry.desktop_entry;
+
+    println!("ID:       {}", entry.id);
+    println!("Path:     {}", entry.path.display());
+    println!("Origin:   {}", entry.origin);
+    println!("State:    {}", entry.effective_state);
+    println!("Name:     {}", desktop.name);
+    if let Some(ref localized) = desktop.localized_name {
+        println!("Name[{}]: {}", localized.locale, localized.value);
+    }
+    println!("Command:  {}", desktop.exec);
+    if let Some(delay) = desktop.delay {
+        match delay.seconds {
+            Some(seconds) => println!("Delay:    {}s ({})", seconds, delay.strategy),
+            None => println!("Delay:    after the desktop ({})", delay.strategy),
+        }
+    }
+    if let Some(ref comment) = desktop.comment {
+        println!("Comment:  {}", comment);
+    }
+    if let Some(ref localized) = desktop.localized_comment {
+        println!("Comment[{}]: {}", localized.locale, localized.value);
+    }
+    if let Some(ref icon) = desktop.icon {
+        println!("Icon:     {}", icon);
+    }
+    if let Some(ref try_exec) = desktop.try_exec {
+        println!("TryExec:  {}", try_exec);
+    }
+    if desktop.terminal {
+        println!("Terminal: true");
+    }
+    if let Some(ref dir) = desktop.working_dir {
+        println!("WorkDir:  {}", dir);
+    }
+    if !desktop.environment.is_empty() {
+        let assignments: Vec<String> = desktop
+            .environment
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        println!("Env:      {}", quote_exec(&assignments));
+    }
+    if !desktop.only_show_in.is_empty() {
+        println!("OnlyShowIn: {}", desktop.only_show_in.join(";"));
+    }
+    if !desktop.not_show_in.is_empty() {
+        println!("NotShowIn:  {}", desktop.not_show_in.join(";"));
+    }
+    if let Some(ref condition) = desktop.autostart_condition {
+        println!("Condition:  {}", condition);
+    }
+    if desktop.auto_restart {
+        println!("AutoRestart: true");
+    }
+    if let Some(report) = last_session_report()
+        && let Some(launch) = report.launch(&entry.id)
+    {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        println!(
+            "Last login: {}, {}",
+            launch,
+            format_age(now.saturating_sub(report.timestamp))
+        );
+    }
+
+    Ok(())
+}
+
+fn show_unit(name: &str, json: bool) -> Result<()> {
+    let unit = require_unit(name)?;
+
+    if json {
+        println!("{}", unit_to_json(&unit)?);
+        return Ok(());
+    }
+
+    println!("Unit:     {}", unit.name);
+    println!("Path:     {}", unit.path.display());
+    println!(
+        "State:    {}",
+        if unit.enabled { "Enabled" } else { "Disabled" }
+    );
+    if let Some(ref description) = unit.description {
+        println!("Name:     {}", description);
+    }
+    if let Some(ref exec_start) = unit.exec_start {
+        println!("Command:  {}", exec_start);
+    }
+
+    Ok(())
+}
+
+fn set_enabled(id: &str, enabled: bool) -> Result<()> {
+    if is_unit_name(id) {
+        let unit = require_unit(id)?;
+        set_unit_enabled(&unit, enabled)?;
+        println!(
+            "{} {}",
+            if enabled { "Enabled" } else { "Disabled" },
+            unit.name
+        );
+        return Ok(());
+    }
+
+    let entry = require_entry(id)?;
+    set_entry_enabled(&entry, enabled)?;
+
+    println!(
+        "{} {} ({})",
+        if enabled { "Enabled" } else { "Disabled" },
+        entry.id,
+        entry.user_path().display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::desktop_entry::lint::Diagnostic;
+use crate::desktop_entry::{
+    AutostartCondition, DESKTOP_ENTRY_GROUP, DelayStrategy, DesktopDocument, EffectiveState,
+    LocalizedString,
+};
+use crate::model::{AutostartEntry, EntryOrigin, SystemdUnit};
+
+/// Version of the JSON output format. Bump it whenever a field is renamed,
+/// removed or changes meaning; adding fields is backwards compatible.
+///
+/// 2: `command` is the command without a delay or `env` wrapper, with the
+/// variables in `environment`; `desktop_entry.exec` is Exec as written in
+/// the file, wrappers included.
+pub const SCHEMA_VERSION: u32 = 2;
+
+#[derive(Serialize)]
+struct EntryListing<'a> {
+    schema_version: u32,
+    entries: Vec<EntryJson<'a>>,
+    units: &'a [SystemdUnit],
+}
+
+#[derive(Serialize)]
+struct SingleEntry<'a> {
+    schema_version: u32,
+    entry: EntryJson<'a>,
+}
+
+#[derive(Serialize)]
+struct SingleUnit<'a> {
+    schema_version: u32,
+    unit: &'a SystemdUnit,
+}
+
+#[derive(Serialize)]
+struct CheckReport<'a> {
+    schema_version: u32,
+    files: Vec<CheckedFile<'a>>,
+}
+
+#[derive(Serialize)]
+struct CheckedFile<'a> {
+    path: &'a Path,
+    diagnostics: &'a [Diagnostic],
+}
+
+#[derive(Serialize)]
+struct EntryJson<'a> {
+    id: &'a str,
+    path: &'a Path,
+    origin: EntryOrigin,
+    effective_state: EffectiveState,
+    enabled: bool,
+    command: &'a str,
+    environment: Vec<EnvironmentVariable<'a>>,
+    delay_seconds: Option<u32>,
+    delay_strategy: Option<DelayStrategy>,
+    desktop_entry: DesktopEntryJson<'a>,
+}
+
+#[derive(Serialize)]
+struct EnvironmentVariable<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+/// The keys of the file. Unlike [`EntryJson::command`], `exec` is the value
+/// as written.
+#[derive(Serialize)]
+struct DesktopEntryJson<'a> {
+    name: &'a str,
+    localized_name: Option<&'a LocalizedString>,
+    exec: String,
+    icon: Option<&'a str>,
+    comment: Option<&'a str>,
+    localized_comment: Option<&'a LocalizedString>,
+    hidden: bool,
+    terminal: bool,
+    only_show_in: &'a [String],
+    not_show_in: &'a [String],
+    try_exec: Option<&'a str>,
+    working_dir: Option<&'a str>,
+    no_display: bool,
+    categories: &'a [String],
+    keywords: &'a [String],
+    autostart_condition: Option<&'a AutostartCondition>,
+    auto_restart: bool,
+}
+
+impl<'a> EntryJson<'a> {
+    fn new(entry: &'a AutostartEntry) -> Self {
+        let desktop = &entry.desktop_entry;
+        let exec = DesktopDocument::parse(&entry.raw_content)
+            .get(DESKTOP_ENTRY_GROUP, "Exec")
+            .unwrap_or_default()
+            .to_string();
+
+        Self {
+            id: &entry.id,
+            path: &entry.path,
+            origin: entry.origin,
+            effective_state: entry.effective_state,
+            enabled: !desktop.hidden,
+            command: &desktop.exec,
+            environment: desktop
+                .environment
+                .iter()
+                .map(|(name, value)| EnvironmentVariable { name, value })
+                .collect(),
+            delay_seconds: entry.delay_seconds(),
+            delay_strategy: desktop.delay.map(|d| d.strategy),
+            desktop_entry: DesktopEntryJson {
+                name: &desktop.name,
+                localized_name: desktop.localized_name.as_ref(),
+                exec,
+                icon: desktop.icon.as_deref(),
+                comment: desktop.comment.as_deref(),
+                localized_comment: desktop.localized_comment.as_ref(),
+                hidden: desktop.hidden,
+                terminal: desktop.terminal,
+                only_show_in: &desktop.only_show_in,
+                not_show_in: &desktop.not_show_in,
+                try_exec: desktop.try_exec.as_deref(),
+                working_dir: desktop.working_dir.as_deref(),
+                no_display: desktop.no_display,
+                categories: &desktop.categories,
+                keywords: &desktop.keywords,
+                autostart_condition: desktop.autostart_condition.as_ref(),
+                auto_restart: desktop.auto_restart,
+            },
+        }
+    }
+}
+
+pub fn entries_to_json(entries: &[AutostartEntry], units: &[SystemdUnit]) -> Result<String> {
+    let listing = EntryListing {
+        schema_version: SCHEMA_VERSION,
+        entries: entries.iter().map(EntryJson::new).collect(),
+        units,
+    };
+    Ok(serde_json::to_string_pretty(&listing)?)
+}
+
+pub fn entry_to_json(entry: &AutostartEntry) -> Result<String> {
+    let single = SingleEntry {
+        schema_version: SCHEMA_VERSION,
+        entry: EntryJson::new(entry),
+    };
+    Ok(serde_json::to_string_pretty(&single)?)
+}
+
+pub fn unit_to_json(unit: &SystemdUnit) -> Result<String> {
+    let single = SingleUnit {
+        schema_version: SCHEMA_VERSION,
+        unit,
+    };
+    Ok(serde_json::to_string_pretty(&single)?)
+}
+
+pub fn check_report_to_json(files: &[(PathBuf, Vec<Diagnostic>)]) -> Result<String> {
+    let report = CheckReport {
+        schema_version: SCHEMA_VERSION,
+        files: files
+            .iter()
+            .map(|(path, diagnostics)| CheckedFile { path, diagnostics })
+            .collect(),
+    };
+    Ok(serde_json::to_string_pretty(&report)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop_entry::parser::parse_desktop_file;
+
+    #[test]
+    fn test_entry_json_schema() {
+        let content = "[Desktop Entry]\nType=Application\nName=Tray\nExec=sh -c 'sleep 5 && exec env LANG=C tray'\nHidden=true\n";
+        let entry = AutostartEntry::new(
+            "tray".to_string(),
+            PathBuf::from("/tmp/tray.desktop"),
+            parse_desktop_file(content).unwrap(),
+            content.to_string(),
+            EntryOrigin::UserOverridesSystem,
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&entries_to_json(&[entry], &[]).unwrap()).unwrap();
+
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        let entry = &json["entries"][0];
+        assert_eq!(entry["id"], "tray");
+        assert_eq!(entry["path"], "/tmp/tray.desktop");
+        assert_eq!(entry["origin"], "user_overrides_system");
+        assert_eq!(entry["effective_state"], "disabled");
+        assert_eq!(entry["enabled"], false);
+        assert_eq!(entry["command"], "tray");
+        assert_eq!(
+            entry["environment"],
+            serde_json::json!([{ "name": "LANG", "value": "C" }])
+        );
+        assert_eq!(entry["delay_seconds"], 5);
+        assert_eq!(entry["delay_strategy"], "shell_wrapper");
+        assert_eq!(entry["desktop_entry"]["name"], "Tray");
+        assert_eq!(
+            entry["desktop_entry"]["exec"],
+            "sh -c 'sleep 5 && exec env LANG=C tray'"
+        );
+        assert_eq!(entry["desktop_entry"]["hidden"], true);
+        assert_eq!(json["units"], serde_json::json!([]));
+    }
+}
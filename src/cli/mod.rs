@@ -0,0 +1,18 @@
+//! Headless command-line interface. Nothing in here may touch GTK so that
+//! onset can be scripted on machines without a display.
+
+mod args;
+mod commands;
+mod json;
+
+pub use args::{USAGE, parse_args};
+
+pub fn run(command: args::Command) -> i32 {
+    match commands::execute(command) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("onset: {:#}", e);
+            1
+        }
+    }
+}
@@ -1,14 +1,31 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use std::sync::LazyLock;
 
+use anyhow::{Context, Result};
+
+use crate::desktop_entry::writer::write_atomic;
+use crate::desktop_entry::{DelayStrategy, DesktopDocument};
+
 pub static XDG_PATHS: LazyLock<XdgPaths> = LazyLock::new(XdgPaths::new);
 
 #[derive(Debug, Clone)]
 pub struct XdgPaths {
+    /// `$XDG_CONFIG_HOME`, which `if-exists` conditions are relative to.
+    pub config_home: PathBuf,
     pub user_autostart: PathBuf,
+    pub system_autostart: Vec<PathBuf>,
     pub user_applications: PathBuf,
     pub system_applications: Vec<PathBuf>,
+    pub systemd_user: PathBuf,
+    /// Icon theme and pixmap directories, most important first.
+    pub icon_dirs: Vec<PathBuf>,
+    /// The user's trash directory, `$XDG_DATA_HOME/Trash`.
+    pub trash: PathBuf,
+    /// Onset's own configuration directory.
+    pub onset_config: PathBuf,
+    /// Onset's state directory, holding the undo journal.
+    pub onset_state: PathBuf,
 }
 
 impl XdgPaths {
@@ -21,15 +38,33 @@ impl XdgPaths {
         let data_home = xdg
             .data_home
             .unwrap_or_else(|| home_path.join(".local/share"));
+        let state_home = xdg
+            .state_home
+            .unwrap_or_else(|| home_path.join(".local/state"));
 
         Self {
             user_autostart: config_home.join("autostart"),
+            system_autostart: xdg
+                .config_dirs
+                .iter()
+                .map(|p| p.join("autostart"))
+                .collect(),
             user_applications: data_home.join("applications"),
             system_applications: xdg
                 .data_dirs
                 .iter()
                 .map(|p| p.join("applications"))
                 .collect(),
+            systemd_user: config_home.join("systemd/user"),
+            icon_dirs: std::iter::once(data_home.join("icons"))
+                .chain(std::iter::once(home_path.join(".icons")))
+                .chain(xdg.data_dirs.iter().map(|p| p.join("icons")))
+                .chain(xdg.data_dirs.iter().map(|p| p.join("pixmaps")))
+                .collect(),
+            trash: data_home.join("Trash"),
+            onset_config: config_home.join("onset"),
+            onset_state: state_home.join("onset"),
+            config_home,
         }
     }
 
@@ -46,11 +81,112 @@ impl Default for XdgPaths {
     }
 }
 
+const PREFERENCES_GROUP: &str = "Preferences";
+
+/// User preferences, stored in `settings.conf` in Onset's configuration
+/// directory using the same key file syntax as desktop files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preferences {
+    pub delay_strategy: Option<DelayStrategy>,
+    /// Profile that was applied last.
+    pub active_profile: Option<String>,
+}
+
+impl Preferences {
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&Self::path())
+    }
+
+    fn path() -> PathBuf {
+        XDG_PATHS.onset_config.join("settings.conf")
+    }
+
+    fn load_from(path: &Path) -> Self {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        let document = DesktopDocument::parse(&content);
+
+        Self {
+            delay_strategy: document
+                .get(PREFERENCES_GROUP, "DelayStrategy")
+                .and_then(DelayStrategy::from_key),
+            active_profile: document
+                .get(PREFERENCES_GROUP, "ActiveProfile")
+                .map(str::to_string),
+        }
+    }
+
+    /// Write the preferences, keeping anything else in the file intact.
+    fn save_to(&self, path: &Path) -> Result<()> {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        let mut document = DesktopDocument::parse(&content);
+
+        match self.delay_strategy {
+            Some(strategy) => document.set(PREFERENCES_GROUP, "DelayStrategy", strategy.key()),
+            None => {
+                document.remove(PREFERENCES_GROUP, "DelayStrategy");
+            }
+        }
+        match self.active_profile {
+            Some(ref profile) => document.set(PREFERENCES_GROUP, "ActiveProfile", profile),
+            None => {
+                document.remove(PREFERENCES_GROUP, "ActiveProfile");
+            }
+        }
+
+        write_atomic(path, &document.to_string())
+            .with_context(|| format!("Failed to save preferences: {}", path.display()))
+    }
+}
+
 pub fn get_current_desktop() -> Vec<String> {
-    std::env::var("XDG_CURRENT_DESKTOP")
-        .unwrap_or_default()
+    parse_desktop_list(&std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default())
+}
+
+/// Split a colon-separated desktop list as found in `XDG_CURRENT_DESKTOP`,
+/// e.g. `ubuntu:GNOME`.
+pub fn parse_desktop_list(value: &str) -> Vec<String> {
+    value
         .split(':')
         .map(|s| s.to_string())
         .filter(|s| !s.is_empty())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_desktop_list() {
+        assert_eq!(parse_desktop_list("ubuntu:GNOME"), vec!["ubuntu", "GNOME"]);
+        assert_eq!(parse_desktop_list("sway:"), vec!["sway"]);
+        assert!(parse_desktop_list("").is_empty());
+    }
+
+    #[test]
+    fn test_preferences_round_trip() {
+        let dir = std::env::temp_dir().join("onset_test_preferences");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("settings.conf");
+        std::fs::write(&path, "# mine\n[Other]\nKey=v\n").unwrap();
+
+        let preferences = Preferences {
+            delay_strategy: Some(DelayStrategy::GnomeKey),
+            active_profile: Some("work".to_string()),
+        };
+        preferences.save_to(&path).unwrap();
+        let loaded = Preferences::load_from(&path);
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(loaded, preferences);
+        assert_eq!(
+            content,
+            "# mine\n[Other]\nKey=v\n\n[Preferences]\nDelayStrategy=gnome_key\nActiveProfile=work\n"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+/// Lines of unchanged context around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Keep,
+    Remove,
+    Add,
+}
+
+/// Unified diff of two versions of a file, as `diff -u` prints it. Returns
+/// an empty string when they are equal.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    if ops.iter().all(|&op| op == Op::Keep) {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+
+    // Position in both files before each op
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut o, mut n) = (0, 0);
+    for &op in &ops {
+        positions.push((o, n));
+        match op {
+            Op::Keep => {
+                o += 1;
+                n += 1;
+            }
+            Op::Remove => o += 1,
+            Op::Add => n += 1,
+        }
+    }
+
+    let mut index = 0;
+    while let Some(offset) = ops[index..].iter().position(|&op| op != Op::Keep) {
+        let start = (index + offset).saturating_sub(CONTEXT);
+
+        // Extend the hunk while changes are close enough to share context
+        let mut end = index + offset;
+        let mut last_change = end;
+        while end < ops.len() {
+            if ops[end] != Op::Keep {
+                last_change = end;
+            } else if end - last_change > 2 * CONTEXT {
+                break;
+            }
+            end += 1;
+        }
+        let end = (last_change + 1 + CONTEXT).min(ops.len());
+
+        let (old_start, new_start) = positions[start];
+        let old_count = ops[start..end].iter().filter(|&&op| op != Op::Add).count();
+        let new_count = ops[start..end]
+            .iter()
+            .filter(|&&op| op != Op::Remove)
+            .count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+
+        for (&op, &(o, n)) in ops[start..end].iter().zip(&positions[start..end]) {
+            match op {
+                Op::Keep => out.push_str(&format!(" {}\n", old_lines[o])),
+                Op::Remove => out.push_str(&format!("-{}\n", old_lines[o])),
+                Op::Add => out.push_str(&format!("+{}\n", new_lines[n])),
+            }
+        }
+
+        index = end;
+    }
+
+    out
+}
+
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Edit script from a longest common subsequence. Desktop files are short,
+/// so the quadratic table is fine.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(Op::Keep);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] > lcs[i + 1][j]) {
+            ops.push(Op::Add);
+            j += 1;
+        } else {
+            ops.push(Op::Remove);
+            i += 1;
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "[Desktop Entry]\nType=Application\nName=App\nExec=app %U\nHidden=True\n";
+        let new = "[Desktop Entry]\nType=Application\nName=App\nExec=app\nHidden=true\n";
+
+        assert_eq!(unified_diff(old, old, "a", "b"), "");
+        assert_eq!(
+            unified_diff(old, new, "a/app.desktop", "b/app.desktop"),
+            "--- a/app.desktop\n+++ b/app.desktop\n@@ -1,5 +1,5 @@\n [Desktop Entry]\n Type=Application\n Name=App\n-Exec=app %U\n-Hidden=True\n+Exec=app\n+Hidden=true\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_separate_hunks() {
+        let old: String = (1..=20).map(|i| format!("line{}\n", i)).collect();
+        let new = old
+            .replace("line2\n", "")
+            .replace("line18\n", "line18\nextra\n");
+
+        assert_eq!(
+            unified_diff(&old, &new, "a", "b"),
+            "--- a\n+++ b\n@@ -1,5 +1,4 @@\n line1\n-line2\n line3\n line4\n line5\n@@ -16,5 +15,6 @@\n line16\n line17\n line18\n+extra\n line19\n line20\n"
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::fmt;
+
+pub const DESKTOP_ENTRY_GROUP: &str = "Desktop Entry";
+
+/// What a single line of a desktop file contains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineKind {
+    Blank,
+    Comment,
+    Group(String),
+    /// A `key=value` pair. `key` includes any locale suffix (`Name[de]`) and
+    /// `value` is still escaped exactly as it appears in the file.
+    Entry {
+        key: String,
+        value: String,
+    },
+    /// Anything that is not valid desktop entry syntax. Kept verbatim so
+    /// that editing a slightly broken file does not lose data.
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub kind: LineKind,
+    text: String,
+    eol: String,
+}
+
+impl Line {
+    fn parse(text: &str, eol: &str) -> Self {
+        let trimmed = text.trim();
+
+        let kind = if trimmed.is_empty() {
+            LineKind::Blank
+        } else if trimmed.starts_with('#') {
+            LineKind::Comment
+        } else if let Some(name) = trimmed
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            LineKind::Group(name.to_string())
+        } else if let Some((key, value)) = trimmed.split_once('=') {
+            LineKind::Entry {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            }
+        } else {
+            LineKind::Invalid
+        };
+
+        Self {
+            kind,
+            text: text.to_string(),
+            eol: eol.to_string(),
+        }
+    }
+
+    fn entry(key: &str, value: &str, eol: &str) -> Self {
+        Self {
+            kind: LineKind::Entry {
+                key: key.to_string(),
+                value: value.to_string(),
+            },
+            text: format!("{}={}", key, value),
+            eol: eol.to_string(),
+        }
+    }
+
+    fn blank(eol: &str) -> Self {
+        Self {
+            kind: LineKind::Blank,
+            text: String::new(),
+            eol: eol.to_string(),
+        }
+    }
+
+    fn group(name: &str, eol: &str) -> Self {
+        Self {
+            kind: LineKind::Group(name.to_string()),
+            text: format!("[{}]", name),
+            eol: eol.to_string(),
+        }
+    }
+}
+
+/// Lossless representation of a desktop file.
+///
+/// Every line, including comments, blank lines, unknown keys, other groups
+/// and the original line endings, is kept so that `to_string()` on an
+/// unmodified document reproduces the input byte for byte. Edits only touch
+/// the lines they change.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DesktopDocument {
+    lines: Vec<Line>,
+}
+
+impl DesktopDocument {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let lines = content
+            .split_inclusive('\n')
+            .map(|raw| {
+                let (text, eol) = if let Some(text) = raw.strip_suffix("\r\n") {
+                    (text, "\r\n")
+                } else if let Some(text) = raw.strip_suffix('\n') {
+                    (text, "\n")
+                } else {
+                    (raw, "")
+                };
+                Line::parse(text, eol)
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    /// Every line of the file, in order. Index `i` is line `i + 1`.
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// All key/value pairs of a group in file order, with values still
+    /// escaped. Keys that appear in several instances of the same group are
+    /// all returned.
+    pub fn entries<'a>(&'a self, group: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> {
+        let mut current: Option<&str> = None;
+        self.lines.iter().filter_map(move |line| match line.kind {
+            LineKind::Group(ref name) => {
+                current = Some(name.as_str());
+                None
+            }
+            LineKind::Entry { ref key, ref value } if current == Some(group) => {
+                Some((key.as_str(), value.as_str()))
+            }
+            _ => None,
+        })
+    }
+
+    /// Raw value of `key` in `group`. When a key is duplicated the last
+    /// occurrence wins, matching how the file is read by other tools.
+    pub fn get(&self, group: &str, key: &str) -> Option<&str> {
+        let index = self.key_indices(group, key).last().copied()?;
+        match self.lines[index].kind {
+            LineKind::Entry { ref value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Set the raw (already escaped) value of `key` in `group`.
+    ///
+    /// An existing line is rewritten in place, unless it already has this
+    /// value; otherwise the key is inserted after the last key of the
+    /// group, creating the group if needed.
+    pub fn set(&mut self, group: &str, key: &str, value: &str) {
+        if let Some(index) = self.key_indices(group, key).last().copied() {
+            if matches!(self.lines[index].kind, LineKind::Entry { value: ref v, .. } if v == value)
+            {
+                return;
+            }
+            let eol = self.lines[index].eol.clone();
+            self.lines[index] = Line::entry(key, value, &eol);
+            return;
+        }
+
+        let index = match self.insertion_index(group) {
+            Some(index) => index,
+            None => {
+                if !self.lines.is_empty() {
+                    self.push_line(Line::blank("\n"));
+                }
+                self.push_line(Line::group(group, "\n"));
+                self.lines.len()
+            }
+        };
+
+        self.insert_line(index, Line::entry(key, value, "\n"));
+    }
+
+    /// Remove every occurrence of `key` from `group`. Returns whether
+    /// anything was removed.
+    pub fn remove(&mut self, group: &str, key: &str) -> bool {
+        let indices = self.key_indices(group, key);
+        for &index in indices.iter().rev() {
+            let removed = self.lines.remove(index);
+            // Keep a missing final newline missing
+            if removed.eol.is_empty()
+                && index == self.lines.len()
+                && let Some(last) = self.lines.last_mut()
+            {
+                last.eol.clear();
+            }
+        }
+        !indices.is_empty()
+    }
+
+    /// Remove all but the last occurrence of every key in each group,
+    /// keeping the value that is read. Returns the removed keys.
+    pub fn remove_duplicate_keys(&mut self) -> Vec<String> {
+        let mut current: Option<usize> = None;
+        let mut last_seen: HashMap<(usize, &str), usize> = HashMap::new();
+        let mut duplicates = Vec::new();
+
+        for (index, line) in self.lines.iter().enumerate() {
+            match line.kind {
+                LineKind::Group(_) => current = Some(index),
+                LineKind::Entry { ref key, .. } => {
+                    if let Some(group) = current
+                        && let Some(earlier) = last_seen.insert((group, key), index)
+                    {
+                        duplicates.push(earlier);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        duplicates.sort_unstable();
+        let mut removed = Vec::new();
+        for index in duplicates.into_iter().rev() {
+            if let LineKind::Entry { ref key, .. } = self.lines.remove(index).kind {
+                removed.push(key.clone());
+            }
+        }
+        removed.reverse();
+        removed
+    }
+
+    fn key_indices(&self, group: &str, key: &str) -> Vec<usize> {
+        let mut current: Option<&str> = None;
+        let mut indices = Vec::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            match line.kind {
+                LineKind::Group(ref name) => current = Some(name.as_str()),
+                LineKind::Entry { key: ref k, .. } if current == Some(group) && k == key => {
+                    indices.push(index);
+                }
+                _ => {}
+            }
+        }
+        indices
+    }
+
+    /// Index right after the last key of the first instance of `group`,
+    /// so that trailing comments and blank lines stay where they are.
+    fn insertion_index(&self, group: &str) -> Option<usize> {
+        let start = self
+            .lines
+            .iter()
+            .position(|line| matches!(line.kind, LineKind::Group(ref name) if name == group))?;
+
+        let mut index = start + 1;
+        for (offset, line) in self.lines[start + 1..].iter().enumerate() {
+            match line.kind {
+                LineKind::Group(_) => break,
+                LineKind::Entry { .. } | LineKind::Invalid => index = start + 2 + offset,
+                LineKind::Blank | LineKind::Comment => {}
+            }
+        }
+        Some(index)
+    }
+
+    fn insert_line(&mut self, index: usize, mut line: Line) {
+        if index == self.lines.len() {
+            self.push_line(line);
+            return;
+        }
+        if let Some(prev) = index.checked_sub(1).map(|i| &self.lines[i]) {
+            line.eol = prev.eol.clone();
+        }
+        self.lines.insert(index, line);
+    }
+
+    /// Append a line, making sure the previous last line gets terminated.
+    fn push_line(&mut self, mut line: Line) {
+        if let Some(last) = self.lines.last_mut() {
+            if last.eol.is_empty() {
+                last.eol = "\n".to_string();
+                line.eol.clear();
+            } else {
+                line.eol = last.eol.clone();
+            }
+        }
+        self.lines.push(line);
+    }
+}
+
+impl fmt::Display for DesktopDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            f.write_str(&line.text)?;
+            f.write_str(&line.eol)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSY: &str = "# Managed by hand\r\n[Desktop Entry]\r\nType=Application\r\nName = Tray\r\nName[de]=Leiste\r\n\r\n# keep me\r\nX-GNOME-Autostart-Phase=Panel\r\nthis is not valid\r\nExec=tray\r\n\r\n[Desktop Action quit]\r\nName=Quit\r\nExec=tray --quit";
+
+    #[test]
+    fn test_round_trip_is_lossless() {
+        for content in [MESSY, "", "\n", "[Desktop Entry]\nName=x\n\n\n", "junk"] {
+            assert_eq!(DesktopDocument::parse(content).to_string(), content);
+        }
+    }
+
+    #[test]
+    fn test_get_and_entries() {
+        let doc = DesktopDocument::parse(MESSY);
+        assert_eq!(doc.get(DESKTOP_ENTRY_GROUP, "Name"), Some("Tray"));
+        assert_eq!(doc.get(DESKTOP_ENTRY_GROUP, "Name[de]"), Some("Leiste"));
+        assert_eq!(doc.get(DESKTOP_ENTRY_GROUP, "Exec"), Some("tray"));
+        assert_eq!(doc.get("Desktop Action quit", "Exec"), Some("tray --quit"));
+        assert_eq!(doc.entries(DESKTOP_ENTRY_GROUP).count(), 5);
+    }
+
+    #[test]
+    fn test_set_existing_key_only_touches_that_line() {
+        let mut doc = DesktopDocument::parse(MESSY);
+        doc.set(DESKTOP_ENTRY_GROUP, "Exec", "tray --minimized");
+        assert_eq!(
+            doc.to_string(),
+            MESSY.replace("Exec=tray\r\n", "Exec=tray --minimized\r\n")
+        );
+    }
+
+    #[test]
+    fn test_set_same_value_keeps_line_as_written() {
+        let content = "[Desktop Entry]\nName = Tray\n";
+        let mut doc = DesktopDocument::parse(content);
+        doc.set(DESKTOP_ENTRY_GROUP, "Name", "Tray");
+        assert_eq!(doc.to_string(), content);
+    }
+
+    #[test]
+    fn test_set_new_key_inserts_after_last_key_of_group() {
+        let mut doc = DesktopDocument::parse(MESSY);
+        doc.set(DESKTOP_ENTRY_GROUP, "Hidden", "true");
+        assert_eq!(
+            doc.to_string(),
+            MESSY.replace("Exec=tray\r\n", "Exec=tray\r\nHidden=true\r\n")
+        );
+    }
+
+    #[test]
+    fn test_set_without_trailing_newline() {
+        let mut doc = DesktopDocument::parse("[Desktop Entry]\nName=x");
+        doc.set(DESKTOP_ENTRY_GROUP, "Hidden", "true");
+        assert_eq!(doc.to_string(), "[Desktop Entry]\nName=x\nHidden=true");
+    }
+
+    #[test]
+    fn test_set_creates_group() {
+        let mut doc = DesktopDocument::new();
+        doc.set(DESKTOP_ENTRY_GROUP, "Type", "Application");
+        doc.set(DESKTOP_ENTRY_GROUP, "Name", "x");
+        assert_eq!(
+            doc.to_string(),
+            "[Desktop Entry]\nType=Application\nName=x\n"
+        );
+
+        doc.set("Other", "Key", "v");
+        assert_eq!(
+            doc.to_string(),
+            "[Desktop Entry]\nType=Application\nName=x\n\n[Other]\nKey=v\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_duplicate_keys() {
+        let mut doc = DesktopDocument::parse(
+            "[Desktop Entry]\nName=a\nExec=x\nName=b\n\n[Other]\nName=c\nExec=y\nExec=z\n",
+        );
+        assert_eq!(doc.remove_duplicate_keys(), vec!["Name", "Exec"]);
+        assert_eq!(
+            doc.to_string(),
+            "[Desktop Entry]\nExec=x\nName=b\n\n[Other]\nName=c\nExec=z\n"
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut doc = DesktopDocument::parse("[Desktop Entry]\nHidden=true\nName=x\nHidden=false");
+        assert!(doc.remove(DESKTOP_ENTRY_GROUP, "Hidden"));
+        assert_eq!(doc.to_string(), "[Desktop Entry]\nName=x");
+        assert!(!doc.remove(DESKTOP_ENTRY_GROUP, "Hidden"));
+    }
+}
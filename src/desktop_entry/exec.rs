@@ -0,0 +1,420 @@
+use std::fmt;
+
+/// Characters that must be quoted in an Exec value according to the
+/// Desktop Entry spec.
+const RESERVED: &[char] = &[
+    ' ', '\t', '\n', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')',
+    '`',
+];
+
+/// Characters that must be escaped with a backslash inside double quotes.
+const QUOTE_ESCAPED: &[char] = &['"', '`', '$', '\\'];
+
+pub(crate) const FILE_FIELD_CODES: &[char] = &['f', 'F', 'u', 'U'];
+pub(crate) const DEPRECATED_FIELD_CODES: &[char] = &['d', 'D', 'n', 'N', 'v', 'm'];
+const OTHER_FIELD_CODES: &[char] = &['i', 'c', 'k'];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecError {
+    Empty,
+    UnterminatedQuote { position: usize },
+    ReservedCharacter { character: char, position: usize },
+    InvalidFieldCode { code: Option<char>, position: usize },
+    TrailingBackslash,
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::Empty => write!(f, "Command is empty"),
+            ExecError::UnterminatedQuote { position } => {
+                write!(f, "Quote opened at column {} is never closed", position + 1)
+            }
+            ExecError::ReservedCharacter {
+                character,
+                position,
+            } => write!(
+                f,
+                "Reserved character '{}' at column {} must be quoted",
+                character,
+                position + 1
+            ),
+            ExecError::InvalidFieldCode {
+                code: Some(code),
+                position,
+            } => write!(
+                f,
+                "Unknown field code '%{}' at column {} (use %% for a literal %)",
+                code,
+                position + 1
+            ),
+            ExecError::InvalidFieldCode {
+                code: None,
+                position,
+            } => write!(
+                f,
+                "Incomplete field code at column {} (use %% for a literal %)",
+                position + 1
+            ),
+            ExecError::TrailingBackslash => write!(f, "Command ends with a backslash"),
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+/// Split an Exec value into its arguments following the quoting rules of
+/// the Desktop Entry spec. The value must already have the general string
+/// escapes (`\\`, `\n`, ...) removed.
+///
+/// Field codes are kept verbatim in the returned arguments (`%U`, `%%`) and
+/// only validated when they appear outside quotes. In addition to the spec,
+/// single quotes and backslash escapes outside of double quotes are
+/// accepted since GLib allows them and they are common in existing files.
+pub fn parse_exec(exec: &str) -> Result<Vec<String>, ExecError> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = exec.char_indices().peekable();
+
+    while let Some((position, c)) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        None => return Err(ExecError::UnterminatedQuote { position }),
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) if QUOTE_ESCAPED.contains(&escaped) => {
+                                current.push(escaped)
+                            }
+                            Some((_, other)) => {
+                                current.push('\\');
+                                current.push(other);
+                            }
+                            None => return Err(ExecError::UnterminatedQuote { position }),
+                        },
+                        Some((inner, reserved @ ('`' | '$'))) => {
+                            return Err(ExecError::ReservedCharacter {
+                                character: reserved,
+                                position: inner,
+                            });
+                        }
+                        Some((_, other)) => current.push(other),
+                    }
+                }
+            }
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        None => return Err(ExecError::UnterminatedQuote { position }),
+                        Some((_, '\'')) => break,
+                        Some((_, other)) => current.push(other),
+                    }
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                match chars.next() {
+                    Some((_, escaped)) => current.push(escaped),
+                    None => return Err(ExecError::TrailingBackslash),
+                }
+            }
+            '%' => {
+                in_arg = true;
+                match chars.next() {
+                    Some((_, code)) if code == '%' || is_field_code(code) => {
+                        current.push('%');
+                        current.push(code);
+                    }
+                    Some((_, code)) => {
+                        return Err(ExecError::InvalidFieldCode {
+                            code: Some(code),
+                            position,
+                        });
+                    }
+                    None => {
+                        return Err(ExecError::InvalidFieldCode {
+                            code: None,
+                            position,
+                        });
+                    }
+                }
+            }
+            reserved if RESERVED.contains(&reserved) => {
+                return Err(ExecError::ReservedCharacter {
+                    character: reserved,
+                    position,
+                });
+            }
+            other => {
+                in_arg = true;
+                current.push(other);
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+
+    if args.is_empty() {
+        return Err(ExecError::Empty);
+    }
+
+    Ok(args)
+}
+
+/// Join arguments back into an Exec value, double-quoting every argument
+/// that contains a reserved character. Field codes in the arguments are
+/// written as-is.
+pub fn quote_exec<S: AsRef<str>>(args: &[S]) -> String {
+    args.iter()
+        .map(|arg| quote_arg(arg.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(RESERVED) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    for c in arg.chars() {
+        if QUOTE_ESCAPED.contains(&c) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Split an `env NAME=value... command` wrapper into the command and the
+/// variables it sets. Wrappers that pass options to `env` are left alone.
+pub fn unwrap_env(exec: &str) -> (String, Vec<(String, String)>) {
+    let Ok(args) = parse_exec(exec) else {
+        return (exec.to_string(), Vec::new());
+    };
+    let Some((program, rest)) = args.split_first() else {
+        return (exec.to_string(), Vec::new());
+    };
+    if program != "env" && !program.ends_with("/env") {
+        return (exec.to_string(), Vec::new());
+    }
+
+    let environment: Vec<(String, String)> = rest
+        .iter()
+        .map_while(|arg| {
+            let (name, value) = arg.split_once('=')?;
+            is_env_name(name).then(|| (name.to_string(), value.replace("%%", "%")))
+        })
+        .collect();
+    let command = &rest[environment.len()..];
+    if environment.is_empty() || command.is_empty() {
+        return (exec.to_string(), Vec::new());
+    }
+
+    (quote_exec(command), environment)
+}
+
+/// Prefix an Exec value with `env` setting `environment`, the inverse of
+/// [`unwrap_env`].
+pub fn wrap_with_env(exec: &str, environment: &[(String, String)]) -> String {
+    if environment.is_empty() {
+        return exec.to_string();
+    }
+
+    let mut args = vec!["env".to_string()];
+    args.extend(
+        environment
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value.replace('%', "%%"))),
+    );
+    format!("{} {}", quote_exec(&args), exec)
+}
+
+/// Whether `name` can be set by `env`: letters, digits and underscores, not
+/// starting with a digit.
+pub fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn is_field_code(code: char) -> bool {
+    FILE_FIELD_CODES.contains(&code)
+        || DEPRECATED_FIELD_CODES.contains(&code)
+        || OTHER_FIELD_CODES.contains(&code)
+}
+
+/// Remove all field codes from parsed arguments. These are placeholders for
+/// files, URLs and launcher metadata that are meaningless for autostart.
+/// Literal `%%` is kept.
+pub fn remove_field_codes(args: Vec<String>) -> Vec<String> {
+    args.into_iter()
+        .filter_map(|arg| {
+            let stripped = strip_arg_field_codes(&arg);
+            if stripped.is_empty() && !arg.is_empty() {
+                None
+            } else {
+                Some(stripped)
+            }
+        })
+        .collect()
+}
+
+fn strip_arg_field_codes(arg: &str) -> String {
+    let mut result = String::with_capacity(arg.len());
+    let mut chars = arg.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some(code) if is_field_code(code) => {}
+            Some(other) => {
+                result.push('%');
+                result.push(other);
+            }
+            None => result.push('%'),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &[&str]) -> Vec<String> {
+        s.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_simple() {
+        assert_eq!(
+            parse_exec("firefox  --new-window %U").unwrap(),
+            args(&["firefox", "--new-window", "%U"])
+        );
+    }
+
+    #[test]
+    fn test_parse_double_quotes() {
+        assert_eq!(
+            parse_exec(r#"sh -c "echo \"hi\" \$HOME \\ \`x\`""#).unwrap(),
+            args(&["sh", "-c", r#"echo "hi" $HOME \ `x`"#])
+        );
+        assert_eq!(parse_exec(r#"app """#).unwrap(), args(&["app", ""]));
+        assert_eq!(
+            parse_exec(r#"app --title="My App""#).unwrap(),
+            args(&["app", "--title=My App"])
+        );
+    }
+
+    #[test]
+    fn test_parse_glib_extensions() {
+        assert_eq!(
+            parse_exec(r"sh -c 'sleep 5 && exec app --name='\''test'\'''").unwrap(),
+            args(&["sh", "-c", "sleep 5 && exec app --name='test'"])
+        );
+        assert_eq!(parse_exec(r"my\ app").unwrap(), args(&["my app"]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse_exec("  "), Err(ExecError::Empty));
+        assert_eq!(
+            parse_exec(r#"app "open"#),
+            Err(ExecError::UnterminatedQuote { position: 4 })
+        );
+        assert_eq!(
+            parse_exec("app > log"),
+            Err(ExecError::ReservedCharacter {
+                character: '>',
+                position: 4
+            })
+        );
+        assert_eq!(
+            parse_exec(r#"sh -c "echo $HOME""#),
+            Err(ExecError::ReservedCharacter {
+                character: '$',
+                position: 12
+            })
+        );
+        assert_eq!(
+            parse_exec("app %x"),
+            Err(ExecError::InvalidFieldCode {
+                code: Some('x'),
+                position: 4
+            })
+        );
+        assert_eq!(
+            parse_exec("app 100%"),
+            Err(ExecError::InvalidFieldCode {
+                code: None,
+                position: 7
+            })
+        );
+        assert_eq!(parse_exec(r"app \"), Err(ExecError::TrailingBackslash));
+    }
+
+    #[test]
+    fn test_quote_round_trip() {
+        let original = args(&["sh", "-c", r#"echo "$1" `date` \ ; ~"#, "", "plain", "%%"]);
+        let quoted = quote_exec(&original);
+        assert_eq!(
+            quoted,
+            r#"sh -c "echo \"\$1\" \`date\` \\ ; ~" "" plain %%"#
+        );
+        assert_eq!(parse_exec(&quoted).unwrap(), original);
+    }
+
+    #[test]
+    fn test_remove_field_codes() {
+        assert_eq!(
+            remove_field_codes(args(&["app", "%U", "--file=%f", "%%", "%i"])),
+            args(&["app", "--file=", "%%"])
+        );
+    }
+
+    #[test]
+    fn test_env_wrapper_round_trip() {
+        let environment = vec![
+            ("QT_QPA_PLATFORM".to_string(), "wayland".to_string()),
+            ("LABEL".to_string(), "50% done".to_string()),
+        ];
+        let wrapped = wrap_with_env("app --flag", &environment);
+
+        assert_eq!(
+            wrapped,
+            r#"env QT_QPA_PLATFORM=wayland "LABEL=50%% done" app --flag"#
+        );
+        assert_eq!(
+            unwrap_env(&wrapped),
+            ("app --flag".to_string(), environment)
+        );
+        assert_eq!(
+            unwrap_env("/usr/bin/env -i PATH=/bin app"),
+            ("/usr/bin/env -i PATH=/bin app".to_string(), Vec::new())
+        );
+        assert_eq!(unwrap_env("env A=1").1, Vec::new());
+        assert!(!is_env_name("1A"));
+    }
+}
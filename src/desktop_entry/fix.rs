@@ -0,0 +1,217 @@
+use std::fmt;
+
+use super::document::{DESKTOP_ENTRY_GROUP, DesktopDocument};
+use super::exec::{parse_exec, quote_exec, remove_field_codes, unwrap_env, wrap_with_env};
+use super::lint::BOOLEAN_KEYS;
+use super::parser::{escape_value, unescape_value};
+use crate::operations::delay::{unwrap_delay, wrap_with_delay};
+use crate::utils::find_binary;
+
+/// A mechanical repair for a problem reported by the linter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fix {
+    /// Keep the last value of a duplicated key, the one that is read.
+    DuplicateKeys,
+    MissingType,
+    BooleanCase,
+    FieldCodes,
+    /// Rewrite `sh -c 'sleep N && exec ...'` from older versions in the
+    /// current quoting.
+    LegacyDelay,
+    /// Replace a program name looked up in `$PATH` with its absolute path.
+    AbsoluteExec,
+}
+
+impl Fix {
+    /// Fixes that never change what is run. [`Fix::AbsoluteExec`] is left
+    /// out: it ties the entry to the current install location.
+    pub const DEFAULT: [Fix; 5] = [
+        Fix::DuplicateKeys,
+        Fix::MissingType,
+        Fix::BooleanCase,
+        Fix::FieldCodes,
+        Fix::LegacyDelay,
+    ];
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fix::DuplicateKeys => write!(f, "Remove duplicate keys"),
+            Fix::MissingType => write!(f, "Add Type=Application"),
+            Fix::BooleanCase => write!(f, "Lowercase true and false"),
+            Fix::FieldCodes => write!(f, "Strip field codes from Exec"),
+            Fix::LegacyDelay => write!(f, "Rewrite the delay wrapper"),
+            Fix::AbsoluteExec => write!(f, "Use the absolute path of the program"),
+        }
+    }
+}
+
+/// Apply `fixes` to a desktop file. Returns the new content and the fixes
+/// that changed something, in the order they were applied. Everything the
+/// fixes do not touch is kept byte for byte.
+pub fn fix_content(content: &str, fixes: &[Fix]) -> (String, Vec<Fix>) {
+    let mut document = DesktopDocument::parse(content);
+    let mut applied = Vec::new();
+
+    if fixes.contains(&Fix::DuplicateKeys) && !document.remove_duplicate_keys().is_empty() {
+        applied.push(Fix::DuplicateKeys);
+    }
+
+    if fixes.contains(&Fix::MissingType) && document.get(DESKTOP_ENTRY_GROUP, "Type").is_none() {
+        document.set(DESKTOP_ENTRY_GROUP, "Type", "Application");
+        applied.push(Fix::MissingType);
+    }
+
+    if fixes.contains(&Fix::BooleanCase) {
+        let mut changed = false;
+        for key in BOOLEAN_KEYS {
+            let Some(value) = document.get(DESKTOP_ENTRY_GROUP, key) else {
+                continue;
+            };
+            let lower = value.to_ascii_lowercase();
+            if lower != value && (lower == "true" || lower == "false") {
+                document.set(DESKTOP_ENTRY_GROUP, key, &lower);
+                changed = true;
+            }
+        }
+        if changed {
+            applied.push(Fix::BooleanCase);
+        }
+    }
+
+    if let Some(exec) = document.get(DESKTOP_ENTRY_GROUP, "Exec") {
+        let original = unescape_value(exec);
+        let (exec, exec_fixes) = fix_exec(&original, fixes);
+        if exec != original {
+            document.set(DESKTOP_ENTRY_GROUP, "Exec", &escape_value(&exec));
+            applied.extend(exec_fixes);
+        }
+    }
+
+    (document.to_string(), applied)
+}
+
+/// The Exec fixes work on the command inside a delay and `env` wrapper,
+/// which are then written in the current form.
+fn fix_exec(exec: &str, fixes: &[Fix]) -> (String, Vec<Fix>) {
+    let mut applied = Vec::new();
+
+    let (command, delay) = unwrap_delay(exec);
+    let (program_command, environment) = unwrap_env(&command);
+    let Ok(mut args) = parse_exec(&program_command) else {
+        return (exec.to_string(), applied);
+    };
+
+    if fixes.contains(&Fix::FieldCodes) {
+        let stripped = remove_field_codes(args.clone());
+        if stripped != args && !stripped.is_empty() {
+            args = stripped;
+            applied.push(Fix::FieldCodes);
+        }
+    }
+
+    if fixes.contains(&Fix::AbsoluteExec)
+        && let Some(program) = args.first_mut()
+        && !program.contains('/')
+        && let Some(path) = find_binary(program)
+    {
+        *program = path.display().to_string();
+        applied.push(Fix::AbsoluteExec);
+    }
+
+    if let Some(seconds) = delay
+        && fixes.contains(&Fix::LegacyDelay)
+        && wrap_with_delay(&command, seconds) != exec
+    {
+        applied.push(Fix::LegacyDelay);
+    }
+
+    if applied.is_empty() {
+        return (exec.to_string(), applied);
+    }
+
+    let command = if applied == [Fix::LegacyDelay] {
+        command
+    } else {
+        wrap_with_env(&quote_exec(&args), &environment)
+    };
+    let fixed = match delay {
+        Some(seconds) => wrap_with_delay(&command, seconds),
+        None => command,
+    };
+
+    (fixed, applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fix_content() {
+        let content = "# keep\n[Desktop Entry]\nName=Old\nName=App\nExec=app --open %U\nHidden=False\nTerminal=yes\n";
+        let (fixed, applied) = fix_content(content, &Fix::DEFAULT);
+
+        assert_eq!(
+            fixed,
+            "# keep\n[Desktop Entry]\nName=App\nExec=app --open\nHidden=false\nTerminal=yes\nType=Application\n"
+        );
+        assert_eq!(
+            applied,
+            vec![
+                Fix::DuplicateKeys,
+                Fix::MissingType,
+                Fix::BooleanCase,
+                Fix::FieldCodes
+            ]
+        );
+
+        let (again, applied) = fix_content(&fixed, &Fix::DEFAULT);
+        assert_eq!(again, fixed);
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn test_fix_delay_wrapper() {
+        let legacy = "sh -c 'sleep 5 && exec app %u'";
+
+        assert_eq!(
+            fix_exec(legacy, &[Fix::LegacyDelay]),
+            (
+                r#"sh -c "sleep 5 && exec app %u""#.to_string(),
+                vec![Fix::LegacyDelay]
+            )
+        );
+        assert_eq!(
+            fix_exec(legacy, &Fix::DEFAULT),
+            (
+                r#"sh -c "sleep 5 && exec app""#.to_string(),
+                vec![Fix::FieldCodes, Fix::LegacyDelay]
+            )
+        );
+        assert_eq!(
+            fix_exec(r#"sh -c "sleep 5 && exec app""#, &Fix::DEFAULT).1,
+            vec![]
+        );
+        // A changed command is always wrapped in the current form
+        assert_eq!(
+            fix_exec(legacy, &[Fix::FieldCodes]),
+            (
+                r#"sh -c "sleep 5 && exec app""#.to_string(),
+                vec![Fix::FieldCodes]
+            )
+        );
+    }
+
+    #[test]
+    fn test_absolute_exec() {
+        let (fixed, applied) = fix_exec("sh -c true", &[Fix::AbsoluteExec]);
+        assert_eq!(applied, vec![Fix::AbsoluteExec]);
+        assert!(fixed.starts_with('/') && fixed.ends_with("/sh -c true"));
+        assert_eq!(
+            fix_exec("no-such-program-here", &[Fix::AbsoluteExec]).1,
+            vec![]
+        );
+    }
+}
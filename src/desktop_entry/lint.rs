@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::Serialize;
+
+use super::document::{DESKTOP_ENTRY_GROUP, DesktopDocument, LineKind};
+use super::exec::{DEPRECATED_FIELD_CODES, FILE_FIELD_CODES, parse_exec};
+use super::locale::Locale;
+use super::parser::unescape_value;
+use crate::operations::delay::unwrap_delay;
+use crate::utils::{binary_exists, icon_exists};
+
+/// `Key` or `Key[locale]`, see "Basic format of the file" in the spec.
+static KEY_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z0-9-]+)(?:\[([^\]]*)\])?$").unwrap());
+
+pub(crate) const BOOLEAN_KEYS: &[&str] = &[
+    "Hidden",
+    "NoDisplay",
+    "Terminal",
+    "StartupNotify",
+    "DBusActivatable",
+    "PrefersNonDefaultGPU",
+    "SingleMainWindow",
+    "X-GNOME-Autostart-enabled",
+    "X-GNOME-AutoRestart",
+];
+
+/// Keys of type `localestring` or `iconstring` that may carry a locale.
+const LOCALIZABLE_KEYS: &[&str] = &["Name", "GenericName", "Comment", "Keywords", "Icon"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Onset and most sessions will not start the entry as intended.
+    Error,
+    /// Works here but violates the spec or is likely a mistake.
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// What a diagnostic is about, so that fixes and scripts can match on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Check {
+    Syntax,
+    MissingGroup,
+    MissingType,
+    UnsupportedType,
+    MissingKey,
+    InvalidKey,
+    DuplicateKey,
+    InvalidLocale,
+    InvalidBoolean,
+    InvalidExec,
+    FieldCode,
+    MissingProgram,
+    MissingIcon,
+    ShowInConflict,
+}
+
+impl Check {
+    pub fn name(self) -> &'static str {
+        match self {
+            Check::Syntax => "syntax",
+            Check::MissingGroup => "missing-group",
+            Check::MissingType => "missing-type",
+            Check::UnsupportedType => "unsupported-type",
+            Check::MissingKey => "missing-key",
+            Check::InvalidKey => "invalid-key",
+            Check::DuplicateKey => "duplicate-key",
+            Check::InvalidLocale => "invalid-locale",
+            Check::InvalidBoolean => "invalid-boolean",
+            Check::InvalidExec => "invalid-exec",
+            Check::FieldCode => "field-code",
+            Check::MissingProgram => "missing-program",
+            Check::MissingIcon => "missing-icon",
+            Check::ShowInConflict => "show-in-conflict",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub check: Check,
+    /// 1-based line the problem is on, `None` for problems with the file
+    /// as a whole such as a missing key.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "{}: ", line)?;
+        }
+        write!(
+            f,
+            "{}[{}]: {}",
+            self.severity,
+            self.check.name(),
+            self.message
+        )
+    }
+}
+
+/// Check a desktop file against the Desktop Entry spec and for problems
+/// that keep it from being autostarted, such as a program that is not
+/// installed. Diagnostics are ordered by line.
+pub fn lint_desktop_file(content: &str) -> Vec<Diagnostic> {
+    lint_with(content, binary_exists, icon_exists)
+}
+
+fn lint_with(
+    content: &str,
+    program_exists: impl Fn(&str) -> bool,
+    icon_found: impl Fn(&str) -> bool,
+) -> Vec<Diagnostic> {
+    let document = DesktopDocument::parse(content);
+    let mut linter = Linter::default();
+
+    linter.check_structure(&document);
+
+    let main_group = linter.main_group.clone();
+    let line_of = |key: &str| main_group.get(key).map(|&(line, _)| line);
+    let value_of = |key: &str| main_group.get(key).map(|(_, value)| value.as_str());
+
+    match value_of("Type") {
+        None => linter.push(
+            Severity::Error,
+            Check::MissingType,
+            None,
+            "Missing required key Type".to_string(),
+        ),
+        Some("Application") => {}
+        Some(other) => linter.push(
+            Severity::Error,
+            Check::UnsupportedType,
+            line_of("Type"),
+            format!("Type={} cannot be autostarted, only Application", other),
+        ),
+    }
+
+    for key in ["Name", "Exec"] {
+        if value_of(key).is_none_or(str::is_empty) {
+            linter.push(
+                Severity::Error,
+                Check::MissingKey,
+                line_of(key),
+                format!("Missing required key {}", key),
+            );
+        }
+    }
+
+    for key in BOOLEAN_KEYS {
+        let Some(value) = value_of(key) else {
+            continue;
+        };
+        if value == "true" || value == "false" {
+            continue;
+        }
+        let (severity, hint) =
+            if value.eq_ignore_ascii_case("true") || value.eq_ignore_ascii_case("false") {
+                (Severity::Warning, "booleans are lowercase")
+            } else {
+                (Severity::Error, "must be true or false")
+            };
+        linter.push(
+            severity,
+            Check::InvalidBoolean,
+            line_of(key),
+            format!("Invalid value '{}' for {} ({})", value, key, hint),
+        );
+    }
+
+    if let Some(exec) = value_of("Exec").filter(|e| !e.is_empty()) {
+        linter.check_exec(exec, line_of("Exec"), &program_exists);
+    }
+
+    if let Some(icon) = value_of("Icon").filter(|i| !i.is_empty())
+        && !icon_found(icon)
+    {
+        linter.push(
+            Severity::Warning,
+            Check::MissingIcon,
+            line_of("Icon"),
+            format!("Icon '{}' is not installed", icon),
+        );
+    }
+
+    let list = |key: &str| -> Vec<&str> {
+        value_of(key)
+            .map(|v| v.split(';').filter(|d| !d.is_empty()).collect())
+            .unwrap_or_default()
+    };
+    let only = list("OnlyShowIn");
+    let not = list("NotShowIn");
+    if !only.is_empty() && !not.is_empty() {
+        let both: Vec<&str> = only.iter().filter(|d| not.contains(d)).copied().collect();
+        let (severity, message) = if both.is_empty() {
+            (
+                Severity::Warning,
+                "OnlyShowIn and NotShowIn should not both be set".to_string(),
+            )
+        } else {
+            (
+                Severity::Error,
+                format!("{} is in both OnlyShowIn and NotShowIn", both.join(", ")),
+            )
+        };
+        linter.push(
+            severity,
+            Check::ShowInConflict,
+            line_of("NotShowIn"),
+            message,
+        );
+    }
+
+    linter.diagnostics.sort_by_key(|d| d.line);
+    linter.diagnostics
+}
+
+#[derive(Default)]
+struct Linter {
+    diagnostics: Vec<Diagnostic>,
+    /// Last line and value of each key in `[Desktop Entry]`.
+    main_group: HashMap<String, (usize, String)>,
+}
+
+impl Linter {
+    fn push(&mut self, severity: Severity, check: Check, line: Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            check,
+            line,
+            message,
+        });
+    }
+
+    /// Line syntax, groups, key names, locales and duplicates.
+    fn check_structure(&mut self, document: &DesktopDocument) {
+        let mut group: Option<&str> = None;
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        let mut groups: Vec<&str> = Vec::new();
+
+        for (index, line) in document.lines().iter().enumerate() {
+            let number = Some(index + 1);
+            match line.kind {
+                LineKind::Blank | LineKind::Comment => {}
+                LineKind::Invalid => self.push(
+                    Severity::Error,
+                    Check::Syntax,
+                    number,
+                    "Line is not a group header, key or comment".to_string(),
+                ),
+                LineKind::Group(ref name) => {
+                    if groups.contains(&name.as_str()) {
+                        self.push(
+                            Severity::Error,
+                            Check::DuplicateKey,
+                            number,
+                            format!("Group [{}] appears more than once", name),
+                        );
+                    }
+                    groups.push(name);
+                    group = Some(name);
+                    seen.clear();
+                }
+                LineKind::Entry { ref key, ref value } => {
+                    let Some(current) = group else {
+                        self.push(
+                            Severity::Error,
+                            Check::Syntax,
+                            number,
+                            format!("Key {} comes before any group header", key),
+                        );
+                        continue;
+                    };
+
+                    if let Some(first) = seen.insert(key, index + 1) {
+                        self.push(
+                            Severity::Error,
+                            Check::DuplicateKey,
+                            number,
+                            format!("Duplicate key {} (first set on line {})", key, first),
+                        );
+                    }
+
+                    self.check_key(key, number);
+
+                    if current == DESKTOP_ENTRY_GROUP {
+                        self.main_group
+                            .insert(key.clone(), (index + 1, value.clone()));
+                    }
+                }
+            }
+        }
+
+        if groups.first() != Some(&DESKTOP_ENTRY_GROUP) {
+            let message = if groups.contains(&DESKTOP_ENTRY_GROUP) {
+                "[Desktop Entry] must be the first group"
+            } else {
+                "Missing [Desktop Entry] group"
+            };
+            self.push(
+                Severity::Error,
+                Check::MissingGroup,
+                None,
+                message.to_string(),
+            );
+        }
+    }
+
+    fn check_key(&mut self, key: &str, line: Option<usize>) {
+        let Some(caps) = KEY_PATTERN.captures(key) else {
+            self.push(
+                Severity::Error,
+                Check::InvalidKey,
+                line,
+                format!("Invalid key name '{}'", key),
+            );
+            return;
+        };
+
+        let Some(locale) = caps.get(2).map(|m| m.as_str()) else {
+            return;
+        };
+        let base = &caps[1];
+
+        if Locale::parse(locale).is_none() {
+            self.push(
+                Severity::Error,
+                Check::InvalidLocale,
+                line,
+                format!("Invalid locale '{}' in {}", locale, key),
+            );
+        } else if !LOCALIZABLE_KEYS.contains(&base) && !base.starts_with("X-") {
+            self.push(
+                Severity::Warning,
+                Check::InvalidLocale,
+                line,
+                format!("{} cannot be translated", base),
+            );
+        }
+    }
+
+    fn check_exec(
+        &mut self,
+        exec: &str,
+        line: Option<usize>,
+        program_exists: &impl Fn(&str) -> bool,
+    ) {
+        let exec = unescape_value(exec);
+        if let Err(e) = parse_exec(&exec) {
+            self.push(Severity::Error, Check::InvalidExec, line, e.to_string());
+            return;
+        }
+
+        // Look at the command inside a delay wrapper; it is what gets run
+        let (command, _) = unwrap_delay(&exec);
+        let Ok(args) = parse_exec(&command) else {
+            self.push(
+                Severity::Error,
+                Check::InvalidExec,
+                line,
+                "The delayed command cannot be parsed".to_string(),
+            );
+            return;
+        };
+
+        for code in args.iter().flat_map(|arg| field_codes(arg)) {
+            let reason = if FILE_FIELD_CODES.contains(&code) {
+                "autostart passes no files or URLs"
+            } else if DEPRECATED_FIELD_CODES.contains(&code) {
+                "it is deprecated"
+            } else {
+                continue;
+            };
+            self.push(
+                Severity::Warning,
+                Check::FieldCode,
+                line,
+                format!("Field code %{} has no effect: {}", code, reason),
+            );
+        }
+
+        // `env VAR=value program` runs `program`
+        let mut programs = args.iter().map(String::as_str);
+        let mut program = programs.next();
+        if program == Some("env") {
+            program = programs.find(|arg| !arg.contains('=') && !arg.starts_with('-'));
+        }
+
+        if let Some(program) = program
+            && !program.starts_with('%')
+            && !program_exists(program)
+        {
+            self.push(
+                Severity::Warning,
+                Check::MissingProgram,
+                line,
+                format!("Program '{}' was not found", program),
+            );
+        }
+    }
+}
+
+/// Field codes used in an argument, ignoring literal `%%`.
+fn field_codes(arg: &str) -> Vec<char> {
+    let mut codes = Vec::new();
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        if c == '%'
+            && let Some(code) = chars.next()
+            && code != '%'
+        {
+            codes.push(code);
+        }
+    }
+    codes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(content: &str) -> Vec<(Severity, Check, Option<usize>)> {
+        lint_with(content, |program| program == "app", |icon| icon == "app")
+            .into_iter()
+            .map(|d| (d.severity, d.check, d.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_entry_has_no_diagnostics() {
+        let content = "[Desktop Entry]\nType=Application\nName=App\nName[de_DE@euro]=Anw\nExec=app --flag %%\nIcon=app\nHidden=false\n\n[Desktop Action new]\nName=New\n";
+        assert_eq!(lint(content), vec![]);
+    }
+
+    #[test]
+    fn test_structure_problems() {
+        let content = "Stray=1\n[Desktop Entry]\nType=Application\nName=App\nExec=app\nnot a key\nName=Again\nName[x-y]=Bad\nExec[de]=app\nBad Key=1\n";
+        assert_eq!(
+            lint(content),
+            vec![
+                (Severity::Error, Check::Syntax, Some(1)),
+                (Severity::Error, Check::Syntax, Some(6)),
+                (Severity::Error, Check::DuplicateKey, Some(7)),
+                (Severity::Error, Check::InvalidLocale, Some(8)),
+                (Severity::Warning, Check::InvalidLocale, Some(9)),
+                (Severity::Error, Check::InvalidKey, Some(10)),
+            ]
+        );
+        assert_eq!(
+            lint("[Other]\nKey=v\n"),
+            vec![
+                (Severity::Error, Check::MissingGroup, None),
+                (Severity::Error, Check::MissingType, None),
+                (Severity::Error, Check::MissingKey, None),
+                (Severity::Error, Check::MissingKey, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_value_problems() {
+        let content = "[Desktop Entry]\nType=Link\nName=App\nExec=gone %U\nIcon=gone\nHidden=True\nTerminal=yes\nOnlyShowIn=GNOME;KDE;\nNotShowIn=KDE;\n";
+        assert_eq!(
+            lint(content),
+            vec![
+                (Severity::Error, Check::UnsupportedType, Some(2)),
+                (Severity::Warning, Check::FieldCode, Some(4)),
+                (Severity::Warning, Check::MissingProgram, Some(4)),
+                (Severity::Warning, Check::MissingIcon, Some(5)),
+                (Severity::Warning, Check::InvalidBoolean, Some(6)),
+                (Severity::Error, Check::InvalidBoolean, Some(7)),
+                (Severity::Error, Check::ShowInConflict, Some(9)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_exec_problems() {
+        let base = "[Desktop Entry]\nType=Application\nName=App\n";
+        let diagnostics = lint_with(&format!("{}Exec=app %x\n", base), |_| true, |_| true);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].check, Check::InvalidExec);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "4: error[invalid-exec]: Unknown field code '%x' at column 5 (use %% for a literal %)"
+        );
+
+        // The wrapped program is checked, not the shell
+        assert_eq!(
+            lint(&format!("{}Exec=sh -c \"sleep 5 && exec gone %u\"\n", base)),
+            vec![
+                (Severity::Warning, Check::FieldCode, Some(4)),
+                (Severity::Warning, Check::MissingProgram, Some(4)),
+            ]
+        );
+        assert_eq!(lint(&format!("{}Exec=env A=1 app\n", base)), vec![]);
+    }
+}
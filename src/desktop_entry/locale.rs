@@ -0,0 +1,138 @@
+use std::fmt;
+use std::sync::LazyLock;
+
+static CURRENT_LOCALE: LazyLock<Option<Locale>> = LazyLock::new(Locale::from_env);
+
+/// The locale used for messages, as seen by the Desktop Entry spec:
+/// `lang_COUNTRY.ENCODING@MODIFIER` with everything but `lang` optional.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    pub lang: String,
+    pub country: Option<String>,
+    pub modifier: Option<String>,
+}
+
+impl Locale {
+    /// Parse a POSIX locale name. The encoding is ignored and the `C` and
+    /// `POSIX` locales yield `None` since they have no translations, as do
+    /// malformed names.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (rest, modifier) = match value.split_once('@') {
+            Some((rest, modifier)) => (rest, Some(modifier.to_string())),
+            None => (value, None),
+        };
+        let rest = rest.split('.').next().unwrap_or_default();
+        let (lang, country) = match rest.split_once('_') {
+            Some((lang, country)) => (lang, Some(country.to_string())),
+            None => (rest, None),
+        };
+
+        if lang.is_empty() || lang == "C" || lang == "POSIX" {
+            return None;
+        }
+        let alphanumeric = |part: &Option<String>| {
+            part.as_deref()
+                .is_none_or(|p| p.chars().all(|c| c.is_ascii_alphanumeric()))
+        };
+        if !lang.chars().all(|c| c.is_ascii_lowercase())
+            || !alphanumeric(&country)
+            || !alphanumeric(&modifier)
+        {
+            return None;
+        }
+
+        Some(Self {
+            lang: lang.to_string(),
+            country: country.filter(|c| !c.is_empty()),
+            modifier: modifier.filter(|m| !m.is_empty()),
+        })
+    }
+
+    /// Read the message locale from `LC_ALL`, `LC_MESSAGES` or `LANG`, in
+    /// the same order of precedence as the C library.
+    pub fn from_env() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::parse(&value))
+    }
+
+    /// Locale suffixes to try when looking up a localized key, from most to
+    /// least specific, as described by the Desktop Entry spec.
+    pub fn candidates(&self) -> Vec<String> {
+        let mut candidates = Vec::with_capacity(4);
+        if let (Some(country), Some(modifier)) = (&self.country, &self.modifier) {
+            candidates.push(format!("{}_{}@{}", self.lang, country, modifier));
+        }
+        if let Some(country) = &self.country {
+            candidates.push(format!("{}_{}", self.lang, country));
+        }
+        if let Some(modifier) = &self.modifier {
+            candidates.push(format!("{}@{}", self.lang, modifier));
+        }
+        candidates.push(self.lang.clone());
+        candidates
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.lang)?;
+        if let Some(ref country) = self.country {
+            write!(f, "_{}", country)?;
+        }
+        if let Some(ref modifier) = self.modifier {
+            write!(f, "@{}", modifier)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn current_locale() -> Option<&'static Locale> {
+    CURRENT_LOCALE.as_ref()
+}
+
+/// Build the key for a localized value, e.g. `Name[de_DE]`.
+pub fn localized_key(key: &str, locale: &str) -> String {
+    format!("{}[{}]", key, locale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!(
+            Locale::parse("sr_RS.UTF-8@latin"),
+            Some(Locale {
+                lang: "sr".to_string(),
+                country: Some("RS".to_string()),
+                modifier: Some("latin".to_string()),
+            })
+        );
+        assert_eq!(Locale::parse("de").unwrap().country, None);
+        assert_eq!(Locale::parse("C.UTF-8"), None);
+        assert_eq!(Locale::parse("POSIX"), None);
+        assert_eq!(Locale::parse(""), None);
+        assert_eq!(Locale::parse("x-y"), None);
+        assert_eq!(Locale::parse("de_D E"), None);
+    }
+
+    #[test]
+    fn test_candidates_follow_spec_order() {
+        assert_eq!(
+            Locale::parse("sr_RS@latin").unwrap().candidates(),
+            vec!["sr_RS@latin", "sr_RS", "sr@latin", "sr"]
+        );
+        assert_eq!(
+            Locale::parse("de_DE.UTF-8").unwrap().candidates(),
+            vec!["de_DE", "de"]
+        );
+        assert_eq!(
+            Locale::parse("ca@valencia").unwrap().candidates(),
+            vec!["ca@valencia", "ca"]
+        );
+    }
+}
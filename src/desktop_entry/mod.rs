@@ -1,5 +1,13 @@
+mod diff;
+mod document;
+pub mod exec;
+pub mod fix;
+pub mod lint;
+pub mod locale;
 pub mod parser;
 mod types;
 pub mod writer;
 
+pub use diff::unified_diff;
+pub use document::{DESKTOP_ENTRY_GROUP, DesktopDocument};
 pub use types::*;
@@ -1,125 +1,114 @@
 use anyhow::Result;
 
-use super::types::DesktopEntry;
-use crate::operations::delay::{unwrap_delay, wrap_with_delay};
+use super::document::{DESKTOP_ENTRY_GROUP, DesktopDocument};
+use super::exec::unwrap_env;
+use super::locale::{Locale, current_locale, localized_key};
+use super::types::{AutostartCondition, DesktopEntry, LocalizedString};
+use crate::operations::delay::{detect_delay, unwrap_delay};
 
 pub fn parse_desktop_file(content: &str) -> Result<DesktopEntry> {
-    let mut entry = DesktopEntry::default();
-    let mut in_desktop_entry = false;
-
-    for line in content.lines() {
-        let line = line.trim();
+    Ok(desktop_entry_from_document(&DesktopDocument::parse(
+        content,
+    )))
+}
 
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
+/// Build the typed view of the `[Desktop Entry]` group. Keys that are not
+/// modelled by [`DesktopEntry`] stay in the document untouched.
+pub fn desktop_entry_from_document(document: &DesktopDocument) -> DesktopEntry {
+    desktop_entry_for_locale(document, current_locale())
+}
 
-        if line.starts_with('[') {
-            in_desktop_entry = line == "[Desktop Entry]";
-            continue;
-        }
+pub fn desktop_entry_for_locale(
+    document: &DesktopDocument,
+    locale: Option<&Locale>,
+) -> DesktopEntry {
+    let mut entry = DesktopEntry::default();
+    let mut wrapper_delay = None;
 
-        if !in_desktop_entry {
-            continue;
-        }
+    if let Some(locale) = locale {
+        entry.localized_name = lookup_localized(document, "Name", locale);
+        entry.localized_comment = lookup_localized(document, "Comment", locale);
+    }
 
-        if let Some((key, value)) = line.split_once('=') {
-            let key = key.trim();
-            let value = value.trim();
-
-            match key {
-                "Name" => entry.name = unescape_value(value),
-                "Exec" => {
-                    let (base, delay) = unwrap_delay(value);
-                    let stripped = super::writer::strip_field_codes(&base);
-                    entry.exec = match delay {
-                        Some(d) if d > 0 => wrap_with_delay(&stripped, d),
-                        _ => stripped,
-                    };
-                }
-                "Icon" => entry.icon = Some(value.to_string()),
-                "Comment" => entry.comment = Some(unescape_value(value)),
-                "Hidden" => entry.hidden = value.eq_ignore_ascii_case("true"),
-                "Terminal" => entry.terminal = value.eq_ignore_ascii_case("true"),
-                "NoDisplay" => entry.no_display = value.eq_ignore_ascii_case("true"),
-                "TryExec" => entry.try_exec = Some(value.to_string()),
-                "OnlyShowIn" => {
-                    entry.only_show_in = value
-                        .split(';')
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
-                        .collect();
-                }
-                "NotShowIn" => {
-                    entry.not_show_in = value
-                        .split(';')
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
-                        .collect();
-                }
-                "Categories" => {
-                    entry.categories = value
-                        .split(';')
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
-                        .collect();
-                }
-                "Keywords" => {
-                    entry.keywords = value
-                        .split(';')
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
-                        .collect();
-                }
-                _ => {}
+    for (key, value) in document.entries(DESKTOP_ENTRY_GROUP) {
+        match key {
+            "Name" => entry.name = unescape_value(value),
+            "Exec" => {
+                let (base, delay) = unwrap_delay(&unescape_value(value));
+                let (base, environment) = unwrap_env(&base);
+                entry.exec = super::writer::strip_field_codes(&base);
+                entry.environment = environment;
+                wrapper_delay = delay;
+            }
+            "Icon" => entry.icon = Some(value.to_string()),
+            "Comment" => entry.comment = Some(unescape_value(value)),
+            "Hidden" => entry.hidden = value.eq_ignore_ascii_case("true"),
+            "Terminal" => entry.terminal = value.eq_ignore_ascii_case("true"),
+            "NoDisplay" => entry.no_display = value.eq_ignore_ascii_case("true"),
+            "TryExec" => entry.try_exec = Some(value.to_string()),
+            "Path" if !value.trim().is_empty() => entry.working_dir = Some(unescape_value(value)),
+            "OnlyShowIn" => entry.only_show_in = parse_list(value),
+            "NotShowIn" => entry.not_show_in = parse_list(value),
+            "Categories" => entry.categories = parse_list(value),
+            "Keywords" => entry.keywords = parse_list(value),
+            "AutostartCondition" if !value.trim().is_empty() => {
+                entry.autostart_condition = Some(AutostartCondition::parse(value));
             }
+            "X-GNOME-AutoRestart" => entry.auto_restart = value.eq_ignore_ascii_case("true"),
+            _ => {}
         }
     }
 
-    Ok(entry)
+    entry.delay = detect_delay(document, wrapper_delay);
+
+    entry
 }
 
-pub fn is_valid_desktop_entry(content: &str) -> bool {
-    let mut has_type = false;
-    let mut type_is_application = false;
-    let mut has_name = false;
-    let mut has_exec = false;
-    let mut in_desktop_entry = false;
-
-    for line in content.lines() {
-        let line = line.trim();
-
-        if line == "[Desktop Entry]" {
-            in_desktop_entry = true;
-            continue;
-        }
+/// Find the best matching translation of `key` for `locale`.
+fn lookup_localized(
+    document: &DesktopDocument,
+    key: &str,
+    locale: &Locale,
+) -> Option<LocalizedString> {
+    locale.candidates().into_iter().find_map(|candidate| {
+        document
+            .get(DESKTOP_ENTRY_GROUP, &localized_key(key, &candidate))
+            .map(|value| LocalizedString {
+                locale: candidate,
+                value: unescape_value(value),
+            })
+    })
+}
 
-        if line.starts_with('[') {
-            in_desktop_entry = false;
-            continue;
-        }
+pub fn is_valid_desktop_entry(content: &str) -> bool {
+    let document = DesktopDocument::parse(content);
+    let non_empty = |key| {
+        document
+            .get(DESKTOP_ENTRY_GROUP, key)
+            .is_some_and(|v| !v.is_empty())
+    };
 
-        if !in_desktop_entry {
-            continue;
-        }
+    document.get(DESKTOP_ENTRY_GROUP, "Type") == Some("Application")
+        && non_empty("Name")
+        && non_empty("Exec")
+}
 
-        if let Some((key, value)) = line.split_once('=') {
-            match key.trim() {
-                "Type" => {
-                    has_type = true;
-                    type_is_application = value.trim() == "Application";
-                }
-                "Name" => has_name = !value.trim().is_empty(),
-                "Exec" => has_exec = !value.trim().is_empty(),
-                _ => {}
-            }
-        }
-    }
+/// A file that only sets `Hidden=true`, the spec's way of disabling the
+/// entry of the same ID in a less important directory.
+pub fn is_hidden_stub(content: &str) -> bool {
+    !is_valid_desktop_entry(content)
+        && DesktopDocument::parse(content).get(DESKTOP_ENTRY_GROUP, "Hidden") == Some("true")
+}
 
-    has_type && type_is_application && has_name && has_exec
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
 }
 
-fn unescape_value(value: &str) -> String {
+pub fn unescape_value(value: &str) -> String {
     value
         .replace("\\n", "\n")
         .replace("\\t", "\t")
@@ -138,6 +127,7 @@ pub fn escape_value(value: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::desktop_entry::{Delay, DelayStrategy};
 
     #[test]
     fn test_parse_basic_entry() {
@@ -147,12 +137,14 @@ Name=Test App
 Exec=/usr/bin/test
 Icon=test-icon
 Comment=A test application
+Path=/opt/test
 "#;
         let entry = parse_desktop_file(content).unwrap();
         assert_eq!(entry.name, "Test App");
         assert_eq!(entry.exec, "/usr/bin/test");
         assert_eq!(entry.icon, Some("test-icon".to_string()));
         assert_eq!(entry.comment, Some("A test application".to_string()));
+        assert_eq!(entry.working_dir.as_deref(), Some("/opt/test"));
     }
 
     #[test]
@@ -179,6 +171,77 @@ OnlyShowIn=GNOME;Unity;
         assert_eq!(entry.only_show_in, vec!["GNOME", "Unity"]);
     }
 
+    #[test]
+    fn test_parse_autostart_condition() {
+        let content = "[Desktop Entry]\nType=Application\nName=Orca\nExec=orca\nAutostartCondition=GSettings org.gnome.desktop.a11y.applications screen-reader-enabled\nX-GNOME-AutoRestart=true\n";
+        let entry = parse_desktop_file(content).unwrap();
+
+        assert_eq!(
+            entry.autostart_condition,
+            Some(AutostartCondition::GSettings {
+                schema: "org.gnome.desktop.a11y.applications".to_string(),
+                key: "screen-reader-enabled".to_string(),
+            })
+        );
+        assert!(entry.auto_restart);
+        assert_eq!(
+            AutostartCondition::parse("GNOME3 if-session gnome"),
+            AutostartCondition::Other("GNOME3 if-session gnome".to_string())
+        );
+        assert_eq!(
+            AutostartCondition::parse("unless-exists app/disabled").to_string(),
+            "unless-exists app/disabled"
+        );
+    }
+
+    #[test]
+    fn test_parse_autostart_condition_keywords_ignore_case() {
+        assert_eq!(
+            AutostartCondition::parse("If-Exists app/on"),
+            AutostartCondition::IfExists("app/on".to_string())
+        );
+        assert_eq!(
+            AutostartCondition::parse("UNLESS-EXISTS app/off"),
+            AutostartCondition::UnlessExists("app/off".to_string())
+        );
+        assert_eq!(
+            AutostartCondition::parse("gsettings org.example key"),
+            AutostartCondition::GSettings {
+                schema: "org.example".to_string(),
+                key: "key".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_autostart_condition_file_with_spaces() {
+        let condition = AutostartCondition::parse("if-exists  My App/enabled flag");
+
+        assert_eq!(
+            condition,
+            AutostartCondition::IfExists("My App/enabled flag".to_string())
+        );
+        assert_eq!(AutostartCondition::parse(&condition.to_string()), condition);
+    }
+
+    #[test]
+    fn test_autostart_condition_is_met() {
+        let dir = std::env::temp_dir().join("onset_test_condition");
+        std::fs::create_dir_all(dir.join("app")).unwrap();
+        std::fs::write(dir.join("app/enabled"), "").unwrap();
+
+        let met = |condition: &str| AutostartCondition::parse(condition).is_met(&dir);
+        let results = [
+            met("if-exists app/enabled"),
+            met("if-exists app/missing"),
+            met("unless-exists app/enabled"),
+            met("GSettings org.example key"),
+        ];
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(results, [Some(true), Some(false), Some(false), None]);
+    }
+
     #[test]
     fn test_parse_strips_field_codes() {
         let content = r#"[Desktop Entry]
@@ -198,7 +261,50 @@ Name=ZapZap
 Exec=sh -c 'sleep 5 && exec zapzap %u'
 "#;
         let entry = parse_desktop_file(content).unwrap();
-        assert_eq!(entry.exec, "sh -c 'sleep 5 && exec zapzap'");
+        assert_eq!(entry.exec, "zapzap");
+        assert_eq!(entry.delay, Delay::new(DelayStrategy::ShellWrapper, 5));
+    }
+
+    #[test]
+    fn test_parse_exec_unescapes_and_requotes() {
+        let content = r#"[Desktop Entry]
+Type=Application
+Name=Notes
+Exec=notes --dir='My Notes' --title="\\$USER" %F
+"#;
+        let entry = parse_desktop_file(content).unwrap();
+        assert_eq!(entry.exec, r#"notes "--dir=My Notes" "--title=\$USER""#);
+    }
+
+    #[test]
+    fn test_parse_localized_name_and_comment() {
+        let content = r#"[Desktop Entry]
+Type=Application
+Name=Files
+Name[de]=Dateien
+Name[de_CH]=Dateie
+Comment=Browse files
+Comment[de]=Dateien durchsuchen
+Exec=nautilus
+"#;
+        let document = DesktopDocument::parse(content);
+
+        let swiss = Locale::parse("de_CH.UTF-8").unwrap();
+        let entry = desktop_entry_for_locale(&document, Some(&swiss));
+        assert_eq!(entry.name, "Files");
+        assert_eq!(entry.display_name(), "Dateie");
+        assert_eq!(entry.localized_name.as_ref().unwrap().locale, "de_CH");
+        assert_eq!(entry.display_comment(), Some("Dateien durchsuchen"));
+
+        let austrian = Locale::parse("de_AT").unwrap();
+        let entry = desktop_entry_for_locale(&document, Some(&austrian));
+        assert_eq!(entry.display_name(), "Dateien");
+
+        let french = Locale::parse("fr_FR").unwrap();
+        let entry = desktop_entry_for_locale(&document, Some(&french));
+        assert!(entry.localized_name.is_none());
+        assert_eq!(entry.display_name(), "Files");
+        assert_eq!(entry.display_comment(), Some("Browse files"));
     }
 
     #[test]
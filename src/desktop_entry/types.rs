@@ -1,25 +1,249 @@
+use std::fmt;
+use std::path::Path;
+
+use serde::Serialize;
+
+/// A translated value together with the locale suffix of the key it was
+/// read from, e.g. `de` for `Name[de]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LocalizedString {
+    pub locale: String,
+    pub value: String,
+}
+
+/// `XDG_CURRENT_DESKTOP` values offered for `OnlyShowIn` and `NotShowIn`:
+/// the names registered in the menu specification, followed by common
+/// compositors that set their own.
+pub const KNOWN_DESKTOPS: &[&str] = &[
+    "GNOME",
+    "GNOME-Classic",
+    "GNOME-Flashback",
+    "KDE",
+    "XFCE",
+    "LXDE",
+    "LXQt",
+    "MATE",
+    "Cinnamon",
+    "Budgie",
+    "Pantheon",
+    "Unity",
+    "DDE",
+    "Enlightenment",
+    "COSMIC",
+    "Hyprland",
+    "sway",
+    "niri",
+    "wlroots",
+];
+
 #[derive(Debug, Clone, Default)]
 pub struct DesktopEntry {
     pub name: String,
+    pub localized_name: Option<LocalizedString>,
+    /// The command without any delay or `env` wrapper; see `delay` and
+    /// `environment`.
     pub exec: String,
+    pub delay: Option<Delay>,
+    /// Variables set by an `env NAME=value` prefix of Exec.
+    pub environment: Vec<(String, String)>,
     pub icon: Option<String>,
     pub comment: Option<String>,
+    pub localized_comment: Option<LocalizedString>,
     pub hidden: bool,
     pub terminal: bool,
     pub only_show_in: Vec<String>,
     pub not_show_in: Vec<String>,
     pub try_exec: Option<String>,
+    /// `Path`: the directory the program is started in.
+    pub working_dir: Option<String>,
     pub no_display: bool,
     pub categories: Vec<String>,
     pub keywords: Vec<String>,
+    pub autostart_condition: Option<AutostartCondition>,
+    /// `X-GNOME-AutoRestart`: GNOME restarts the program when it exits.
+    pub auto_restart: bool,
+}
+
+impl DesktopEntry {
+    /// Name in the user's language, falling back to the untranslated one.
+    pub fn display_name(&self) -> &str {
+        self.localized_name
+            .as_ref()
+            .map(|l| l.value.as_str())
+            .unwrap_or(&self.name)
+    }
+
+    pub fn display_comment(&self) -> Option<&str> {
+        self.localized_comment
+            .as_ref()
+            .map(|l| l.value.as_str())
+            .or(self.comment.as_deref())
+    }
+}
+
+/// How the start of an entry is postponed after login.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DelayStrategy {
+    /// `sh -c "sleep N && exec ..."` around the command. Works everywhere,
+    /// but hides the real command from `TryExec` and session managers.
+    #[default]
+    ShellWrapper,
+    /// `X-GNOME-Autostart-Delay=N`, honoured by gnome-session.
+    GnomeKey,
+    /// `X-KDE-autostart-phase`/`X-KDE-autostart-after`. These only order
+    /// the start within the Plasma session and cannot express seconds.
+    KdePhase,
+    /// An `ExecStartPre=sleep N` drop-in for the unit systemd generates
+    /// from the entry.
+    Systemd,
+}
+
+impl DelayStrategy {
+    pub const ALL: [DelayStrategy; 4] = [
+        DelayStrategy::ShellWrapper,
+        DelayStrategy::GnomeKey,
+        DelayStrategy::KdePhase,
+        DelayStrategy::Systemd,
+    ];
+
+    /// Name used in the settings file, on the command line and in JSON.
+    pub fn key(self) -> &'static str {
+        match self {
+            DelayStrategy::ShellWrapper => "shell_wrapper",
+            DelayStrategy::GnomeKey => "gnome_key",
+            DelayStrategy::KdePhase => "kde_phase",
+            DelayStrategy::Systemd => "systemd",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.key() == key)
+    }
+
+    pub fn supports_seconds(self) -> bool {
+        self != DelayStrategy::KdePhase
+    }
+}
+
+impl std::fmt::Display for DelayStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DelayStrategy::ShellWrapper => write!(f, "Shell Wrapper"),
+            DelayStrategy::GnomeKey => write!(f, "GNOME Delay Key"),
+            DelayStrategy::KdePhase => write!(f, "KDE Startup Phase"),
+            DelayStrategy::Systemd => write!(f, "systemd Drop-in"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delay {
+    pub strategy: DelayStrategy,
+    /// `None` for strategies that cannot express a duration.
+    pub seconds: Option<u32>,
+}
+
+impl Delay {
+    /// A delay of `seconds` using `strategy`; zero seconds means no delay.
+    pub fn new(strategy: DelayStrategy, seconds: u32) -> Option<Self> {
+        (seconds > 0).then(|| Self {
+            strategy,
+            seconds: strategy.supports_seconds().then_some(seconds),
+        })
+    }
+}
+
+/// `AutostartCondition`, checked by GNOME's session manager before it
+/// starts an entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutostartCondition {
+    /// Start only if the file, relative to `$XDG_CONFIG_HOME`, exists.
+    IfExists(String),
+    UnlessExists(String),
+    /// Start only if a boolean GSettings key is true.
+    GSettings {
+        schema: String,
+        key: String,
+    },
+    /// Conditions such as `GNOME3 if-session gnome`, kept as written.
+    Other(String),
+}
+
+impl AutostartCondition {
+    /// Keywords are matched case-insensitively, as gnome-session does. A
+    /// file name is everything after the keyword, so it may contain spaces.
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim();
+        let (keyword, argument) = value
+            .split_once(char::is_whitespace)
+            .map_or((value, ""), |(keyword, argument)| {
+                (keyword, argument.trim())
+            });
+        let words: Vec<&str> = argument.split_whitespace().collect();
+
+        if argument.is_empty() {
+            AutostartCondition::Other(value.to_string())
+        } else if keyword.eq_ignore_ascii_case("if-exists") {
+            AutostartCondition::IfExists(argument.to_string())
+        } else if keyword.eq_ignore_ascii_case("unless-exists") {
+            AutostartCondition::UnlessExists(argument.to_string())
+        } else if keyword.eq_ignore_ascii_case("GSettings")
+            && let [schema, key] = words.as_slice()
+        {
+            AutostartCondition::GSettings {
+                schema: schema.to_string(),
+                key: key.to_string(),
+            }
+        } else {
+            AutostartCondition::Other(value.to_string())
+        }
+    }
+
+    /// Whether the entry would be started, or `None` when that is only
+    /// decided at login.
+    pub fn is_met(&self, config_home: &Path) -> Option<bool> {
+        match self {
+            AutostartCondition::IfExists(file) => Some(config_home.join(file).exists()),
+            AutostartCondition::UnlessExists(file) => Some(!config_home.join(file).exists()),
+            AutostartCondition::GSettings { .. } | AutostartCondition::Other(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for AutostartCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutostartCondition::IfExists(file) => write!(f, "if-exists {}", file),
+            AutostartCondition::UnlessExists(file) => write!(f, "unless-exists {}", file),
+            AutostartCondition::GSettings { schema, key } => {
+                write!(f, "GSettings {} {}", schema, key)
+            }
+            AutostartCondition::Other(condition) => write!(f, "{}", condition),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum EffectiveState {
     Enabled,
     Disabled,
     EnvironmentExcluded,
     TryExecFailed,
+    /// An `if-exists` or `unless-exists` condition does not hold.
+    ConditionUnmet,
+    /// Enabled, but a condition that is only evaluated at login decides.
+    Conditional,
+}
+
+impl EffectiveState {
+    /// Whether the entry is expected to start, perhaps depending on a
+    /// condition.
+    pub fn may_start(self) -> bool {
+        matches!(self, EffectiveState::Enabled | EffectiveState::Conditional)
+    }
 }
 
 impl std::fmt::Display for EffectiveState {
@@ -29,6 +253,8 @@ impl std::fmt::Display for EffectiveState {
             EffectiveState::Disabled => write!(f, "Disabled"),
             EffectiveState::EnvironmentExcluded => write!(f, "Environment Excluded"),
             EffectiveState::TryExecFailed => write!(f, "TryExec Failed"),
+            EffectiveState::ConditionUnmet => write!(f, "Condition Unmet"),
+            EffectiveState::Conditional => write!(f, "Conditional"),
         }
     }
 }
@@ -37,22 +263,44 @@ impl std::fmt::Display for EffectiveState {
 pub struct CreateOptions {
     pub icon: Option<String>,
     pub comment: Option<String>,
+    pub localized_name: Option<LocalizedString>,
+    pub localized_comment: Option<LocalizedString>,
     pub delay_seconds: u32,
+    /// `None` uses the preferred strategy.
+    pub delay_strategy: Option<DelayStrategy>,
     pub terminal: bool,
+    pub working_dir: Option<String>,
+    pub environment: Vec<(String, String)>,
     pub only_show_in: Vec<String>,
     pub not_show_in: Vec<String>,
     pub hidden: bool,
+    pub autostart_condition: Option<AutostartCondition>,
+    pub auto_restart: bool,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct EntryChanges {
+    /// When set, `name` and `comment` are written to the keys localized for
+    /// this locale (e.g. `Name[de]`) instead of the untranslated ones.
+    pub locale: Option<String>,
     pub name: Option<String>,
     pub exec: Option<String>,
     pub comment: Option<String>,
+    /// An empty icon removes it.
     pub icon: Option<String>,
     pub delay_seconds: Option<u32>,
+    /// Moves an existing delay to another strategy, or selects the strategy
+    /// for a newly added one.
+    pub delay_strategy: Option<DelayStrategy>,
     pub hidden: Option<bool>,
     pub terminal: Option<bool>,
+    /// `Some(None)` removes the working directory.
+    pub working_dir: Option<Option<String>>,
+    /// Replaces all variables; an empty list removes the `env` wrapper.
+    pub environment: Option<Vec<(String, String)>>,
     pub only_show_in: Option<Vec<String>>,
     pub not_show_in: Option<Vec<String>>,
+    /// `Some(None)` removes the condition.
+    pub autostart_condition: Option<Option<AutostartCondition>>,
+    pub auto_restart: Option<bool>,
 }
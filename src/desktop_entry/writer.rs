@@ -2,23 +2,80 @@ use std::fs;
 use std::io::Write;
 use std::path::Path;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use std::sync::LazyLock;
 
 use regex::Regex;
 
-use super::parser::escape_value;
-use super::types::{CreateOptions, DesktopEntry};
-use crate::operations::delay::{unwrap_delay, wrap_with_delay};
+use super::document::{DESKTOP_ENTRY_GROUP, DesktopDocument};
+use super::exec::{
+    is_env_name, parse_exec, quote_exec, remove_field_codes, unwrap_env, wrap_with_env,
+};
+use super::locale::localized_key;
+use super::parser::{escape_value, unescape_value};
+use super::types::{CreateOptions, Delay, DelayStrategy, DesktopEntry};
+use crate::operations::delay::{apply_delay, detect_delay, unwrap_delay};
 
 /// Strip XDG desktop entry field codes (%u, %U, %f, %F, %i, %c, %k) from an
 /// Exec line.  These are placeholders for file/URL arguments that are
 /// meaningless in an autostart context where no file or URL is being opened.
+///
+/// Valid commands are re-quoted per the spec; commands that do not parse
+/// are only stripped textually so that broken files still display sensibly.
 pub(crate) fn strip_field_codes(exec: &str) -> String {
     static FIELD_CODE_PATTERN: LazyLock<Regex> =
         LazyLock::new(|| Regex::new(r"%[uUfFick]").unwrap());
-    let stripped = FIELD_CODE_PATTERN.replace_all(exec, "");
-    stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+
+    match parse_exec(exec) {
+        Ok(args) => quote_exec(&remove_field_codes(args)),
+        Err(_) => {
+            let stripped = FIELD_CODE_PATTERN.replace_all(exec, "");
+            stripped.split_whitespace().collect::<Vec<_>>().join(" ")
+        }
+    }
+}
+
+/// Validate a user supplied command and normalise it for writing: field
+/// codes are removed and arguments re-quoted.
+pub fn normalize_exec(exec: &str) -> Result<String> {
+    let args = remove_field_codes(parse_exec(exec).context("Invalid command")?);
+    if args.is_empty() {
+        bail!("Invalid command: it only consists of field codes");
+    }
+    Ok(quote_exec(&args))
+}
+
+/// Reject desktops that are both included and excluded, which the spec
+/// leaves undefined.
+pub fn validate_show_in(only_show_in: &[String], not_show_in: &[String]) -> Result<()> {
+    let both: Vec<&str> = only_show_in
+        .iter()
+        .filter(|d| not_show_in.contains(d))
+        .map(String::as_str)
+        .collect();
+    if !both.is_empty() {
+        bail!(
+            "{} cannot be in both OnlyShowIn and NotShowIn",
+            both.join(", ")
+        );
+    }
+    Ok(())
+}
+
+pub fn validate_environment(environment: &[(String, String)]) -> Result<()> {
+    if let Some((name, _)) = environment.iter().find(|(name, _)| !is_env_name(name)) {
+        bail!("Invalid environment variable name: {:?}", name);
+    }
+    Ok(())
+}
+
+/// `Path` is not expanded by the session, so `~` or a relative directory
+/// would make the entry fail to start.
+pub fn validate_working_dir(dir: &str) -> Result<()> {
+    if !dir.starts_with('/') {
+        bail!("The working directory must be an absolute path: {}", dir);
+    }
+    Ok(())
 }
 
 pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
@@ -55,134 +112,191 @@ pub fn write_desktop_entry(
     exec: &str,
     options: &CreateOptions,
 ) -> Result<()> {
-    let mut content = String::new();
-    content.push_str("[Desktop Entry]\n");
-    content.push_str("Type=Application\n");
-    content.push_str(&format!("Name={}\n", escape_value(name)));
-
-    let exec = strip_field_codes(exec);
-    let final_exec = if options.delay_seconds > 0 {
-        wrap_with_delay(&exec, options.delay_seconds)
-    } else {
-        exec
-    };
-    content.push_str(&format!("Exec={}\n", final_exec));
+    let mut document = DesktopDocument::new();
+    let mut set = |key: &str, value: &str| document.set(DESKTOP_ENTRY_GROUP, key, value);
+
+    set("Type", "Application");
+    set("Name", &escape_value(name));
+    if let Some(ref localized) = options.localized_name {
+        set(
+            &localized_key("Name", &localized.locale),
+            &escape_value(&localized.value),
+        );
+    }
+
+    let delay = Delay::new(
+        options.delay_strategy.unwrap_or_default(),
+        options.delay_seconds,
+    );
+    let exec = wrap_with_env(&normalize_exec(exec)?, &options.environment);
+    apply_delay(&mut document, &exec, delay);
+
+    let mut set = |key: &str, value: &str| document.set(DESKTOP_ENTRY_GROUP, key, value);
 
     if let Some(ref icon) = options.icon {
-        content.push_str(&format!("Icon={}\n", icon));
+        set("Icon", icon);
     }
     if let Some(ref comment) = options.comment {
-        content.push_str(&format!("Comment={}\n", escape_value(comment)));
+        set("Comment", &escape_value(comment));
+    }
+    if let Some(ref localized) = options.localized_comment {
+        set(
+            &localized_key("Comment", &localized.locale),
+            &escape_value(&localized.value),
+        );
     }
     if options.terminal {
-        content.push_str("Terminal=true\n");
+        set("Terminal", "true");
+    }
+    if let Some(ref dir) = options.working_dir {
+        set("Path", &escape_value(dir));
     }
     if !options.only_show_in.is_empty() {
-        content.push_str(&format!("OnlyShowIn={};\n", options.only_show_in.join(";")));
+        set("OnlyShowIn", &format_list(&options.only_show_in));
     }
     if !options.not_show_in.is_empty() {
-        content.push_str(&format!("NotShowIn={};\n", options.not_show_in.join(";")));
+        set("NotShowIn", &format_list(&options.not_show_in));
     }
     if options.hidden {
-        content.push_str("Hidden=true\n");
+        set("Hidden", "true");
+    }
+    if let Some(ref condition) = options.autostart_condition {
+        set("AutostartCondition", &condition.to_string());
+    }
+    if options.auto_restart {
+        set("X-GNOME-AutoRestart", "true");
     }
 
-    write_atomic(path, &content)
+    write_atomic(path, &document.to_string())
 }
 
-pub fn update_desktop_entry_content(
-    content: &str,
-    entry: &DesktopEntry,
-    delay_seconds: Option<u32>,
-) -> String {
-    let mut lines: Vec<String> = Vec::new();
-    let mut in_desktop_entry = false;
-    let mut keys_written: std::collections::HashSet<&str> = std::collections::HashSet::new();
+/// Apply the typed fields of `entry` to an existing file, leaving every
+/// other key, comment and group exactly as it was.
+pub fn update_desktop_entry_content(content: &str, entry: &DesktopEntry) -> String {
+    let mut document = DesktopDocument::parse(content);
+    update_desktop_document(&mut document, entry);
+    document.to_string()
+}
 
+pub fn update_desktop_document(document: &mut DesktopDocument, entry: &DesktopEntry) {
     let (base_exec, _) = unwrap_delay(&entry.exec);
-    let base_exec = strip_field_codes(&base_exec);
-    let final_exec = match delay_seconds {
-        Some(d) if d > 0 => wrap_with_delay(&base_exec, d),
-        _ => base_exec,
-    };
 
-    for line in content.lines() {
-        let trimmed = line.trim();
+    document.set(DESKTOP_ENTRY_GROUP, "Name", &escape_value(&entry.name));
+    if let Some(ref localized) = entry.localized_name {
+        document.set(
+            DESKTOP_ENTRY_GROUP,
+            &localized_key("Name", &localized.locale),
+            &escape_value(&localized.value),
+        );
+    }
+    if !exec_unchanged(document, &base_exec, &entry.environment, entry.delay) {
+        apply_delay(
+            document,
+            &wrap_with_env(&strip_field_codes(&base_exec), &entry.environment),
+            entry.delay,
+        );
+    }
 
-        if trimmed == "[Desktop Entry]" {
-            in_desktop_entry = true;
-            lines.push(line.to_string());
-            continue;
-        }
+    set_or_remove(document, "Icon", entry.icon.as_deref().map(String::from));
+    set_or_remove(
+        document,
+        "Comment",
+        entry
+            .comment
+            .as_deref()
+            .filter(|c| !c.is_empty())
+            .map(escape_value),
+    );
+    if let Some(ref localized) = entry.localized_comment {
+        set_or_remove(
+            document,
+            &localized_key("Comment", &localized.locale),
+            Some(&localized.value)
+                .filter(|c| !c.is_empty())
+                .map(|c| escape_value(c)),
+        );
+    }
+    set_or_remove(document, "Hidden", entry.hidden.then(|| "true".to_string()));
+    set_or_remove(
+        document,
+        "Terminal",
+        entry.terminal.then(|| "true".to_string()),
+    );
+    set_or_remove(
+        document,
+        "Path",
+        entry
+            .working_dir
+            .as_deref()
+            .filter(|dir| !dir.is_empty())
+            .map(escape_value),
+    );
+    set_or_remove(
+        document,
+        "OnlyShowIn",
+        (!entry.only_show_in.is_empty()).then(|| format_list(&entry.only_show_in)),
+    );
+    set_or_remove(
+        document,
+        "NotShowIn",
+        (!entry.not_show_in.is_empty()).then(|| format_list(&entry.not_show_in)),
+    );
+    set_or_remove(
+        document,
+        "AutostartCondition",
+        entry.autostart_condition.as_ref().map(ToString::to_string),
+    );
+    set_or_remove(
+        document,
+        "X-GNOME-AutoRestart",
+        entry.auto_restart.then(|| "true".to_string()),
+    );
+}
 
-        if trimmed.starts_with('[') {
-            in_desktop_entry = false;
-            lines.push(line.to_string());
-            continue;
-        }
+/// Whether Exec in `document` already runs `exec` with `environment` after
+/// `delay`, however it is quoted, so that it can be left as written.
+fn exec_unchanged(
+    document: &DesktopDocument,
+    exec: &str,
+    environment: &[(String, String)],
+    delay: Option<Delay>,
+) -> bool {
+    let Some(value) = document.get(DESKTOP_ENTRY_GROUP, "Exec") else {
+        return false;
+    };
+    let (current_exec, wrapper_seconds) = unwrap_delay(&unescape_value(value));
+    let (current_exec, current_environment) = unwrap_env(&current_exec);
 
-        if !in_desktop_entry {
-            lines.push(line.to_string());
-            continue;
-        }
+    // A systemd delay lives in a drop-in, not in the file
+    let delay = delay.filter(|d| d.strategy != DelayStrategy::Systemd);
 
-        if let Some((key, _)) = trimmed.split_once('=') {
-            let key = key.trim();
-            match key {
-                "Name" => {
-                    lines.push(format!("Name={}", escape_value(&entry.name)));
-                    keys_written.insert("Name");
-                }
-                "Exec" => {
-                    lines.push(format!("Exec={}", final_exec));
-                    keys_written.insert("Exec");
-                }
-                "Icon" => {
-                    if let Some(ref icon) = entry.icon {
-                        lines.push(format!("Icon={}", icon));
-                    }
-                    keys_written.insert("Icon");
-                }
-                "Comment" => {
-                    if let Some(ref comment) = entry.comment {
-                        lines.push(format!("Comment={}", escape_value(comment)));
-                    }
-                    keys_written.insert("Comment");
-                }
-                "Hidden" => {
-                    if entry.hidden {
-                        lines.push("Hidden=true".to_string());
-                    }
-                    keys_written.insert("Hidden");
-                }
-                "Terminal" => {
-                    if entry.terminal {
-                        lines.push("Terminal=true".to_string());
-                    }
-                    keys_written.insert("Terminal");
-                }
-                "OnlyShowIn" => {
-                    if !entry.only_show_in.is_empty() {
-                        lines.push(format!("OnlyShowIn={};", entry.only_show_in.join(";")));
-                    }
-                    keys_written.insert("OnlyShowIn");
-                }
-                "NotShowIn" => {
-                    if !entry.not_show_in.is_empty() {
-                        lines.push(format!("NotShowIn={};", entry.not_show_in.join(";")));
-                    }
-                    keys_written.insert("NotShowIn");
-                }
-                _ => {
-                    lines.push(line.to_string());
-                }
-            }
-        } else {
-            lines.push(line.to_string());
+    current_environment == environment
+        && detect_delay(document, wrapper_seconds) == delay
+        && matches!(
+            (parse_exec(&current_exec), parse_exec(exec)),
+            (Ok(current), Ok(new)) if current == new
+        )
+}
+
+fn set_or_remove(document: &mut DesktopDocument, key: &str, value: Option<String>) {
+    match value {
+        Some(value) => document.set(DESKTOP_ENTRY_GROUP, key, &value),
+        None => {
+            document.remove(DESKTOP_ENTRY_GROUP, key);
         }
     }
+}
+
+fn format_list(values: &[String]) -> String {
+    format!("{};", values.join(";"))
+}
 
-    lines.join("\n") + "\n"
+/// Derive a desktop-file ID from a human readable entry name.
+pub fn id_from_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect()
 }
 
 pub fn sanitize_id(id: &str) -> String {
@@ -222,6 +336,21 @@ pub fn sanitize_id(id: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::desktop_entry::parser::parse_desktop_file;
+    use crate::desktop_entry::{AutostartCondition, LocalizedString};
+
+    #[test]
+    fn test_validate_show_in() {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(validate_show_in(&list(&["GNOME"]), &list(&["KDE"])).is_ok());
+        assert_eq!(
+            validate_show_in(&list(&["GNOME", "KDE"]), &list(&["KDE"]))
+                .unwrap_err()
+                .to_string(),
+            "KDE cannot be in both OnlyShowIn and NotShowIn"
+        );
+    }
 
     #[test]
     fn test_strip_field_codes_basic() {
@@ -273,6 +402,117 @@ mod tests {
         assert!(content.contains("Exec=evolution\n"));
     }
 
+    #[test]
+    fn test_write_desktop_entry_quotes_exec() {
+        let dir = std::env::temp_dir().join("onset_test_write_quotes");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test_quotes.desktop");
+
+        let options = CreateOptions {
+            delay_seconds: 2,
+            ..Default::default()
+        };
+        write_desktop_entry(&path, "Test", "app --title='My App' %U", &options).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+
+        let invalid = write_desktop_entry(&path, "Test", "app > /tmp/log", &options);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(content.contains(r#"Exec=sh -c "sleep 2 && exec app \\"--title=My App\\"""#));
+        assert!(format!("{:#}", invalid.unwrap_err()).contains("Reserved character '>'"),);
+    }
+
+    #[test]
+    fn test_update_preserves_unknown_keys_and_groups() {
+        let content = "# comment\n[Desktop Entry]\nType=Application\nName=Old\nName[de]=Alt\nExec=old %U\nX-GNOME-Autostart-Phase=Panel\nHidden=true\n\n[Desktop Action new]\nName=New Window\nExec=old --new\n";
+        let entry = DesktopEntry {
+            name: "New".to_string(),
+            exec: "new".to_string(),
+            delay: Delay::new(DelayStrategy::ShellWrapper, 3),
+            comment: Some("Added".to_string()),
+            ..Default::default()
+        };
+
+        let updated = update_desktop_entry_content(content, &entry);
+
+        assert_eq!(
+            updated,
+            "# comment\n[Desktop Entry]\nType=Application\nName=New\nName[de]=Alt\nExec=sh -c \"sleep 3 && exec new\"\nX-GNOME-Autostart-Phase=Panel\nComment=Added\n\n[Desktop Action new]\nName=New Window\nExec=old --new\n"
+        );
+    }
+
+    #[test]
+    fn test_update_keeps_unchanged_lines_as_written() {
+        let content =
+            "[Desktop Entry]\nType=Application\nName = Echo\nExec=sh -c 'echo hi'\nComment=Old\n";
+        let mut entry = parse_desktop_file(content).unwrap();
+        entry.comment = Some("New".to_string());
+
+        let updated = update_desktop_entry_content(content, &entry);
+
+        assert_eq!(
+            updated,
+            "[Desktop Entry]\nType=Application\nName = Echo\nExec=sh -c 'echo hi'\nComment=New\n"
+        );
+    }
+
+    #[test]
+    fn test_update_autostart_condition() {
+        let content = "[Desktop Entry]\nType=Application\nName=App\nExec=app\nAutostartCondition=if-exists app/on\nX-GNOME-AutoRestart=true\n";
+        let mut entry = parse_desktop_file(content).unwrap();
+
+        assert_eq!(update_desktop_entry_content(content, &entry), content);
+
+        entry.autostart_condition = Some(AutostartCondition::UnlessExists("app/off".to_string()));
+        entry.auto_restart = false;
+        assert_eq!(
+            update_desktop_entry_content(content, &entry),
+            "[Desktop Entry]\nType=Application\nName=App\nExec=app\nAutostartCondition=unless-exists app/off\n"
+        );
+    }
+
+    #[test]
+    fn test_update_environment() {
+        let content = "[Desktop Entry]\nType=Application\nName=App\nExec=sh -c \"sleep 3 && exec env QT_QPA_PLATFORM=wayland app\"\n";
+        let mut entry = parse_desktop_file(content).unwrap();
+
+        assert_eq!(entry.exec, "app");
+        assert_eq!(
+            entry.environment,
+            vec![("QT_QPA_PLATFORM".to_string(), "wayland".to_string())]
+        );
+        assert_eq!(update_desktop_entry_content(content, &entry), content);
+
+        entry.environment.clear();
+        assert_eq!(
+            update_desktop_entry_content(content, &entry),
+            "[Desktop Entry]\nType=Application\nName=App\nExec=sh -c \"sleep 3 && exec app\"\n"
+        );
+        assert!(validate_environment(&[("A-B".to_string(), String::new())]).is_err());
+    }
+
+    #[test]
+    fn test_update_localized_values() {
+        let content =
+            "[Desktop Entry]\nType=Application\nName=Files\nName[de]=Dateien\nExec=nautilus\n";
+        let mut entry = parse_desktop_file(content).unwrap();
+        entry.localized_name = Some(LocalizedString {
+            locale: "de".to_string(),
+            value: "Dateimanager".to_string(),
+        });
+        entry.localized_comment = Some(LocalizedString {
+            locale: "de".to_string(),
+            value: "Dateien durchsuchen".to_string(),
+        });
+
+        let updated = update_desktop_entry_content(content, &entry);
+
+        assert_eq!(
+            updated,
+            "[Desktop Entry]\nType=Application\nName=Files\nName[de]=Dateimanager\nExec=nautilus\nComment[de]=Dateien durchsuchen\n"
+        );
+    }
+
     #[test]
     fn test_sanitize_id() {
         assert_eq!(sanitize_id("my-app"), "my-app");
@@ -19,7 +19,7 @@ pub fn discover_applications() -> Result<Vec<Application>> {
         scan_application_dir(dir, &mut applications, &mut seen_ids)?;
     }
 
-    applications.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    applications.sort_by_key(|a| a.display_name().to_lowercase());
 
     Ok(applications)
 }
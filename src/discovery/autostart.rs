@@ -1,54 +1,154 @@
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 
 use crate::config::XDG_PATHS;
-use crate::desktop_entry::parser::{is_valid_desktop_entry, parse_desktop_file};
-use crate::model::AutostartEntry;
+use crate::desktop_entry::lint::{Check, Diagnostic, Severity, lint_desktop_file};
+use crate::desktop_entry::parser::{is_hidden_stub, is_valid_desktop_entry, parse_desktop_file};
+use crate::desktop_entry::{DESKTOP_ENTRY_GROUP, DesktopDocument};
+use crate::model::{AutostartEntry, BrokenEntry, EntryOrigin};
 
 pub fn discover_autostart_entries() -> Result<Vec<AutostartEntry>> {
-    let mut entries: Vec<AutostartEntry> = Vec::new();
+    discover_autostart_entries_in(&XDG_PATHS.user_autostart, &XDG_PATHS.system_autostart)
+}
 
-    let dir = &XDG_PATHS.user_autostart;
-    if !dir.exists() {
-        return Ok(entries);
-    }
+/// Look up a single entry by its desktop-file ID, honoring the same
+/// precedence rules as [`discover_autostart_entries`].
+pub fn find_autostart_entry(id: &str) -> Result<Option<AutostartEntry>> {
+    let id = id.strip_suffix(".desktop").unwrap_or(id);
+    Ok(discover_autostart_entries()?
+        .into_iter()
+        .find(|e| e.id == id))
+}
 
-    if let Ok(read_dir) = std::fs::read_dir(dir) {
-        for entry in read_dir.flatten() {
-            let path = entry.path();
+/// Merge the user autostart directory with the system ones following the
+/// XDG Autostart precedence rules: the user directory wins, followed by the
+/// system directories in `$XDG_CONFIG_DIRS` order. Files are matched by
+/// desktop-file ID, so only the most important file for each ID is loaded.
+pub fn discover_autostart_entries_in(
+    user_dir: &Path,
+    system_dirs: &[PathBuf],
+) -> Result<Vec<AutostartEntry>> {
+    let mut entries: Vec<AutostartEntry> = resolve_autostart_files(user_dir, system_dirs)
+        .into_iter()
+        .filter_map(|(id, (path, origin))| load_autostart_entry(&path, &id, origin, system_dirs))
+        .collect();
 
-            if !path.is_file() {
-                continue;
-            }
+    entries.sort_by(|a, b| {
+        a.desktop_entry
+            .display_name()
+            .cmp(b.desktop_entry.display_name())
+    });
 
-            if path.extension().map(|e| e != "desktop").unwrap_or(true) {
-                continue;
-            }
+    Ok(entries)
+}
 
-            let id = path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .map(|s| s.to_string());
+/// Autostart files that would be loaded but are not valid desktop entries,
+/// with the problems found in them. Files that only set `Hidden=true` are
+/// a legitimate way to disable an entry and are not reported.
+pub fn discover_broken_entries() -> Result<Vec<BrokenEntry>> {
+    discover_broken_entries_in(&XDG_PATHS.user_autostart, &XDG_PATHS.system_autostart)
+}
 
-            let Some(id) = id else {
-                continue;
+fn discover_broken_entries_in(
+    user_dir: &Path,
+    system_dirs: &[PathBuf],
+) -> Result<Vec<BrokenEntry>> {
+    Ok(resolve_autostart_files(user_dir, system_dirs)
+        .into_iter()
+        .filter_map(|(id, (path, origin))| {
+            let diagnostics = match std::fs::read_to_string(&path) {
+                Ok(content) if is_valid_desktop_entry(&content) => return None,
+                Ok(content) if is_hidden_stub(&content) => return None,
+                Ok(content) => lint_desktop_file(&content),
+                Err(e) => vec![Diagnostic {
+                    severity: Severity::Error,
+                    check: Check::Syntax,
+                    line: None,
+                    message: format!("Cannot be read: {}", e),
+                }],
             };
+            Some(BrokenEntry {
+                id,
+                path,
+                origin,
+                diagnostics,
+            })
+        })
+        .collect())
+}
 
-            if let Some(autostart_entry) = load_autostart_entry(&path, &id) {
-                entries.push(autostart_entry);
-            }
+/// The file that wins for each desktop-file ID, with its origin.
+fn resolve_autostart_files(
+    user_dir: &Path,
+    system_dirs: &[PathBuf],
+) -> BTreeMap<String, (PathBuf, EntryOrigin)> {
+    let mut winners: BTreeMap<String, (PathBuf, EntryOrigin)> = BTreeMap::new();
+
+    for dir in system_dirs.iter().rev() {
+        for (id, path) in scan_autostart_dir(dir) {
+            winners.insert(id, (path, EntryOrigin::System));
         }
     }
 
-    entries.sort_by(|a, b| a.desktop_entry.name.cmp(&b.desktop_entry.name));
+    for (id, path) in scan_autostart_dir(user_dir) {
+        let origin = if winners.contains_key(&id) {
+            EntryOrigin::UserOverridesSystem
+        } else {
+            EntryOrigin::User
+        };
+        winners.insert(id, (path, origin));
+    }
 
-    Ok(entries)
+    winners
 }
 
-fn load_autostart_entry(path: &Path, id: &str) -> Option<AutostartEntry> {
+fn scan_autostart_dir(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut found = Vec::new();
+
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return found;
+    };
+
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+
+        if !path.is_file() {
+            continue;
+        }
+
+        if path.extension().map(|e| e != "desktop").unwrap_or(true) {
+            continue;
+        }
+
+        let id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_string());
+
+        let Some(id) = id else {
+            continue;
+        };
+
+        found.push((id, path));
+    }
+
+    found
+}
+
+fn load_autostart_entry(
+    path: &Path,
+    id: &str,
+    origin: EntryOrigin,
+    system_dirs: &[PathBuf],
+) -> Option<AutostartEntry> {
     let content = std::fs::read_to_string(path).ok()?;
 
+    if origin == EntryOrigin::UserOverridesSystem && is_hidden_stub(&content) {
+        return load_hidden_system_entry(path, id, system_dirs);
+    }
+
     if !is_valid_desktop_entry(&content) {
         tracing::debug!("Skipping invalid desktop entry: {}", path.display());
         return None;
@@ -61,5 +161,125 @@ fn load_autostart_entry(path: &Path, id: &str) -> Option<AutostartEntry> {
         path.to_path_buf(),
         desktop_entry,
         content,
+        origin,
+    ))
+}
+
+/// The system entry a `Hidden=true` stub at `stub` disables. It is listed
+/// with the keys of the system file, disabled, so that it can be enabled
+/// again; its content is what filling in the stub would write.
+fn load_hidden_system_entry(
+    stub: &Path,
+    id: &str,
+    system_dirs: &[PathBuf],
+) -> Option<AutostartEntry> {
+    let system_path = system_dirs
+        .iter()
+        .map(|dir| dir.join(format!("{}.desktop", id)))
+        .find(|path| path.is_file())?;
+    let content = std::fs::read_to_string(&system_path).ok()?;
+
+    if !is_valid_desktop_entry(&content) {
+        tracing::debug!("Skipping invalid desktop entry: {}", system_path.display());
+        return None;
+    }
+
+    let mut document = DesktopDocument::parse(&content);
+    document.set(DESKTOP_ENTRY_GROUP, "Hidden", "true");
+    let content = document.to_string();
+    let desktop_entry = parse_desktop_file(&content).ok()?;
+
+    Some(AutostartEntry::new(
+        id.to_string(),
+        stub.to_path_buf(),
+        desktop_entry,
+        content,
+        EntryOrigin::UserOverridesSystem,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_entry(dir: &Path, id: &str, name: &str, extra: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(
+            dir.join(format!("{}.desktop", id)),
+            format!(
+                "[Desktop Entry]\nType=Application\nName={}\nExec=/usr/bin/{}\n{}",
+                name, id, extra
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_discover_merges_user_and_system_entries() {
+        let root = std::env::temp_dir().join("onset_test_discover_merge");
+        std::fs::remove_dir_all(&root).ok();
+        let user = root.join("user");
+        let system_high = root.join("etc_high");
+        let system_low = root.join("etc_low");
+
+        write_entry(&user, "mine", "Mine", "");
+        write_entry(&user, "shared", "Shared (user)", "Hidden=true\n");
+        write_entry(&system_high, "shared", "Shared (high)", "");
+        write_entry(&system_high, "tray", "Tray (high)", "");
+        write_entry(&system_low, "tray", "Tray (low)", "");
+        write_entry(&system_low, "agent", "Agent", "");
+
+        let entries =
+            discover_autostart_entries_in(&user, &[system_high.clone(), system_low.clone()])
+                .unwrap();
+        std::fs::remove_dir_all(&root).ok();
+
+        let find = |id: &str| entries.iter().find(|e| e.id == id).unwrap();
+
+        assert_eq!(entries.len(), 4);
+        assert_eq!(find("mine").origin, EntryOrigin::User);
+        assert_eq!(find("shared").origin, EntryOrigin::UserOverridesSystem);
+        assert!(find("shared").desktop_entry.hidden);
+        assert_eq!(find("tray").origin, EntryOrigin::System);
+        assert_eq!(find("tray").desktop_entry.name, "Tray (high)");
+        assert_eq!(find("agent").path, system_low.join("agent.desktop"));
+    }
+
+    #[test]
+    fn test_discover_broken_entries() {
+        let root = std::env::temp_dir().join("onset_test_discover_broken");
+        std::fs::remove_dir_all(&root).ok();
+        let user = root.join("user");
+        let system = root.join("etc");
+
+        write_entry(&user, "fine", "Fine", "");
+        write_entry(&system, "stub", "Stub", "");
+        std::fs::write(user.join("stub.desktop"), "[Desktop Entry]\nHidden=true\n").unwrap();
+        std::fs::write(
+            user.join("link.desktop"),
+            "[Desktop Entry]\nType=Link\nName=L\n",
+        )
+        .unwrap();
+
+        let broken = discover_broken_entries_in(&user, std::slice::from_ref(&system)).unwrap();
+        let entries = discover_autostart_entries_in(&user, &[system]).unwrap();
+        std::fs::remove_dir_all(&root).ok();
+
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].id, "link");
+        assert_eq!(broken[0].origin, EntryOrigin::User);
+        assert!(
+            broken[0]
+                .diagnostics
+                .iter()
+                .any(|d| d.check == Check::UnsupportedType)
+        );
+        let stub = entries.iter().find(|e| e.id == "stub").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(stub.origin, EntryOrigin::UserOverridesSystem);
+        assert_eq!(stub.path, user.join("stub.desktop"));
+        assert_eq!(stub.desktop_entry.name, "Stub");
+        assert!(stub.desktop_entry.hidden);
+        assert!(is_valid_desktop_entry(&stub.raw_content));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::model::AutostartEntry;
+
+/// Steps that turn one listing of entries into another while leaving the
+/// unchanged entries in place, so views can update only the rows that
+/// actually changed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ListingDiff {
+    /// Indices into the old listing, highest first, so that they stay valid
+    /// while removing one after another.
+    pub removed: Vec<usize>,
+    /// Indices into the new listing, lowest first, at which to insert the
+    /// new entry after all removals.
+    pub inserted: Vec<usize>,
+}
+
+impl ListingDiff {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.inserted.is_empty()
+    }
+}
+
+/// Compare two listings in display order. Entries that moved are reported
+/// as removed and inserted again.
+pub fn diff_entries(old: &[AutostartEntry], new: &[AutostartEntry]) -> ListingDiff {
+    let mut kept = vec![false; old.len()];
+    let mut inserted = Vec::new();
+    let mut next_old = 0;
+
+    for (index, entry) in new.iter().enumerate() {
+        match old[next_old..]
+            .iter()
+            .position(|candidate| is_unchanged(candidate, entry))
+        {
+            Some(offset) => {
+                kept[next_old + offset] = true;
+                next_old += offset + 1;
+            }
+            None => inserted.push(index),
+        }
+    }
+
+    let removed = (0..old.len()).rev().filter(|&i| !kept[i]).collect();
+
+    ListingDiff { removed, inserted }
+}
+
+/// Besides the file itself, the systemd drop-in and the presence of the
+/// `TryExec` binary affect how an entry is shown.
+fn is_unchanged(old: &AutostartEntry, new: &AutostartEntry) -> bool {
+    old.id == new.id
+        && old.path == new.path
+        && old.origin == new.origin
+        && old.raw_content == new.raw_content
+        && old.desktop_entry.delay == new.desktop_entry.delay
+        && old.effective_state == new.effective_state
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::desktop_entry::parser::parse_desktop_file;
+    use crate::model::EntryOrigin;
+
+    fn entry(id: &str, name: &str) -> AutostartEntry {
+        let content = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec=app\n",
+            name
+        );
+        AutostartEntry::new(
+            id.to_string(),
+            PathBuf::from(format!("/tmp/{}.desktop", id)),
+            parse_desktop_file(&content).unwrap(),
+            content,
+            EntryOrigin::User,
+        )
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let listing = vec![entry("a", "A"), entry("b", "B")];
+        assert!(diff_entries(&listing, &listing.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_added_removed_and_changed() {
+        let old = vec![entry("a", "A"), entry("b", "B"), entry("c", "C")];
+        let new = vec![entry("a", "A"), entry("b", "Bee"), entry("d", "D")];
+
+        assert_eq!(
+            diff_entries(&old, &new),
+            ListingDiff {
+                removed: vec![2, 1],
+                inserted: vec![1, 2],
+            }
+        );
+    }
+
+    #[test]
+    fn test_diff_moved_entry() {
+        // Renaming "a" sorts it after "b"
+        let old = vec![entry("a", "A"), entry("b", "B")];
+        let new = vec![entry("b", "B"), entry("a", "C")];
+
+        let diff = diff_entries(&old, &new);
+        assert_eq!(
+            diff,
+            ListingDiff {
+                removed: vec![0],
+                inserted: vec![1],
+            }
+        );
+    }
+}
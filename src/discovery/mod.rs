@@ -1,5 +1,11 @@
 mod applications;
 mod autostart;
+mod changes;
+mod systemd;
+mod trash;
 
 pub use applications::discover_applications;
-pub use autostart::discover_autostart_entries;
+pub use autostart::{discover_autostart_entries, discover_broken_entries, find_autostart_entry};
+pub use changes::diff_entries;
+pub use systemd::{discover_systemd_units, find_systemd_unit};
+pub use trash::{TRASH_INFO_GROUP, discover_trashed_entries};
@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::config::XDG_PATHS;
+use crate::desktop_entry::DesktopDocument;
+use crate::model::SystemdUnit;
+use crate::operations::systemd::{GRAPHICAL_SESSION_TARGET, wants_dir};
+
+pub fn discover_systemd_units() -> Result<Vec<SystemdUnit>> {
+    discover_systemd_units_in(&XDG_PATHS.systemd_user)
+}
+
+pub fn find_systemd_unit(name: &str) -> Result<Option<SystemdUnit>> {
+    Ok(discover_systemd_units()?
+        .into_iter()
+        .find(|u| u.name == name))
+}
+
+/// User services in `dir` that are installed into the graphical session,
+/// i.e. have `WantedBy=graphical-session.target`. Other units are left
+/// alone since starting them is not an autostart concern.
+pub fn discover_systemd_units_in(dir: &Path) -> Result<Vec<SystemdUnit>> {
+    let mut units = Vec::new();
+
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Ok(units);
+    };
+
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+
+        if !path.is_file() || path.extension().is_none_or(|e| e != "service") {
+            continue;
+        }
+
+        let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+
+        let document = DesktopDocument::parse(&content);
+        let wanted_by_session = document
+            .entries("Install")
+            .filter(|(key, _)| *key == "WantedBy")
+            .flat_map(|(_, value)| value.split_whitespace())
+            .any(|target| target == GRAPHICAL_SESSION_TARGET);
+
+        if !wanted_by_session {
+            continue;
+        }
+
+        units.push(SystemdUnit {
+            name: name.to_string(),
+            enabled: wants_dir(dir).join(name).symlink_metadata().is_ok(),
+            description: document.get("Unit", "Description").map(String::from),
+            exec_start: document.get("Service", "ExecStart").map(String::from),
+            path,
+        });
+    }
+
+    units.sort_by(|a, b| a.display_name().cmp(b.display_name()));
+
+    Ok(units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::systemd::set_unit_enabled;
+
+    #[test]
+    fn test_discover_session_units() {
+        let dir = std::env::temp_dir().join("onset_test_discover_units");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("other.service"),
+            "[Service]\nExecStart=/usr/bin/other\n[Install]\nWantedBy=default.target\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("tray.service"),
+            "[Unit]\nDescription=Tray\n[Service]\nExecStart=/usr/bin/tray\n[Install]\nWantedBy=default.target graphical-session.target\n",
+        )
+        .unwrap();
+
+        let units = discover_systemd_units_in(&dir).unwrap();
+        set_unit_enabled(&units[0], true).unwrap();
+        let enabled = discover_systemd_units_in(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].name, "tray.service");
+        assert_eq!(units[0].display_name(), "Tray");
+        assert_eq!(units[0].exec_start.as_deref(), Some("/usr/bin/tray"));
+        assert!(!units[0].enabled);
+        assert!(enabled[0].enabled);
+    }
+}
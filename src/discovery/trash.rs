@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::config::XDG_PATHS;
+use crate::desktop_entry::DesktopDocument;
+use crate::desktop_entry::parser::parse_desktop_file;
+use crate::model::TrashedEntry;
+use crate::utils::percent_decode;
+
+pub const TRASH_INFO_GROUP: &str = "Trash Info";
+
+pub fn discover_trashed_entries() -> Result<Vec<TrashedEntry>> {
+    discover_trashed_entries_in(&XDG_PATHS.trash, &XDG_PATHS.user_autostart)
+}
+
+/// Files removed from `autostart_dir` that are still in the trash, most
+/// recently removed first. Entries whose original file exists again, e.g.
+/// after an undo, are skipped since restoring them would fail.
+fn discover_trashed_entries_in(trash: &Path, autostart_dir: &Path) -> Result<Vec<TrashedEntry>> {
+    let Ok(read_dir) = std::fs::read_dir(trash.join("info")) else {
+        return Ok(Vec::new());
+    };
+
+    let mut entries: Vec<TrashedEntry> = read_dir
+        .flatten()
+        .filter_map(|info| load_trashed_entry(trash, &info.path()))
+        .filter(|entry| {
+            entry.original_path.parent() == Some(autostart_dir) && !entry.original_path.exists()
+        })
+        .collect();
+
+    entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+
+    Ok(entries)
+}
+
+fn load_trashed_entry(trash: &Path, info_path: &Path) -> Option<TrashedEntry> {
+    let trash_name = info_path
+        .file_name()?
+        .to_str()?
+        .strip_suffix(".trashinfo")?;
+    if !trash_name.ends_with(".desktop") {
+        return None;
+    }
+
+    let trash_path = trash.join("files").join(trash_name);
+    let content = std::fs::read_to_string(info_path).ok()?;
+    let info = DesktopDocument::parse(&content);
+    let original_path = PathBuf::from(percent_decode(info.get(TRASH_INFO_GROUP, "Path")?));
+
+    let id = original_path.file_stem()?.to_str()?.to_string();
+    let name = std::fs::read_to_string(&trash_path)
+        .ok()
+        .and_then(|content| parse_desktop_file(&content).ok())
+        .map(|entry| entry.display_name().to_string())
+        .unwrap_or_else(|| id.clone());
+
+    Some(TrashedEntry {
+        id,
+        name,
+        original_path,
+        trash_path,
+        info_path: info_path.to_path_buf(),
+        deleted_at: info
+            .get(TRASH_INFO_GROUP, "DeletionDate")
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_trashed_entries() {
+        let dir = std::env::temp_dir().join("onset_test_discover_trash");
+        std::fs::remove_dir_all(&dir).ok();
+        let trash = dir.join("Trash");
+        let autostart = dir.join("my autostart");
+        std::fs::create_dir_all(trash.join("info")).unwrap();
+        std::fs::create_dir_all(trash.join("files")).unwrap();
+        std::fs::create_dir_all(&autostart).unwrap();
+
+        let write = |name: &str, original: &str, date: &str| {
+            std::fs::write(
+                trash.join("info").join(format!("{}.trashinfo", name)),
+                format!("[Trash Info]\nPath={}\nDeletionDate={}\n", original, date),
+            )
+            .unwrap();
+            std::fs::write(
+                trash.join("files").join(name),
+                "[Desktop Entry]\nType=Application\nName=Old App\nExec=app\n",
+            )
+            .unwrap();
+        };
+        let encoded_dir = format!("{}/my%20autostart", dir.display());
+        write(
+            "old.desktop",
+            &format!("{}/old.desktop", encoded_dir),
+            "2026-01-01T10:00:00",
+        );
+        write(
+            "new.desktop",
+            &format!("{}/new.desktop", encoded_dir),
+            "2026-02-01T10:00:00",
+        );
+        write(
+            "back.desktop",
+            &format!("{}/back.desktop", encoded_dir),
+            "2026-03-01T10:00:00",
+        );
+        write(
+            "other.desktop",
+            "/home/u/Desktop/other.desktop",
+            "2026-03-01T10:00:00",
+        );
+        std::fs::write(autostart.join("back.desktop"), "").unwrap();
+
+        let entries = discover_trashed_entries_in(&trash, &autostart).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["new", "old"]);
+        assert_eq!(entries[0].name, "Old App");
+        assert_eq!(entries[0].original_path, autostart.join("new.desktop"));
+        assert_eq!(entries[0].deleted_at, "2026-02-01T10:00:00");
+    }
+}
@@ -1,4 +1,5 @@
 mod app;
+mod cli;
 mod config;
 mod desktop_entry;
 mod discovery;
@@ -10,17 +11,29 @@ mod utils;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 fn main() -> anyhow::Result<()> {
-    if std::env::args().any(|a| a == "--version" || a == "-V") {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // Only in place of a command; later it may be the value of an option
+    if matches!(args.first().map(String::as_str), Some("--version" | "-V")) {
         println!("onset {}", env!("ONSET_VERSION"));
         return Ok(());
     }
 
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "onset=info".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let command = match cli::parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("onset: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if let Some(command) = command {
+        // Keep stdout clean for scripts; only warnings go to stderr
+        init_tracing("onset=warn");
+        std::process::exit(cli::run(command));
+    }
+
+    init_tracing("onset=info");
 
     tracing::info!("Starting onset");
 
@@ -28,3 +41,12 @@ fn main() -> anyhow::Result<()> {
 
     std::process::exit(exit_code);
 }
+
+fn init_tracing(default_filter: &str) {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::new(
+            std::env::var("RUST_LOG").unwrap_or_else(|_| default_filter.into()),
+        ))
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+}
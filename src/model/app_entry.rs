@@ -1,12 +1,15 @@
-use crate::desktop_entry::DesktopEntry;
+use crate::desktop_entry::{DesktopEntry, LocalizedString};
 
 #[derive(Debug, Clone)]
 pub struct Application {
     pub id: String,
     pub name: String,
+    pub localized_name: Option<LocalizedString>,
     pub exec: String,
+    pub environment: Vec<(String, String)>,
     pub icon: Option<String>,
     pub comment: Option<String>,
+    pub localized_comment: Option<LocalizedString>,
     pub keywords: Vec<String>,
 }
 
@@ -15,25 +18,39 @@ impl Application {
         Self {
             id,
             name: entry.name.clone(),
+            localized_name: entry.localized_name.clone(),
             exec: entry.exec.clone(),
+            environment: entry.environment.clone(),
             icon: entry.icon.clone(),
             comment: entry.comment.clone(),
+            localized_comment: entry.localized_comment.clone(),
             keywords: entry.keywords.clone(),
         }
     }
 
+    pub fn display_name(&self) -> &str {
+        self.localized_name
+            .as_ref()
+            .map(|l| l.value.as_str())
+            .unwrap_or(&self.name)
+    }
+
+    pub fn display_comment(&self) -> Option<&str> {
+        self.localized_comment
+            .as_ref()
+            .map(|l| l.value.as_str())
+            .or(self.comment.as_deref())
+    }
+
     pub fn matches_search(&self, query: &str) -> bool {
         let query = query.to_lowercase();
-        self.name.to_lowercase().contains(&query)
-            || self.exec.to_lowercase().contains(&query)
-            || self
-                .comment
-                .as_ref()
-                .map(|c| c.to_lowercase().contains(&query))
-                .unwrap_or(false)
-            || self
-                .keywords
-                .iter()
-                .any(|k| k.to_lowercase().contains(&query))
+        let contains = |s: &str| s.to_lowercase().contains(&query);
+
+        contains(&self.name)
+            || contains(self.display_name())
+            || contains(&self.exec)
+            || self.comment.as_deref().is_some_and(contains)
+            || self.display_comment().is_some_and(contains)
+            || self.keywords.iter().any(|k| contains(k))
     }
 }
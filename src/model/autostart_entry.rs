@@ -1,10 +1,34 @@
 use std::path::PathBuf;
 
-use crate::config::get_current_desktop;
-use crate::desktop_entry::{DesktopEntry, EffectiveState};
-use crate::operations::delay::{get_delay, unwrap_delay};
+use serde::Serialize;
+
+use crate::config::{XDG_PATHS, get_current_desktop};
+use crate::desktop_entry::{Delay, DelayStrategy, DesktopEntry, EffectiveState};
+use crate::operations::delay::read_systemd_delay;
 use crate::utils::binary_exists;
 
+/// Where the file that defines an autostart entry lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryOrigin {
+    /// Only present in the user's autostart directory.
+    User,
+    /// Only present in a system directory from `$XDG_CONFIG_DIRS`.
+    System,
+    /// A user file shadows a system file with the same desktop-file ID.
+    UserOverridesSystem,
+}
+
+impl std::fmt::Display for EntryOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryOrigin::User => write!(f, "User"),
+            EntryOrigin::System => write!(f, "System"),
+            EntryOrigin::UserOverridesSystem => write!(f, "User Override"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AutostartEntry {
     pub id: String,
@@ -12,21 +36,29 @@ pub struct AutostartEntry {
     pub desktop_entry: DesktopEntry,
     pub effective_state: EffectiveState,
     pub raw_content: String,
+    pub origin: EntryOrigin,
 }
 
 impl AutostartEntry {
     pub fn new(
         id: String,
         path: PathBuf,
-        desktop_entry: DesktopEntry,
+        mut desktop_entry: DesktopEntry,
         raw_content: String,
+        origin: EntryOrigin,
     ) -> Self {
+        if desktop_entry.delay.is_none() {
+            desktop_entry.delay = read_systemd_delay(&id)
+                .and_then(|seconds| Delay::new(DelayStrategy::Systemd, seconds));
+        }
+
         let mut entry = Self {
             id,
             path,
             desktop_entry,
             effective_state: EffectiveState::Enabled,
             raw_content,
+            origin,
         };
         entry.effective_state = entry.compute_effective_state(&get_current_desktop());
         entry
@@ -58,15 +90,42 @@ impl AutostartEntry {
             return EffectiveState::EnvironmentExcluded;
         }
 
+        if let Some(ref condition) = self.desktop_entry.autostart_condition {
+            match condition.is_met(&XDG_PATHS.config_home) {
+                Some(true) => {}
+                Some(false) => return EffectiveState::ConditionUnmet,
+                None => return EffectiveState::Conditional,
+            }
+        }
+
         EffectiveState::Enabled
     }
 
-    pub fn delay_seconds(&self) -> Option<u32> {
-        get_delay(&self.desktop_entry.exec)
+    /// Path that modifications should be written to. System entries are
+    /// never modified in place; changes go to a user-level file with the
+    /// same desktop-file ID, which takes precedence over the system one.
+    pub fn user_path(&self) -> PathBuf {
+        match self.origin {
+            EntryOrigin::System => XDG_PATHS
+                .user_autostart
+                .join(format!("{}.desktop", self.id)),
+            EntryOrigin::User | EntryOrigin::UserOverridesSystem => self.path.clone(),
+        }
     }
 
-    pub fn base_exec(&self) -> String {
-        let (base, _) = unwrap_delay(&self.desktop_entry.exec);
-        base
+    pub fn matches_search(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        let contains = |s: &str| s.to_lowercase().contains(&query);
+        let desktop = &self.desktop_entry;
+
+        contains(&desktop.name)
+            || contains(desktop.display_name())
+            || desktop.comment.as_deref().is_some_and(contains)
+            || desktop.display_comment().is_some_and(contains)
+            || contains(&desktop.exec)
+    }
+
+    pub fn delay_seconds(&self) -> Option<u32> {
+        self.desktop_entry.delay.and_then(|d| d.seconds)
     }
 }
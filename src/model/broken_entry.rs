@@ -0,0 +1,15 @@
+use std::path::PathBuf;
+
+use crate::desktop_entry::lint::Diagnostic;
+
+use super::EntryOrigin;
+
+/// An autostart file that is not a usable desktop entry. It is skipped at
+/// login, so it is listed separately with what is wrong with it.
+#[derive(Debug, Clone)]
+pub struct BrokenEntry {
+    pub id: String,
+    pub path: PathBuf,
+    pub origin: EntryOrigin,
+    pub diagnostics: Vec<Diagnostic>,
+}
@@ -1,5 +1,11 @@
 mod app_entry;
 mod autostart_entry;
+mod broken_entry;
+mod systemd_unit;
+mod trashed_entry;
 
 pub use app_entry::Application;
-pub use autostart_entry::AutostartEntry;
+pub use autostart_entry::{AutostartEntry, EntryOrigin};
+pub use broken_entry::BrokenEntry;
+pub use systemd_unit::SystemdUnit;
+pub use trashed_entry::TrashedEntry;
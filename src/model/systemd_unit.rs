@@ -0,0 +1,21 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// A systemd user service that is started with the graphical session.
+#[derive(Debug, Clone, Serialize)]
+pub struct SystemdUnit {
+    /// Unit file name, e.g. `onset-syncthing.service`.
+    pub name: String,
+    pub path: PathBuf,
+    pub description: Option<String>,
+    pub exec_start: Option<String>,
+    /// Whether the unit is linked into `graphical-session.target.wants`.
+    pub enabled: bool,
+}
+
+impl SystemdUnit {
+    pub fn display_name(&self) -> &str {
+        self.description.as_deref().unwrap_or(&self.name)
+    }
+}
@@ -0,0 +1,17 @@
+use std::path::PathBuf;
+
+/// An autostart file that was moved to the trash and can be restored.
+#[derive(Debug, Clone)]
+pub struct TrashedEntry {
+    /// Desktop-file ID the entry had before it was removed.
+    pub id: String,
+    pub name: String,
+    /// Where the file is restored to.
+    pub original_path: PathBuf,
+    /// The file in `Trash/files`.
+    pub trash_path: PathBuf,
+    /// The matching `.trashinfo` file in `Trash/info`.
+    pub info_path: PathBuf,
+    /// `DeletionDate` from the `.trashinfo` file, `YYYY-MM-DDThh:mm:ss`.
+    pub deleted_at: String,
+}
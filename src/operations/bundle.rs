@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::config::XDG_PATHS;
+use crate::desktop_entry::parser::is_valid_desktop_entry;
+use crate::desktop_entry::writer::{sanitize_id, write_atomic};
+use crate::desktop_entry::{Delay, DelayStrategy};
+use crate::discovery::discover_autostart_entries;
+use crate::model::{AutostartEntry, EntryOrigin};
+use crate::operations::delay::{read_systemd_delay, sync_systemd_delay, systemd_drop_in_path};
+use crate::operations::journal::{self, RecordId};
+use crate::operations::trash::move_to_trash;
+
+/// Bumped whenever a field is renamed or removed.
+const BUNDLE_VERSION: u32 = 1;
+
+/// The user's autostart configuration in one portable file. System entries
+/// are not included since they come with the installed packages; user
+/// overrides of them are, so disabling one carries over.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    pub bundle_version: u32,
+    pub entries: Vec<BundledEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledEntry {
+    pub id: String,
+    /// The desktop file exactly as it is on disk, including `Hidden` and
+    /// delay keys.
+    pub content: String,
+    /// Seconds of a systemd drop-in delay, which lives outside the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub systemd_delay: Option<u32>,
+}
+
+impl BundledEntry {
+    fn from_entry(entry: &AutostartEntry) -> Self {
+        Self {
+            id: entry.id.clone(),
+            content: entry.raw_content.clone(),
+            systemd_delay: read_systemd_delay(&entry.id),
+        }
+    }
+}
+
+impl Bundle {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let bundle: Bundle = serde_json::from_str(json).context("Not an Onset bundle")?;
+
+        if bundle.bundle_version > BUNDLE_VERSION {
+            bail!(
+                "The bundle was created by a newer version of Onset (format {})",
+                bundle.bundle_version
+            );
+        }
+
+        for entry in &bundle.entries {
+            if entry.id.is_empty() || sanitize_id(&entry.id) != entry.id {
+                bail!("Invalid entry ID in bundle: {}", entry.id);
+            }
+            if !is_valid_desktop_entry(&entry.content) {
+                bail!("Invalid desktop entry in bundle: {}", entry.id);
+            }
+        }
+
+        Ok(bundle)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Collect all user-level entries.
+pub fn export_bundle() -> Result<Bundle> {
+    Ok(bundle_from_entries(&discover_autostart_entries()?))
+}
+
+fn bundle_from_entries(entries: &[AutostartEntry]) -> Bundle {
+    Bundle {
+        bundle_version: BUNDLE_VERSION,
+        entries: entries
+            .iter()
+            .filter(|e| e.origin != EntryOrigin::System)
+            .map(BundledEntry::from_entry)
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImportMode {
+    /// Add and update the bundled entries, keep all others.
+    #[default]
+    Merge,
+    /// Make the user entries match the bundle exactly, removing the rest.
+    Replace,
+}
+
+/// What importing a bundle would change, by desktop-file ID.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportPlan {
+    pub additions: Vec<String>,
+    /// Entries that exist with different content and will be overwritten.
+    pub conflicts: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Only filled in replace mode.
+    pub removals: Vec<String>,
+}
+
+impl ImportPlan {
+    pub fn has_changes(&self) -> bool {
+        !self.additions.is_empty() || !self.conflicts.is_empty() || !self.removals.is_empty()
+    }
+}
+
+pub fn plan_import(bundle: &Bundle, mode: ImportMode) -> Result<ImportPlan> {
+    Ok(plan_import_against(
+        bundle,
+        &bundle_from_entries(&discover_autostart_entries()?),
+        mode,
+    ))
+}
+
+fn plan_import_against(bundle: &Bundle, current: &Bundle, mode: ImportMode) -> ImportPlan {
+    let existing: BTreeMap<&str, &BundledEntry> =
+        current.entries.iter().map(|e| (e.id.as_str(), e)).collect();
+
+    let mut plan = ImportPlan::default();
+
+    for entry in &bundle.entries {
+        match existing.get(entry.id.as_str()) {
+            None => plan.additions.push(entry.id.clone()),
+            Some(&current) if current == entry => plan.unchanged.push(entry.id.clone()),
+            Some(_) => plan.conflicts.push(entry.id.clone()),
+        }
+    }
+
+    if mode == ImportMode::Replace {
+        plan.removals = existing
+            .keys()
+            .filter(|id| !bundle.entries.iter().any(|e| e.id == **id))
+            .map(|id| id.to_string())
+            .collect();
+    }
+
+    plan
+}
+
+/// Apply `plan` as one journal record, so the whole import can be undone
+/// at once. Removed entries go to the trash.
+pub fn apply_import(bundle: &Bundle, plan: &ImportPlan) -> Result<Option<RecordId>> {
+    let writes: Vec<&BundledEntry> = bundle
+        .entries
+        .iter()
+        .filter(|e| plan.additions.contains(&e.id) || plan.conflicts.contains(&e.id))
+        .collect();
+
+    let entry_path = |id: &str| XDG_PATHS.user_autostart.join(format!("{}.desktop", id));
+    let touched: Vec<PathBuf> = writes
+        .iter()
+        .map(|e| e.id.as_str())
+        .chain(plan.removals.iter().map(String::as_str))
+        .flat_map(|id| [entry_path(id), systemd_drop_in_path(id)])
+        .collect();
+
+    let (_, record) = journal::record("Import bundle", &touched, || {
+        for entry in &writes {
+            let path = entry_path(&entry.id);
+            write_atomic(&path, &entry.content)
+                .with_context(|| format!("Failed to write entry: {}", path.display()))?;
+            sync_systemd_delay(
+                &entry.id,
+                entry
+                    .systemd_delay
+                    .and_then(|seconds| Delay::new(DelayStrategy::Systemd, seconds)),
+            )?;
+        }
+
+        for id in &plan.removals {
+            move_to_trash(&entry_path(id))?;
+            sync_systemd_delay(id, None)?;
+        }
+
+        Ok(())
+    })?;
+
+    tracing::info!(
+        "Imported bundle: {} added, {} updated, {} removed",
+        plan.additions.len(),
+        plan.conflicts.len(),
+        plan.removals.len()
+    );
+
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundled(id: &str, name: &str) -> BundledEntry {
+        BundledEntry {
+            id: id.to_string(),
+            content: format!(
+                "[Desktop Entry]\nType=Application\nName={}\nExec=app\n",
+                name
+            ),
+            systemd_delay: None,
+        }
+    }
+
+    fn bundle(entries: Vec<BundledEntry>) -> Bundle {
+        Bundle {
+            bundle_version: BUNDLE_VERSION,
+            entries,
+        }
+    }
+
+    #[test]
+    fn test_plan_import() {
+        let current = bundle(vec![
+            bundled("a", "A"),
+            bundled("b", "B"),
+            bundled("c", "C"),
+        ]);
+        let incoming = bundle(vec![
+            bundled("a", "A"),
+            bundled("b", "Bee"),
+            bundled("d", "D"),
+        ]);
+
+        let merge = plan_import_against(&incoming, &current, ImportMode::Merge);
+        assert_eq!(merge.additions, vec!["d"]);
+        assert_eq!(merge.conflicts, vec!["b"]);
+        assert_eq!(merge.unchanged, vec!["a"]);
+        assert!(merge.removals.is_empty());
+
+        let replace = plan_import_against(&incoming, &current, ImportMode::Replace);
+        assert_eq!(replace.removals, vec!["c"]);
+        assert!(!plan_import_against(&current, &current, ImportMode::Replace).has_changes());
+    }
+
+    #[test]
+    fn test_bundle_round_trip_and_validation() {
+        let original = bundle(vec![BundledEntry {
+            systemd_delay: Some(5),
+            ..bundled("a", "A")
+        }]);
+        let parsed = Bundle::from_json(&original.to_json().unwrap()).unwrap();
+        assert_eq!(parsed.entries, original.entries);
+
+        let escaping = bundle(vec![bundled("../evil", "Evil")]);
+        assert!(Bundle::from_json(&escaping.to_json().unwrap()).is_err());
+
+        let newer = Bundle {
+            bundle_version: BUNDLE_VERSION + 1,
+            ..original
+        };
+        assert!(Bundle::from_json(&newer.to_json().unwrap()).is_err());
+    }
+}
@@ -3,28 +3,59 @@ use std::path::PathBuf;
 use anyhow::{Context, Result, bail};
 
 use crate::config::XDG_PATHS;
-use crate::desktop_entry::CreateOptions;
-use crate::desktop_entry::writer::{sanitize_id, write_desktop_entry};
+use crate::desktop_entry::writer::{
+    sanitize_id, validate_environment, validate_show_in, validate_working_dir, write_desktop_entry,
+};
+use crate::desktop_entry::{CreateOptions, Delay};
+use crate::operations::delay::{
+    preferred_delay_strategy, sync_systemd_delay, systemd_drop_in_path,
+};
+use crate::operations::journal::{self, RecordId};
 
+/// Write a new user entry, returning its path and the journal record that
+/// removes it again.
 pub fn create_autostart_entry(
     id: &str,
     name: &str,
     exec: &str,
-    options: CreateOptions,
-) -> Result<PathBuf> {
+    mut options: CreateOptions,
+) -> Result<(PathBuf, Option<RecordId>)> {
     let sanitized_id = sanitize_id(id);
     if sanitized_id.is_empty() {
         bail!("Invalid entry ID: {}", id);
     }
+    validate_show_in(&options.only_show_in, &options.not_show_in)?;
+    validate_environment(&options.environment)?;
+    if let Some(ref dir) = options.working_dir {
+        validate_working_dir(dir)?;
+    }
 
     let path = find_unique_path(&sanitized_id);
 
-    write_desktop_entry(&path, name, exec, &options)
-        .with_context(|| format!("Failed to create autostart entry: {}", path.display()))?;
+    let strategy = *options
+        .delay_strategy
+        .get_or_insert_with(preferred_delay_strategy);
+
+    let file_id = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(&sanitized_id)
+        .to_string();
+
+    let (_, record) = journal::record(
+        &format!("Create {}", name),
+        &[path.clone(), systemd_drop_in_path(&file_id)],
+        || {
+            write_desktop_entry(&path, name, exec, &options)
+                .with_context(|| format!("Failed to create autostart entry: {}", path.display()))?;
+
+            sync_systemd_delay(&file_id, Delay::new(strategy, options.delay_seconds))
+        },
+    )?;
 
     tracing::info!("Created autostart entry: {}", path.display());
 
-    Ok(path)
+    Ok((path, record))
 }
 
 fn find_unique_path(base_id: &str) -> PathBuf {
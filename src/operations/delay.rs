@@ -1,44 +1,253 @@
+use std::fs;
+use std::path::PathBuf;
 use std::sync::LazyLock;
 
+use anyhow::{Context, Result};
 use regex::Regex;
 
-static DELAY_PATTERN: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"^sh -c 'sleep (\d+) && exec (.+)'$").unwrap());
+use crate::config::{Preferences, XDG_PATHS, get_current_desktop};
+use crate::desktop_entry::exec::{parse_exec, quote_exec};
+use crate::desktop_entry::parser::escape_value;
+use crate::desktop_entry::writer::write_atomic;
+use crate::desktop_entry::{DESKTOP_ENTRY_GROUP, Delay, DelayStrategy, DesktopDocument};
 
+static DELAY_SCRIPT_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^sleep (\d+) && exec (.+)$").unwrap());
+
+const GNOME_DELAY_KEY: &str = "X-GNOME-Autostart-Delay";
+const KDE_PHASE_KEY: &str = "X-KDE-autostart-phase";
+const KDE_AFTER_KEY: &str = "X-KDE-autostart-after";
+
+/// Plasma starts applications in phase 2, after the desktop is up. Earlier
+/// phases are used by session services and are not treated as a delay.
+const KDE_LATE_PHASE: u32 = 2;
+
+/// Used when moving a delay from a strategy without a duration to one
+/// that needs it.
+const DEFAULT_DELAY_SECONDS: u32 = 10;
+
+const SYSTEMD_DROP_IN: &str = "onset-delay.conf";
+
+/// Wrap an Exec value so that it starts after `delay_seconds`, producing
+/// `sh -c "sleep N && exec <exec>"` quoted according to the spec.
 pub fn wrap_with_delay(exec: &str, delay_seconds: u32) -> String {
     if delay_seconds == 0 {
         return exec.to_string();
     }
 
-    let escaped = exec.replace("'", "'\\''");
-    format!("sh -c 'sleep {} && exec {}'", delay_seconds, escaped)
+    let script = format!("sleep {} && exec {}", delay_seconds, exec);
+    quote_exec(&["sh", "-c", &script])
 }
 
+/// Split a delay wrapper into the wrapped command and its delay. Both the
+/// double-quoted form and the single-quoted form written by older versions
+/// are recognised.
 pub fn unwrap_delay(exec: &str) -> (String, Option<u32>) {
-    if let Some(caps) = DELAY_PATTERN.captures(exec) {
+    if let Ok(args) = parse_exec(exec)
+        && let [shell, flag, script] = args.as_slice()
+        && shell == "sh"
+        && flag == "-c"
+        && let Some(caps) = DELAY_SCRIPT_PATTERN.captures(script)
+    {
         let delay: u32 = caps[1].parse().unwrap_or(0);
-        let command = caps[2].replace("'\\''", "'");
-        (command, Some(delay))
+        return (caps[2].to_string(), Some(delay));
+    }
+
+    (exec.to_string(), None)
+}
+
+/// Detect a delay encoded in the desktop file itself. `wrapper_seconds` is
+/// the delay found by [`unwrap_delay`] on the Exec value. A systemd drop-in
+/// lives outside the file and is looked up with [`read_systemd_delay`].
+pub fn detect_delay(document: &DesktopDocument, wrapper_seconds: Option<u32>) -> Option<Delay> {
+    wrapper_seconds
+        .and_then(|seconds| Delay::new(DelayStrategy::ShellWrapper, seconds))
+        .or_else(|| {
+            document
+                .get(DESKTOP_ENTRY_GROUP, GNOME_DELAY_KEY)
+                .and_then(|value| value.trim().parse().ok())
+                .and_then(|seconds| Delay::new(DelayStrategy::GnomeKey, seconds))
+        })
+        .or_else(|| {
+            has_kde_delay(document).then_some(Delay {
+                strategy: DelayStrategy::KdePhase,
+                seconds: None,
+            })
+        })
+}
+
+fn kde_phase(document: &DesktopDocument) -> Option<u32> {
+    document
+        .get(DESKTOP_ENTRY_GROUP, KDE_PHASE_KEY)
+        .and_then(|value| value.trim().parse().ok())
+}
+
+fn has_kde_delay(document: &DesktopDocument) -> bool {
+    kde_phase(document).is_some_and(|phase| phase >= KDE_LATE_PHASE)
+        || document.get(DESKTOP_ENTRY_GROUP, KDE_AFTER_KEY).is_some()
+}
+
+/// Write `exec` and `delay` to the document, removing the keys of every
+/// other in-file strategy so that exactly one encoding remains.
+pub fn apply_delay(document: &mut DesktopDocument, exec: &str, delay: Option<Delay>) {
+    let strategy = delay.map(|d| d.strategy);
+
+    if strategy != Some(DelayStrategy::GnomeKey) {
+        document.remove(DESKTOP_ENTRY_GROUP, GNOME_DELAY_KEY);
+    }
+    if strategy != Some(DelayStrategy::KdePhase) && has_kde_delay(document) {
+        document.remove(DESKTOP_ENTRY_GROUP, KDE_AFTER_KEY);
+        if kde_phase(document).is_some_and(|phase| phase >= KDE_LATE_PHASE) {
+            document.remove(DESKTOP_ENTRY_GROUP, KDE_PHASE_KEY);
+        }
+    }
+
+    let exec = match delay {
+        Some(Delay {
+            strategy: DelayStrategy::ShellWrapper,
+            seconds: Some(seconds),
+        }) => wrap_with_delay(exec, seconds),
+        _ => exec.to_string(),
+    };
+    document.set(DESKTOP_ENTRY_GROUP, "Exec", &escape_value(&exec));
+
+    match delay {
+        Some(Delay {
+            strategy: DelayStrategy::GnomeKey,
+            seconds: Some(seconds),
+        }) => document.set(DESKTOP_ENTRY_GROUP, GNOME_DELAY_KEY, &seconds.to_string()),
+        Some(Delay {
+            strategy: DelayStrategy::KdePhase,
+            ..
+        }) if !has_kde_delay(document) => {
+            document.set(
+                DESKTOP_ENTRY_GROUP,
+                KDE_PHASE_KEY,
+                &KDE_LATE_PHASE.to_string(),
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Combine the current delay of an entry with the requested seconds and
+/// strategy. Changing only the strategy never adds a delay.
+pub fn resolve_delay(
+    current: Option<Delay>,
+    seconds: Option<u32>,
+    strategy: Option<DelayStrategy>,
+) -> Option<Delay> {
+    let seconds = match (seconds, current) {
+        (Some(seconds), _) => seconds,
+        (None, Some(current)) => current.seconds.unwrap_or(DEFAULT_DELAY_SECONDS),
+        (None, None) => return None,
+    };
+    let strategy = strategy
+        .or(current.map(|d| d.strategy))
+        .unwrap_or_else(preferred_delay_strategy);
+
+    Delay::new(strategy, seconds)
+}
+
+/// The strategy used when none is chosen explicitly: the user's preference,
+/// otherwise the native mechanism of the running desktop.
+pub fn preferred_delay_strategy() -> DelayStrategy {
+    Preferences::load()
+        .delay_strategy
+        .unwrap_or_else(|| default_delay_strategy(&get_current_desktop()))
+}
+
+fn default_delay_strategy(current_desktop: &[String]) -> DelayStrategy {
+    if current_desktop.iter().any(|d| d == "GNOME") {
+        DelayStrategy::GnomeKey
     } else {
-        (exec.to_string(), None)
+        DelayStrategy::ShellWrapper
+    }
+}
+
+/// Name of the unit systemd-xdg-autostart-generator creates for an entry.
+pub fn systemd_unit_name(id: &str) -> String {
+    let mut escaped = String::with_capacity(id.len());
+    for (index, c) in id.char_indices() {
+        match c {
+            '/' => escaped.push('-'),
+            '.' if index > 0 => escaped.push(c),
+            c if c.is_ascii_alphanumeric() || c == ':' || c == '_' => escaped.push(c),
+            c => {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    escaped.push_str(&format!("\\x{:02x}", byte));
+                }
+            }
+        }
     }
+    format!("app-{}@autostart.service", escaped)
+}
+
+pub fn systemd_drop_in_path(id: &str) -> PathBuf {
+    XDG_PATHS
+        .systemd_user
+        .join(format!("{}.d", systemd_unit_name(id)))
+        .join(SYSTEMD_DROP_IN)
 }
 
-pub fn get_delay(exec: &str) -> Option<u32> {
-    DELAY_PATTERN
-        .captures(exec)
-        .and_then(|caps| caps[1].parse().ok())
+pub fn read_systemd_delay(id: &str) -> Option<u32> {
+    let content = fs::read_to_string(systemd_drop_in_path(id)).ok()?;
+    parse_systemd_delay(&content)
+}
+
+fn parse_systemd_delay(content: &str) -> Option<u32> {
+    let document = DesktopDocument::parse(content);
+    let args = parse_exec(document.get("Service", "ExecStartPre")?).ok()?;
+    match args.as_slice() {
+        [program, seconds] if program.ends_with("sleep") => seconds.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Create or remove the systemd drop-in so that it matches `delay`.
+pub fn sync_systemd_delay(id: &str, delay: Option<Delay>) -> Result<()> {
+    let path = systemd_drop_in_path(id);
+
+    match delay {
+        Some(Delay {
+            strategy: DelayStrategy::Systemd,
+            seconds: Some(seconds),
+        }) => {
+            // The sleep counts towards the start timeout of the unit
+            let content = format!(
+                "[Service]\nExecStartPre=sleep {}\nTimeoutStartSec=infinity\n",
+                seconds
+            );
+            write_atomic(&path, &content)
+                .with_context(|| format!("Failed to write drop-in: {}", path.display()))?;
+        }
+        _ if path.exists() => {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove drop-in: {}", path.display()))?;
+            if let Some(dir) = path.parent() {
+                fs::remove_dir(dir).ok();
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn delay(strategy: DelayStrategy, seconds: u32) -> Option<Delay> {
+        Delay::new(strategy, seconds)
+    }
+
     #[test]
     fn test_wrap_with_delay() {
         assert_eq!(
             wrap_with_delay("/usr/bin/app", 5),
-            "sh -c 'sleep 5 && exec /usr/bin/app'"
+            r#"sh -c "sleep 5 && exec /usr/bin/app""#
         );
     }
 
@@ -51,7 +260,11 @@ mod tests {
     fn test_wrap_with_quotes() {
         assert_eq!(
             wrap_with_delay("/usr/bin/app --name='test'", 3),
-            "sh -c 'sleep 3 && exec /usr/bin/app --name='\\''test'\\'''"
+            r#"sh -c "sleep 3 && exec /usr/bin/app --name='test'""#
+        );
+        assert_eq!(
+            wrap_with_delay(r#"app --title="My App" "\$HOME""#, 3),
+            r#"sh -c "sleep 3 && exec app --title=\"My App\" \"\\\$HOME\"""#
         );
     }
 
@@ -75,11 +288,111 @@ mod tests {
         let (cmd, delay) = unwrap_delay(&wrapped);
         assert_eq!(cmd, "/usr/bin/app --name='test'");
         assert_eq!(delay, Some(5));
+
+        let exec = r#"app --title="My App" "\$HOME""#;
+        let (cmd, delay) = unwrap_delay(&wrap_with_delay(exec, 7));
+        assert_eq!(cmd, exec);
+        assert_eq!(delay, Some(7));
+    }
+
+    #[test]
+    fn test_unwrap_legacy_single_quotes() {
+        let (cmd, delay) =
+            unwrap_delay(r"sh -c 'sleep 3 && exec /usr/bin/app --name='\''test'\'''");
+        assert_eq!(cmd, "/usr/bin/app --name='test'");
+        assert_eq!(delay, Some(3));
+    }
+
+    #[test]
+    fn test_detect_delay() {
+        let gnome = DesktopDocument::parse("[Desktop Entry]\nX-GNOME-Autostart-Delay=8\n");
+        assert_eq!(
+            detect_delay(&gnome, None),
+            delay(DelayStrategy::GnomeKey, 8)
+        );
+        assert_eq!(
+            detect_delay(&gnome, Some(3)),
+            delay(DelayStrategy::ShellWrapper, 3)
+        );
+
+        let kde = DesktopDocument::parse("[Desktop Entry]\nX-KDE-autostart-after=panel\n");
+        assert_eq!(detect_delay(&kde, None), delay(DelayStrategy::KdePhase, 1));
+
+        let early = DesktopDocument::parse("[Desktop Entry]\nX-KDE-autostart-phase=1\n");
+        assert_eq!(detect_delay(&early, None), None);
     }
 
     #[test]
-    fn test_get_delay() {
-        assert_eq!(get_delay("sh -c 'sleep 15 && exec /usr/bin/app'"), Some(15));
-        assert_eq!(get_delay("/usr/bin/app"), None);
+    fn test_apply_delay_migrates_between_strategies() {
+        let mut document = DesktopDocument::parse(
+            "[Desktop Entry]\nExec=sh -c 'sleep 5 && exec app'\nX-KDE-autostart-phase=1\n",
+        );
+
+        apply_delay(&mut document, "app", delay(DelayStrategy::GnomeKey, 5));
+        assert_eq!(
+            document.to_string(),
+            "[Desktop Entry]\nExec=app\nX-KDE-autostart-phase=1\nX-GNOME-Autostart-Delay=5\n"
+        );
+
+        apply_delay(&mut document, "app", delay(DelayStrategy::KdePhase, 5));
+        assert_eq!(
+            document.to_string(),
+            "[Desktop Entry]\nExec=app\nX-KDE-autostart-phase=2\n"
+        );
+
+        apply_delay(&mut document, "app", delay(DelayStrategy::Systemd, 5));
+        assert_eq!(document.to_string(), "[Desktop Entry]\nExec=app\n");
+
+        apply_delay(&mut document, "app", delay(DelayStrategy::ShellWrapper, 5));
+        assert_eq!(
+            document.to_string(),
+            "[Desktop Entry]\nExec=sh -c \"sleep 5 && exec app\"\n"
+        );
+    }
+
+    #[test]
+    fn test_resolve_delay() {
+        let gnome = delay(DelayStrategy::GnomeKey, 5);
+        let kde = delay(DelayStrategy::KdePhase, 1);
+
+        assert_eq!(resolve_delay(gnome, None, None), gnome);
+        assert_eq!(
+            resolve_delay(gnome, None, Some(DelayStrategy::Systemd)),
+            delay(DelayStrategy::Systemd, 5)
+        );
+        assert_eq!(resolve_delay(gnome, Some(0), None), None);
+        assert_eq!(
+            resolve_delay(kde, None, Some(DelayStrategy::GnomeKey)),
+            delay(DelayStrategy::GnomeKey, DEFAULT_DELAY_SECONDS)
+        );
+        assert_eq!(
+            resolve_delay(None, None, Some(DelayStrategy::GnomeKey)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_systemd_unit_name() {
+        assert_eq!(
+            systemd_unit_name("org.gnome.Software"),
+            "app-org.gnome.Software@autostart.service"
+        );
+        assert_eq!(
+            systemd_unit_name("nm-applet"),
+            "app-nm\\x2dapplet@autostart.service"
+        );
+    }
+
+    #[test]
+    fn test_parse_systemd_delay() {
+        assert_eq!(
+            parse_systemd_delay("[Service]\nExecStartPre=sleep 12\n"),
+            Some(12)
+        );
+        assert_eq!(
+            parse_systemd_delay("[Service]\nExecStartPre=/usr/bin/sleep 4\n"),
+            Some(4)
+        );
+        assert_eq!(parse_systemd_delay("[Service]\nExecStartPre=true\n"), None);
     }
 }
@@ -0,0 +1,35 @@
+use anyhow::{Context, Result, bail};
+
+use crate::model::{AutostartEntry, EntryOrigin};
+use crate::operations::delay::{sync_systemd_delay, systemd_drop_in_path};
+use crate::operations::journal::{self, RecordId};
+use crate::operations::trash::move_to_trash;
+
+/// Move a user autostart file to the trash. Removing a user override makes
+/// the shadowed system entry take effect again; system files themselves
+/// can only be disabled, never removed.
+pub fn delete_autostart_entry(entry: &AutostartEntry) -> Result<Option<RecordId>> {
+    if entry.origin == EntryOrigin::System {
+        bail!(
+            "{} is a system entry and cannot be deleted; disable it instead",
+            entry.id
+        );
+    }
+
+    let (_, record) = journal::record(
+        &format!("Delete {}", entry.desktop_entry.display_name()),
+        &[entry.path.clone(), systemd_drop_in_path(&entry.id)],
+        || {
+            move_to_trash(&entry.path)
+                .with_context(|| format!("Failed to delete entry: {}", entry.path.display()))?;
+
+            // A systemd delay belongs to the removed file, not to a system
+            // entry that may become visible again
+            sync_systemd_delay(&entry.id, None)
+        },
+    )?;
+
+    tracing::info!("Deleted autostart entry: {}", entry.path.display());
+
+    Ok(record)
+}
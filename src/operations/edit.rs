@@ -1,24 +1,108 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 
-use crate::desktop_entry::EntryChanges;
-use crate::desktop_entry::writer::{update_desktop_entry_content, write_atomic};
+use crate::desktop_entry::lint::{Severity, lint_desktop_file};
+use crate::desktop_entry::writer::{
+    normalize_exec, update_desktop_entry_content, validate_environment, validate_show_in,
+    validate_working_dir, write_atomic,
+};
+use crate::desktop_entry::{DesktopEntry, EntryChanges, LocalizedString, unified_diff};
 use crate::model::AutostartEntry;
-use crate::operations::delay::unwrap_delay;
+use crate::operations::delay::{resolve_delay, sync_systemd_delay, systemd_drop_in_path};
+use crate::operations::journal::{self, RecordId};
 
-pub fn edit_autostart_entry(entry: &AutostartEntry, changes: EntryChanges) -> Result<()> {
+/// Write `changes` to the user copy of `entry`, returning the journal record
+/// that undoes them.
+pub fn edit_autostart_entry(
+    entry: &AutostartEntry,
+    changes: EntryChanges,
+) -> Result<Option<RecordId>> {
+    let (updated_entry, new_content) = apply_changes(entry, changes)?;
+
+    let path = entry.user_path();
+
+    let (_, record) = journal::record(
+        &format!("Edit {}", entry.desktop_entry.display_name()),
+        &[path.clone(), systemd_drop_in_path(&entry.id)],
+        || {
+            write_atomic(&path, &new_content)
+                .with_context(|| format!("Failed to save entry: {}", path.display()))?;
+
+            sync_systemd_delay(&entry.id, updated_entry.delay)
+        },
+    )?;
+
+    tracing::info!("Updated autostart entry: {}", path.display());
+
+    Ok(record)
+}
+
+/// Replace the file of `entry` with hand-written `content`. Refused when the
+/// linter finds errors, since the session would skip the file at login.
+pub fn edit_autostart_source(entry: &AutostartEntry, content: &str) -> Result<Option<RecordId>> {
+    if let Some(error) = lint_desktop_file(content)
+        .into_iter()
+        .find(|d| d.severity == Severity::Error)
+    {
+        bail!("Not a valid autostart entry: {}", error);
+    }
+
+    let path = entry.user_path();
+
+    let (_, record) = journal::record(
+        &format!("Edit source of {}", entry.desktop_entry.display_name()),
+        std::slice::from_ref(&path),
+        || {
+            write_atomic(&path, content)
+                .with_context(|| format!("Failed to save entry: {}", path.display()))
+        },
+    )?;
+
+    tracing::info!("Replaced source of autostart entry: {}", path.display());
+
+    Ok(record)
+}
+
+/// Unified diff of what [`edit_autostart_entry`] would write, without
+/// writing it. Empty when the file would not change.
+pub fn preview_edit(entry: &AutostartEntry, changes: EntryChanges) -> Result<String> {
+    let (_, new_content) = apply_changes(entry, changes)?;
+
+    Ok(unified_diff(
+        &entry.raw_content,
+        &new_content,
+        &entry.path.display().to_string(),
+        &entry.user_path().display().to_string(),
+    ))
+}
+
+/// The edited entry and the file content it is written as.
+fn apply_changes(entry: &AutostartEntry, changes: EntryChanges) -> Result<(DesktopEntry, String)> {
     let mut updated_entry = entry.desktop_entry.clone();
 
+    let localize = |value: String| {
+        changes.locale.as_ref().map(|locale| LocalizedString {
+            locale: locale.clone(),
+            value,
+        })
+    };
+
     if let Some(name) = changes.name {
-        updated_entry.name = name;
+        match localize(name.clone()) {
+            Some(localized) => updated_entry.localized_name = Some(localized),
+            None => updated_entry.name = name,
+        }
     }
     if let Some(exec) = changes.exec {
-        updated_entry.exec = exec;
+        updated_entry.exec = normalize_exec(&exec)?;
     }
     if let Some(comment) = changes.comment {
-        updated_entry.comment = Some(comment);
+        match localize(comment.clone()) {
+            Some(localized) => updated_entry.localized_comment = Some(localized),
+            None => updated_entry.comment = Some(comment),
+        }
     }
     if let Some(icon) = changes.icon {
-        updated_entry.icon = Some(icon);
+        updated_entry.icon = (!icon.is_empty()).then_some(icon);
     }
     if let Some(hidden) = changes.hidden {
         updated_entry.hidden = hidden;
@@ -26,24 +110,111 @@ pub fn edit_autostart_entry(entry: &AutostartEntry, changes: EntryChanges) -> Re
     if let Some(terminal) = changes.terminal {
         updated_entry.terminal = terminal;
     }
+    if let Some(environment) = changes.environment {
+        validate_environment(&environment)?;
+        updated_entry.environment = environment;
+    }
+    if let Some(working_dir) = changes.working_dir {
+        if let Some(ref dir) = working_dir {
+            validate_working_dir(dir)?;
+        }
+        updated_entry.working_dir = working_dir;
+    }
+    // Files that already conflict can still be edited otherwise
+    let show_in_changed = changes.only_show_in.is_some() || changes.not_show_in.is_some();
     if let Some(only_show_in) = changes.only_show_in {
         updated_entry.only_show_in = only_show_in;
     }
     if let Some(not_show_in) = changes.not_show_in {
         updated_entry.not_show_in = not_show_in;
     }
+    if let Some(condition) = changes.autostart_condition {
+        updated_entry.autostart_condition = condition;
+    }
+    if let Some(auto_restart) = changes.auto_restart {
+        updated_entry.auto_restart = auto_restart;
+    }
 
-    let delay = changes.delay_seconds.or_else(|| {
-        let (_, existing_delay) = unwrap_delay(&entry.desktop_entry.exec);
-        existing_delay
-    });
+    if show_in_changed {
+        validate_show_in(&updated_entry.only_show_in, &updated_entry.not_show_in)?;
+    }
+
+    updated_entry.delay = resolve_delay(
+        entry.desktop_entry.delay,
+        changes.delay_seconds,
+        changes.delay_strategy,
+    );
 
-    let new_content = update_desktop_entry_content(&entry.raw_content, &updated_entry, delay);
+    let new_content = update_desktop_entry_content(&entry.raw_content, &updated_entry);
+
+    Ok((updated_entry, new_content))
+}
 
-    write_atomic(&entry.path, &new_content)
-        .with_context(|| format!("Failed to save entry: {}", entry.path.display()))?;
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
 
-    tracing::info!("Updated autostart entry: {}", entry.path.display());
+    use super::*;
+    use crate::desktop_entry::parser::parse_desktop_file;
+    use crate::model::EntryOrigin;
 
-    Ok(())
+    #[test]
+    fn test_preview_edit() {
+        let content = "[Desktop Entry]\nType=Application\nName=Notes\nComment=Take notes\nIcon=notes\nExec=notes\nX-Custom=kept\n";
+        let entry = AutostartEntry::new(
+            "notes".to_string(),
+            PathBuf::from("/home/u/.config/autostart/notes.desktop"),
+            parse_desktop_file(content).unwrap(),
+            content.to_string(),
+            EntryOrigin::User,
+        );
+
+        let unchanged = preview_edit(&entry, EntryChanges::default()).unwrap();
+        let diff = preview_edit(
+            &entry,
+            EntryChanges {
+                comment: Some(String::new()),
+                icon: Some(String::new()),
+                terminal: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(unchanged, "");
+        assert!(diff.contains("\n-Comment=Take notes\n"));
+        assert!(diff.contains("\n-Icon=notes\n"));
+        assert!(diff.contains("\n+Terminal=true\n"));
+        assert!(diff.contains("\n X-Custom=kept\n"));
+
+        let error = edit_autostart_source(
+            &entry,
+            "[Desktop Entry]\nType=Link\nName=Notes\nExec=notes\n",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.starts_with("Not a valid autostart entry: 2: error[unsupported-type]"));
+    }
+
+    #[test]
+    fn test_edit_working_dir() {
+        let content = "[Desktop Entry]\nType=Application\nName=Server\nExec=./serve\n";
+        let entry = AutostartEntry::new(
+            "server".to_string(),
+            PathBuf::from("/home/u/.config/autostart/server.desktop"),
+            parse_desktop_file(content).unwrap(),
+            content.to_string(),
+            EntryOrigin::User,
+        );
+        let changes = |dir: &str| EntryChanges {
+            working_dir: Some(Some(dir.to_string())),
+            ..Default::default()
+        };
+
+        let diff = preview_edit(&entry, changes("/srv/my app")).unwrap();
+        let relative = preview_edit(&entry, changes("~/srv"));
+
+        assert!(diff.contains("\n+Path=/srv/my app\n"));
+        assert!(relative.is_err());
+    }
 }
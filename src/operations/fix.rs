@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::XDG_PATHS;
+use crate::desktop_entry::fix::{Fix, fix_content};
+use crate::desktop_entry::unified_diff;
+use crate::desktop_entry::writer::write_atomic;
+use crate::discovery::{discover_autostart_entries, discover_broken_entries};
+use crate::operations::journal::{self, RecordId};
+
+/// Repairs for one file, computed up front so they can be reviewed before
+/// anything is written.
+#[derive(Debug, Clone)]
+pub struct FixPlan {
+    pub source: PathBuf,
+    /// Where the fixed file goes; a user override for system files.
+    pub target: PathBuf,
+    pub original: String,
+    pub fixed: String,
+    pub applied: Vec<Fix>,
+}
+
+impl FixPlan {
+    pub fn diff(&self) -> String {
+        unified_diff(
+            &self.original,
+            &self.fixed,
+            &self.source.display().to_string(),
+            &self.target.display().to_string(),
+        )
+    }
+}
+
+/// What `fixes` would change in `source`, or `None` if nothing.
+pub fn plan_fix(source: &Path, target: &Path, fixes: &[Fix]) -> Result<Option<FixPlan>> {
+    let original = std::fs::read_to_string(source)
+        .with_context(|| format!("Failed to read {}", source.display()))?;
+    let (fixed, applied) = fix_content(&original, fixes);
+
+    if applied.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(FixPlan {
+        source: source.to_path_buf(),
+        target: target.to_path_buf(),
+        original,
+        fixed,
+        applied,
+    }))
+}
+
+/// Plans for every autostart file, including broken ones. Fixes to system
+/// files are written as user overrides.
+pub fn plan_autostart_fixes(fixes: &[Fix]) -> Result<Vec<FixPlan>> {
+    let files = discover_autostart_entries()?
+        .into_iter()
+        .map(|e| (e.path, e.id))
+        .chain(
+            discover_broken_entries()?
+                .into_iter()
+                .map(|b| (b.path, b.id)),
+        );
+
+    let mut plans = Vec::new();
+    for (path, id) in files {
+        let target = XDG_PATHS.user_autostart.join(format!("{}.desktop", id));
+        plans.extend(plan_fix(&path, &target, fixes)?);
+    }
+    plans.sort_by(|a, b| a.target.cmp(&b.target));
+
+    Ok(plans)
+}
+
+/// Write a reviewed plan. Fails if the file changed since it was planned,
+/// so that nothing is written that was not in the preview.
+pub fn apply_fix(plan: &FixPlan) -> Result<Option<RecordId>> {
+    let current = std::fs::read_to_string(&plan.source)
+        .with_context(|| format!("Failed to read {}", plan.source.display()))?;
+    if current != plan.original {
+        bail!(
+            "{} changed since the fix was previewed",
+            plan.source.display()
+        );
+    }
+
+    let file_name = plan
+        .target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let (_, record) = journal::record(
+        &format!("Fix {}", file_name),
+        std::slice::from_ref(&plan.target),
+        || {
+            write_atomic(&plan.target, &plan.fixed)
+                .with_context(|| format!("Failed to write {}", plan.target.display()))
+        },
+    )?;
+
+    tracing::info!(
+        "Fixed {}: {}",
+        plan.target.display(),
+        plan.applied
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_fix_to_override() {
+        let dir = std::env::temp_dir().join("onset_test_fix");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("system.desktop");
+        let target = dir.join("user.desktop");
+        std::fs::write(&source, "[Desktop Entry]\nName=App\nExec=app\n").unwrap();
+
+        let plan = plan_fix(&source, &target, &Fix::DEFAULT).unwrap().unwrap();
+        let diff = plan.diff();
+        std::fs::write(&source, "[Desktop Entry]\nName=Changed\nExec=app\n").unwrap();
+        let stale = apply_fix(&plan);
+        let target_exists = target.exists();
+        std::fs::write(&target, &plan.fixed).unwrap();
+        let nothing = plan_fix(&target, &target, &Fix::DEFAULT).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(plan.applied, vec![Fix::MissingType]);
+        assert!(diff.ends_with(" Exec=app\n+Type=Application\n"));
+        assert!(stale.is_err());
+        assert!(!target_exists);
+        assert!(nothing.is_none());
+    }
+}
@@ -0,0 +1,513 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{XDG_PATHS, get_current_desktop};
+use crate::desktop_entry::exec::{parse_exec, quote_exec, remove_field_codes};
+use crate::desktop_entry::writer::write_atomic;
+use crate::desktop_entry::{CreateOptions, EffectiveState};
+use crate::discovery::find_autostart_entry;
+use crate::model::{AutostartEntry, EntryOrigin};
+use crate::operations::delay::systemd_unit_name;
+use crate::operations::{create_autostart_entry, delete_autostart_entry};
+
+/// Oldest reports are dropped once the history grows past this.
+const MAX_REPORTS: usize = 30;
+
+/// ID of the entry that writes a report at every login.
+pub const REPORT_ENTRY_ID: &str = "onset-session-report";
+
+/// Time the slowest entry gets to start before the report is written at
+/// login.
+const LOGIN_GRACE: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LaunchResult {
+    Running,
+    /// Started and exited successfully.
+    Exited,
+    Failed,
+    /// No trace of it was found.
+    Missing,
+    /// No trace of an entry whose `AutostartCondition` is only decided at
+    /// login; the condition may have been false.
+    Skipped,
+}
+
+impl LaunchResult {
+    /// Whether the entry did what was expected of it.
+    pub fn is_ok(self) -> bool {
+        !matches!(self, LaunchResult::Failed | LaunchResult::Missing)
+    }
+}
+
+impl std::fmt::Display for LaunchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LaunchResult::Running => write!(f, "Running"),
+            LaunchResult::Exited => write!(f, "Exited"),
+            LaunchResult::Failed => write!(f, "Failed"),
+            LaunchResult::Missing => write!(f, "Not running"),
+            LaunchResult::Skipped => write!(f, "Not started"),
+        }
+    }
+}
+
+/// How one entry fared at a login.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchRecord {
+    pub id: String,
+    pub result: LaunchResult,
+    /// "PID 1234", "exited with status 1", ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl std::fmt::Display for LaunchRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.detail {
+            Some(ref detail) => write!(f, "{} ({})", self.result, detail),
+            None => write!(f, "{}", self.result),
+        }
+    }
+}
+
+/// The entries that were expected to start at one login.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionReport {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub desktop: Vec<String>,
+    pub launches: Vec<LaunchRecord>,
+}
+
+impl SessionReport {
+    pub fn launch(&self, id: &str) -> Option<&LaunchRecord> {
+        self.launches.iter().find(|launch| launch.id == id)
+    }
+}
+
+fn history_path() -> PathBuf {
+    XDG_PATHS.onset_state.join("history")
+}
+
+/// Check every entry that should have started in this session and append
+/// the result to the history.
+pub fn write_session_report(entries: &[AutostartEntry]) -> Result<SessionReport> {
+    let processes = running_processes();
+    let launches = entries
+        .iter()
+        .filter(|entry| entry.effective_state.may_start() && entry.id != REPORT_ENTRY_ID)
+        .map(|entry| check_launch(entry, &processes))
+        .collect();
+
+    let report = SessionReport {
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        desktop: get_current_desktop(),
+        launches,
+    };
+
+    append_report(&history_path(), &report)?;
+
+    Ok(report)
+}
+
+/// How long to wait at login so that delayed entries had their chance.
+pub fn login_wait(entries: &[AutostartEntry]) -> Duration {
+    let longest_delay = entries
+        .iter()
+        .filter(|entry| entry.effective_state.may_start())
+        .filter_map(|entry| entry.delay_seconds())
+        .max()
+        .unwrap_or(0);
+
+    Duration::from_secs(longest_delay.into()) + LOGIN_GRACE
+}
+
+/// Reports from oldest to newest.
+pub fn session_reports() -> Result<Vec<SessionReport>> {
+    read_reports(&history_path())
+}
+
+pub fn last_session_report() -> Option<SessionReport> {
+    session_reports().ok()?.pop()
+}
+
+/// Add an autostart entry that writes a report at every login.
+pub fn install_report_entry() -> Result<PathBuf> {
+    if let Some(entry) = find_autostart_entry(REPORT_ENTRY_ID)? {
+        bail!("Already installed: {}", entry.path.display());
+    }
+
+    let program = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(String::from))
+        .unwrap_or_else(|| "onset".to_string());
+    let exec = quote_exec(&[
+        program,
+        "session-report".to_string(),
+        "--at-login".to_string(),
+    ]);
+
+    create_autostart_entry(
+        REPORT_ENTRY_ID,
+        "Onset Session Report",
+        &exec,
+        CreateOptions {
+            comment: Some("Records which autostart entries started".to_string()),
+            ..Default::default()
+        },
+    )
+    .map(|(path, _)| path)
+}
+
+pub fn uninstall_report_entry() -> Result<()> {
+    match find_autostart_entry(REPORT_ENTRY_ID)? {
+        Some(entry) if entry.origin == EntryOrigin::User => {
+            delete_autostart_entry(&entry)?;
+            Ok(())
+        }
+        _ => bail!("The session report is not installed"),
+    }
+}
+
+/// Ask systemd first, for sessions that start entries as
+/// `app-<id>@autostart.service`, then look for a matching process.
+fn check_launch(entry: &AutostartEntry, processes: &[(u32, Vec<String>)]) -> LaunchRecord {
+    let record = |result, detail: Option<String>| LaunchRecord {
+        id: entry.id.clone(),
+        result,
+        detail,
+    };
+
+    if let Some(properties) = unit_properties(&systemd_unit_name(&entry.id))
+        && let Some((result, detail)) = unit_launch(&properties)
+    {
+        return record(result, detail);
+    }
+
+    let command = parse_exec(&entry.desktop_entry.exec)
+        .ok()
+        .map(remove_field_codes)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|arg| arg.replace("%%", "%"))
+        .collect::<Vec<_>>();
+    let pid = processes
+        .iter()
+        .find(|(_, cmdline)| runs_command(cmdline, &command))
+        .map(|(pid, _)| *pid);
+
+    match pid {
+        Some(pid) => record(LaunchResult::Running, Some(format!("PID {}", pid))),
+        None if entry.effective_state == EffectiveState::Conditional => record(
+            LaunchResult::Skipped,
+            Some("no process found; its condition may have been false".to_string()),
+        ),
+        None => record(
+            LaunchResult::Missing,
+            Some("no process found; it may have exited".to_string()),
+        ),
+    }
+}
+
+fn unit_properties(unit: &str) -> Option<HashMap<String, String>> {
+    let output = Command::new("systemctl")
+        .args([
+            "--user",
+            "show",
+            "--property=LoadState,ActiveState,Result,ExecMainPID,ExecMainStatus,ExecMainCode,ExecMainStartTimestampMonotonic",
+            unit,
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    )
+}
+
+/// Result of a unit as `systemctl show` describes it; `None` if the unit
+/// does not exist.
+fn unit_launch(properties: &HashMap<String, String>) -> Option<(LaunchResult, Option<String>)> {
+    let property = |key: &str| properties.get(key).map(String::as_str).unwrap_or("");
+
+    if property("LoadState") != "loaded" {
+        return None;
+    }
+
+    let launch = match property("ActiveState") {
+        "active" | "activating" | "reloading" | "deactivating" => {
+            let detail = match property("ExecMainPID") {
+                "" | "0" => None,
+                pid => Some(format!("PID {}", pid)),
+            };
+            (LaunchResult::Running, detail)
+        }
+        "failed" => {
+            // ExecMainCode is the si_code: 1 exited, 2 killed, 3 dumped core
+            let detail = match (property("ExecMainCode"), property("Result")) {
+                ("1", _) => format!("exited with status {}", property("ExecMainStatus")),
+                ("2" | "3", _) => format!("was killed by signal {}", property("ExecMainStatus")),
+                (_, result) => format!("unit failed: {}", result),
+            };
+            (LaunchResult::Failed, Some(detail))
+        }
+        _ if matches!(property("ExecMainStartTimestampMonotonic"), "" | "0") => (
+            LaunchResult::Missing,
+            Some("the unit was not started".to_string()),
+        ),
+        _ => (LaunchResult::Exited, None),
+    };
+
+    Some(launch)
+}
+
+/// PIDs and command lines of all processes that can be read.
+fn running_processes() -> Vec<(u32, Vec<String>)> {
+    let Ok(read_dir) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let own_pid = std::process::id();
+    read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            if pid == own_pid {
+                return None;
+            }
+            let cmdline = std::fs::read(entry.path().join("cmdline")).ok()?;
+            let args = cmdline
+                .split(|&b| b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect::<Vec<_>>();
+            (!args.is_empty()).then_some((pid, args))
+        })
+        .collect()
+}
+
+/// Whether a process runs `command`: the same program with at least its
+/// arguments, directly or as the script of an interpreter (`python3
+/// /usr/bin/program`). Comparing the arguments keeps `sh -c …` or
+/// `flatpak run …` from matching every shell or Flatpak app.
+fn runs_command(cmdline: &[String], command: &[String]) -> bool {
+    let Some((program, args)) = command.split_first() else {
+        return false;
+    };
+    let runs_at = |index: usize| {
+        cmdline
+            .get(index)
+            .is_some_and(|arg| file_name(arg) == file_name(program))
+            && cmdline[index + 1..].starts_with(args)
+    };
+
+    runs_at(0) || (cmdline.first().is_some_and(|arg| is_interpreter(arg)) && runs_at(1))
+}
+
+/// `python3`, `/usr/bin/perl`, ...
+fn is_interpreter(program: &str) -> bool {
+    const INTERPRETERS: &[&str] = &[
+        "sh", "bash", "dash", "python", "perl", "ruby", "node", "gjs",
+    ];
+    let name = file_name(program).trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    INTERPRETERS.contains(&name)
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn append_report(history: &Path, report: &SessionReport) -> Result<()> {
+    let mut reports = read_reports(history)?;
+    reports.push(report.clone());
+
+    let excess = reports.len().saturating_sub(MAX_REPORTS);
+    reports.drain(..excess);
+
+    let mut content = String::new();
+    for report in &reports {
+        content.push_str(&serde_json::to_string(report)?);
+        content.push('\n');
+    }
+
+    write_atomic(history, &content)
+        .with_context(|| format!("Failed to write history: {}", history.display()))
+}
+
+fn read_reports(history: &Path) -> Result<Vec<SessionReport>> {
+    let content = match std::fs::read_to_string(history) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", history.display()));
+        }
+    };
+
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            serde_json::from_str(line)
+                .inspect_err(|e| tracing::warn!("Skipping damaged session report: {}", e))
+                .ok()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_unit_launch() {
+        assert_eq!(
+            unit_launch(&properties(&[("LoadState", "not-found")])),
+            None
+        );
+        assert_eq!(
+            unit_launch(&properties(&[
+                ("LoadState", "loaded"),
+                ("ActiveState", "active"),
+                ("ExecMainPID", "812"),
+            ])),
+            Some((LaunchResult::Running, Some("PID 812".to_string())))
+        );
+        assert_eq!(
+            unit_launch(&properties(&[
+                ("LoadState", "loaded"),
+                ("ActiveState", "failed"),
+                ("Result", "exit-code"),
+                ("ExecMainCode", "1"),
+                ("ExecMainStatus", "2"),
+            ])),
+            Some((
+                LaunchResult::Failed,
+                Some("exited with status 2".to_string())
+            ))
+        );
+        assert_eq!(
+            unit_launch(&properties(&[
+                ("LoadState", "loaded"),
+                ("ActiveState", "inactive"),
+                ("ExecMainStartTimestampMonotonic", "5403921"),
+            ])),
+            Some((LaunchResult::Exited, None))
+        );
+        assert_eq!(
+            unit_launch(&properties(&[
+                ("LoadState", "loaded"),
+                ("ActiveState", "inactive"),
+                ("ExecMainStartTimestampMonotonic", "0"),
+            ]))
+            .map(|(result, _)| result),
+            Some(LaunchResult::Missing)
+        );
+    }
+
+    #[test]
+    fn test_runs_command() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert!(runs_command(
+            &args(&["/usr/bin/nm-applet", "--indicator"]),
+            &args(&["nm-applet"])
+        ));
+        assert!(runs_command(
+            &args(&["python3.12", "/usr/bin/blueman-applet"]),
+            &args(&["/usr/bin/blueman-applet"])
+        ));
+        assert!(runs_command(
+            &args(&["sh", "-c", "sync-notes ~/Notes"]),
+            &args(&["sh", "-c", "sync-notes ~/Notes"])
+        ));
+        assert!(!runs_command(
+            &args(&["/bin/sh", "-c", "something else"]),
+            &args(&["sh", "-c", "sync-notes ~/Notes"])
+        ));
+        assert!(!runs_command(
+            &args(&["flatpak", "run", "org.example.Other"]),
+            &args(&["flatpak", "run", "org.example.App"])
+        ));
+        assert!(!runs_command(
+            &args(&["vim", "nm-applet"]),
+            &args(&["nm-applet"])
+        ));
+        assert!(!runs_command(&args(&["nm-applet"]), &[]));
+    }
+
+    #[test]
+    fn test_check_launch_of_conditional_entry() {
+        let content = "[Desktop Entry]\nType=Application\nName=Orca\nExec=onset-test-orca\nAutostartCondition=GSettings org.gnome.desktop.a11y.applications screen-reader-enabled\n";
+        let entry = AutostartEntry::new(
+            "onset-test-orca".to_string(),
+            PathBuf::from("/tmp/onset-test-orca.desktop"),
+            crate::desktop_entry::parser::parse_desktop_file(content).unwrap(),
+            content.to_string(),
+            EntryOrigin::User,
+        );
+
+        assert_eq!(entry.effective_state, EffectiveState::Conditional);
+        assert_eq!(check_launch(&entry, &[]).result, LaunchResult::Skipped);
+        assert_eq!(
+            check_launch(&entry, &[(42, vec!["onset-test-orca".to_string()])]).result,
+            LaunchResult::Running
+        );
+    }
+
+    #[test]
+    fn test_append_report_keeps_recent() {
+        let dir = std::env::temp_dir().join("onset_test_history");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let history = dir.join("history");
+
+        for timestamp in 0..MAX_REPORTS as u64 + 2 {
+            let report = SessionReport {
+                timestamp,
+                desktop: vec!["GNOME".to_string()],
+                launches: vec![LaunchRecord {
+                    id: "app".to_string(),
+                    result: LaunchResult::Running,
+                    detail: None,
+                }],
+            };
+            append_report(&history, &report).unwrap();
+        }
+        let reports = read_reports(&history).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(reports.len(), MAX_REPORTS);
+        assert_eq!(reports.first().map(|r| r.timestamp), Some(2));
+        assert_eq!(
+            reports
+                .last()
+                .and_then(|r| r.launch("app"))
+                .map(|l| l.result),
+            Some(LaunchResult::Running)
+        );
+    }
+}
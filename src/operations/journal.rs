@@ -0,0 +1,380 @@
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::config::XDG_PATHS;
+use crate::desktop_entry::writer::write_atomic;
+
+/// Identifies a journal record, for undoing that one change.
+pub type RecordId = u64;
+
+/// Oldest records are dropped once the journal grows past this.
+const MAX_RECORDS: usize = 100;
+
+thread_local! {
+    /// Set while an operation is being recorded. Operations built from
+    /// other operations record once, as a whole.
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+
+    /// Files outside the recorded paths that the operation created, such as
+    /// copies moved to the trash.
+    static CREATED: RefCell<Vec<PathBuf>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Content of a file before an operation; `None` if it did not exist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    pub path: PathBuf,
+    pub content: Option<String>,
+}
+
+impl FileSnapshot {
+    fn take(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+            }
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            content,
+        })
+    }
+
+    fn restore(&self) -> Result<()> {
+        match self.content {
+            Some(ref content) => write_atomic(&self.path, content),
+            None => match std::fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(e).with_context(|| format!("Failed to remove {}", self.path.display()))
+                }
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+/// One mutation of the autostart configuration and the files it touched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalRecord {
+    pub id: RecordId,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub description: String,
+    pub files: Vec<FileSnapshot>,
+}
+
+fn journal_path() -> PathBuf {
+    XDG_PATHS.onset_state.join("journal.jsonl")
+}
+
+/// Run `operation`, remembering the previous content of `paths` so that it
+/// can be undone later. Returns the ID of the record written, or `None`
+/// when nothing was recorded: the journal is best effort, and failing to
+/// update it is logged but does not fail the operation.
+///
+/// Nested calls are not recorded separately, so `paths` must cover every
+/// file the operation and the operations it calls may change.
+pub fn record<T>(
+    description: &str,
+    paths: &[PathBuf],
+    operation: impl FnOnce() -> Result<T>,
+) -> Result<(T, Option<RecordId>)> {
+    record_in(&journal_path(), description, paths, operation)
+}
+
+/// Note that the operation being recorded created `path`, so that undoing
+/// it removes the file again. Does nothing outside a recorded operation.
+pub fn note_created(path: &Path) {
+    if RECORDING.get() {
+        CREATED.with_borrow_mut(|created| created.push(path.to_path_buf()));
+    }
+}
+
+/// Records from oldest to newest.
+pub fn journal_records() -> Result<Vec<JournalRecord>> {
+    read_records(&journal_path())
+}
+
+/// Revert a single operation, such as the one a toast offered to undo.
+pub fn undo(id: RecordId) -> Result<JournalRecord> {
+    undo_in(&journal_path(), id)
+}
+
+/// Revert the `count` most recent operations, newest first.
+pub fn undo_last(count: usize) -> Result<Vec<JournalRecord>> {
+    undo_last_in(&journal_path(), count)
+}
+
+fn record_in<T>(
+    journal: &Path,
+    description: &str,
+    paths: &[PathBuf],
+    operation: impl FnOnce() -> Result<T>,
+) -> Result<(T, Option<RecordId>)> {
+    if RECORDING.get() {
+        return Ok((operation()?, None));
+    }
+
+    let mut snapshots = paths
+        .iter()
+        .map(|path| FileSnapshot::take(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    CREATED.take();
+    RECORDING.set(true);
+    let result = operation();
+    RECORDING.set(false);
+    let created = CREATED.take();
+    let result = result?;
+
+    // Reverted first, as the snapshots are restored in reverse
+    snapshots.extend(created.into_iter().map(|path| FileSnapshot {
+        path,
+        content: None,
+    }));
+
+    let id = append_record(journal, description, snapshots)
+        .inspect_err(|e| tracing::warn!("Failed to update journal {}: {:#}", journal.display(), e))
+        .ok();
+
+    Ok((result, id))
+}
+
+fn append_record(journal: &Path, description: &str, files: Vec<FileSnapshot>) -> Result<RecordId> {
+    let mut records = read_records(journal)?;
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let id = records.last().map_or(1, |r| r.id + 1);
+    records.push(JournalRecord {
+        id,
+        timestamp,
+        description: description.to_string(),
+        files,
+    });
+
+    let excess = records.len().saturating_sub(MAX_RECORDS);
+    records.drain(..excess);
+
+    write_records(journal, &records)?;
+    Ok(id)
+}
+
+fn undo_in(journal: &Path, id: RecordId) -> Result<JournalRecord> {
+    let mut records = read_records(journal)?;
+
+    let Some(index) = records.iter().position(|r| r.id == id) else {
+        bail!("Nothing to undo: the change is no longer in the journal");
+    };
+
+    revert(&records[index])?;
+    let record = records.remove(index);
+    write_records(journal, &records)?;
+
+    Ok(record)
+}
+
+fn undo_last_in(journal: &Path, count: usize) -> Result<Vec<JournalRecord>> {
+    let mut records = read_records(journal)?;
+
+    if records.is_empty() {
+        bail!("Nothing to undo");
+    }
+
+    let mut undone = Vec::new();
+    while undone.len() < count {
+        let Some(record) = records.pop() else {
+            break;
+        };
+
+        // Keep the journal in sync with what was actually reverted
+        if let Err(e) = revert(&record) {
+            records.push(record);
+            write_records(journal, &records)?;
+            return Err(e);
+        }
+        undone.push(record);
+    }
+
+    write_records(journal, &records)?;
+
+    Ok(undone)
+}
+
+fn revert(record: &JournalRecord) -> Result<()> {
+    for snapshot in record.files.iter().rev() {
+        snapshot
+            .restore()
+            .with_context(|| format!("Failed to undo \"{}\"", record.description))?;
+    }
+
+    tracing::info!("Undid \"{}\"", record.description);
+
+    Ok(())
+}
+
+fn read_records(journal: &Path) -> Result<Vec<JournalRecord>> {
+    let content = match std::fs::read_to_string(journal) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", journal.display()));
+        }
+    };
+
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            serde_json::from_str(line)
+                .inspect_err(|e| tracing::warn!("Skipping damaged journal record: {}", e))
+                .ok()
+        })
+        .collect())
+}
+
+fn write_records(journal: &Path, records: &[JournalRecord]) -> Result<()> {
+    let mut content = String::new();
+    for record in records {
+        content.push_str(&serde_json::to_string(record)?);
+        content.push('\n');
+    }
+
+    write_atomic(journal, &content)
+        .with_context(|| format!("Failed to write journal: {}", journal.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_undo() {
+        let dir = std::env::temp_dir().join("onset_test_journal");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let journal = dir.join("journal.jsonl");
+        let existing = dir.join("a.desktop");
+        let created = dir.join("b.desktop");
+        std::fs::write(&existing, "old").unwrap();
+
+        record_in(&journal, "Edit A", std::slice::from_ref(&existing), || {
+            Ok(std::fs::write(&existing, "new")?)
+        })
+        .unwrap();
+        record_in(&journal, "Create B", std::slice::from_ref(&created), || {
+            Ok(std::fs::write(&created, "b")?)
+        })
+        .unwrap();
+        let failed = record_in(
+            &journal,
+            "Fail",
+            std::slice::from_ref(&existing),
+            || -> Result<()> { bail!("failed") },
+        );
+        let records = read_records(&journal).unwrap();
+
+        let undone = undo_last_in(&journal, 5).unwrap();
+        let existing_content = std::fs::read_to_string(&existing).unwrap();
+        let created_exists = created.exists();
+        let nothing_left = undo_last_in(&journal, 1);
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(failed.is_err());
+        assert_eq!(records.iter().map(|r| r.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            undone
+                .iter()
+                .map(|r| r.description.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Create B", "Edit A"]
+        );
+        assert_eq!(existing_content, "old");
+        assert!(!created_exists);
+        assert!(nothing_left.is_err());
+    }
+
+    #[test]
+    fn test_undo_by_id_keeps_other_records() {
+        let dir = std::env::temp_dir().join("onset_test_journal_id");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let journal = dir.join("journal.jsonl");
+        let file = dir.join("a.desktop");
+
+        record_in(&journal, "Create A", std::slice::from_ref(&file), || {
+            Ok(std::fs::write(&file, "a")?)
+        })
+        .unwrap();
+        record_in(&journal, "Other", &[], || Ok(())).unwrap();
+
+        let undone = undo_in(&journal, 1).unwrap();
+        let remaining = read_records(&journal).unwrap();
+        let again = undo_in(&journal, 1);
+        let file_exists = file.exists();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(undone.description, "Create A");
+        assert!(!file_exists);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].description, "Other");
+        assert!(again.is_err());
+    }
+
+    #[test]
+    fn test_undo_removes_created_files() {
+        let dir = std::env::temp_dir().join("onset_test_journal_created");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let journal = dir.join("journal.jsonl");
+        let file = dir.join("a.desktop");
+        let trashed = dir.join("a.desktop.trashed");
+        std::fs::write(&file, "a").unwrap();
+
+        note_created(&dir.join("unrecorded"));
+        let (_, id) = record_in(&journal, "Delete A", std::slice::from_ref(&file), || {
+            std::fs::rename(&file, &trashed)?;
+            note_created(&trashed);
+            Ok(())
+        })
+        .unwrap();
+        undo_in(&journal, id.unwrap()).unwrap();
+
+        let restored = std::fs::read_to_string(&file).unwrap();
+        let trashed_exists = trashed.exists();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(restored, "a");
+        assert!(!trashed_exists);
+    }
+
+    #[test]
+    fn test_nested_operations_are_recorded_once() {
+        let dir = std::env::temp_dir().join("onset_test_journal_nested");
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        let journal = dir.join("journal.jsonl");
+        let file = dir.join("a.desktop");
+
+        let (inner, outer) = record_in(&journal, "Outer", std::slice::from_ref(&file), || {
+            record_in(&journal, "Inner", &[], || Ok(std::fs::write(&file, "a")?))
+        })
+        .unwrap();
+        let records = read_records(&journal).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].description, "Outer");
+        assert_eq!(inner.1, None);
+        assert_eq!(outer, Some(records[0].id));
+    }
+}
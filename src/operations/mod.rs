@@ -1,8 +1,18 @@
+pub mod bundle;
 mod create;
 pub mod delay;
+mod delete;
 mod edit;
+pub mod fix;
+pub mod history;
+pub mod journal;
+pub mod profile;
+pub mod run;
+pub mod systemd;
 mod toggle;
+pub mod trash;
 
 pub use create::create_autostart_entry;
-pub use edit::edit_autostart_entry;
-pub use toggle::set_entry_enabled_by_path;
+pub use delete::delete_autostart_entry;
+pub use edit::{edit_autostart_entry, edit_autostart_source, preview_edit};
+pub use toggle::set_entry_enabled;
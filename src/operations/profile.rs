@@ -0,0 +1,411 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::{Preferences, XDG_PATHS};
+use crate::desktop_entry::writer::{sanitize_id, write_atomic};
+use crate::desktop_entry::{Delay, DelayStrategy, DesktopDocument, EntryChanges};
+use crate::discovery::{discover_autostart_entries, find_autostart_entry};
+use crate::model::AutostartEntry;
+use crate::operations::delay::systemd_drop_in_path;
+use crate::operations::journal::{self, RecordId};
+use crate::operations::{edit_autostart_entry, set_entry_enabled};
+
+const ENTRIES_GROUP: &str = "Entries";
+const DELAYS_GROUP: &str = "Delays";
+
+/// Which entries are enabled and how they are delayed, by desktop-file ID.
+/// Stored as `<name>.conf` key files:
+///
+/// ```ini
+/// [Entries]
+/// slack=enabled
+/// obs=disabled
+///
+/// [Delays]
+/// slack=gnome_key:10
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub entries: BTreeMap<String, ProfileEntry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfileEntry {
+    pub enabled: bool,
+    pub delay: Option<Delay>,
+}
+
+/// One difference between a profile and the current entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileChange {
+    Enable(String),
+    Disable(String),
+    Delay {
+        id: String,
+        from: Option<Delay>,
+        to: Option<Delay>,
+    },
+    /// The profile mentions an entry that does not exist here.
+    Missing(String),
+}
+
+impl fmt::Display for ProfileChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |delay: &Option<Delay>| match delay {
+            None => "no delay".to_string(),
+            Some(Delay {
+                strategy,
+                seconds: Some(seconds),
+            }) => format!("{}s ({})", seconds, strategy),
+            Some(Delay { strategy, .. }) => format!("late ({})", strategy),
+        };
+
+        match self {
+            ProfileChange::Enable(id) => write!(f, "Enabled {}", id),
+            ProfileChange::Disable(id) => write!(f, "Disabled {}", id),
+            ProfileChange::Delay { id, from, to } => write!(
+                f,
+                "Changed delay of {} from {} to {}",
+                id,
+                describe(from),
+                describe(to)
+            ),
+            ProfileChange::Missing(id) => write!(f, "Skipped {}: no such entry", id),
+        }
+    }
+}
+
+fn profiles_dir() -> PathBuf {
+    XDG_PATHS.onset_config.join("profiles")
+}
+
+fn profile_path(dir: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || sanitize_id(name) != name {
+        bail!(
+            "Invalid profile name: {} (use letters, digits, '-', '_' and '.')",
+            name
+        );
+    }
+    Ok(dir.join(format!("{}.conf", name)))
+}
+
+/// Names of all saved profiles, sorted.
+pub fn list_profiles() -> Vec<String> {
+    list_profiles_in(&profiles_dir())
+}
+
+fn list_profiles_in(dir: &Path) -> Vec<String> {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = read_dir
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()?
+                .strip_suffix(".conf")
+                .map(str::to_string)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn load_profile(name: &str) -> Result<Profile> {
+    load_profile_in(&profiles_dir(), name)
+}
+
+fn load_profile_in(dir: &Path, name: &str) -> Result<Profile> {
+    let path = profile_path(dir, name)?;
+    let content =
+        std::fs::read_to_string(&path).with_context(|| format!("No profile named '{}'", name))?;
+    let document = DesktopDocument::parse(&content);
+
+    let delays: BTreeMap<&str, Delay> = document
+        .entries(DELAYS_GROUP)
+        .filter_map(|(id, value)| Some((id, parse_delay(value)?)))
+        .collect();
+
+    let entries = document
+        .entries(ENTRIES_GROUP)
+        .map(|(id, state)| {
+            (
+                id.to_string(),
+                ProfileEntry {
+                    enabled: state != "disabled",
+                    delay: delays.get(id).copied(),
+                },
+            )
+        })
+        .collect();
+
+    Ok(Profile {
+        name: name.to_string(),
+        entries,
+    })
+}
+
+pub fn save_profile(profile: &Profile) -> Result<PathBuf> {
+    save_profile_in(&profiles_dir(), profile)
+}
+
+fn save_profile_in(dir: &Path, profile: &Profile) -> Result<PathBuf> {
+    let path = profile_path(dir, &profile.name)?;
+    let mut document = DesktopDocument::new();
+
+    for (id, entry) in &profile.entries {
+        document.set(
+            ENTRIES_GROUP,
+            id,
+            if entry.enabled { "enabled" } else { "disabled" },
+        );
+    }
+    for (id, entry) in &profile.entries {
+        if let Some(delay) = entry.delay {
+            document.set(DELAYS_GROUP, id, &format_delay(delay));
+        }
+    }
+
+    write_atomic(&path, &document.to_string())
+        .with_context(|| format!("Failed to save profile: {}", path.display()))?;
+
+    Ok(path)
+}
+
+pub fn delete_profile(name: &str) -> Result<()> {
+    let path = profile_path(&profiles_dir(), name)?;
+    std::fs::remove_file(&path).with_context(|| format!("No profile named '{}'", name))
+}
+
+/// A profile matching the current state of all entries.
+pub fn capture_profile(name: &str) -> Result<Profile> {
+    Ok(profile_from_entries(name, &discover_autostart_entries()?))
+}
+
+fn profile_from_entries(name: &str, entries: &[AutostartEntry]) -> Profile {
+    Profile {
+        name: name.to_string(),
+        entries: entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.id.clone(),
+                    ProfileEntry {
+                        enabled: !entry.desktop_entry.hidden,
+                        delay: entry.desktop_entry.delay,
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Differences between `profile` and `entries`. Entries the profile does
+/// not mention are left alone.
+pub fn plan_profile(profile: &Profile, entries: &[AutostartEntry]) -> Vec<ProfileChange> {
+    let mut changes = Vec::new();
+
+    for (id, wanted) in &profile.entries {
+        let Some(entry) = entries.iter().find(|e| &e.id == id) else {
+            changes.push(ProfileChange::Missing(id.clone()));
+            continue;
+        };
+
+        let current = &entry.desktop_entry;
+        if current.delay != wanted.delay {
+            changes.push(ProfileChange::Delay {
+                id: id.clone(),
+                from: current.delay,
+                to: wanted.delay,
+            });
+        }
+        if current.hidden == wanted.enabled {
+            changes.push(if wanted.enabled {
+                ProfileChange::Enable(id.clone())
+            } else {
+                ProfileChange::Disable(id.clone())
+            });
+        }
+    }
+
+    changes
+}
+
+/// Bring the entries in line with `profile` and remember it as the active
+/// one. The whole switch is a single journal record.
+pub fn apply_profile(profile: &Profile) -> Result<(Vec<ProfileChange>, Option<RecordId>)> {
+    let entries = discover_autostart_entries()?;
+    let changes = plan_profile(profile, &entries);
+
+    let touched: Vec<PathBuf> = entries
+        .iter()
+        .filter(|e| profile.entries.contains_key(&e.id))
+        .flat_map(|e| [e.user_path(), systemd_drop_in_path(&e.id)])
+        .collect();
+
+    let (_, record) = journal::record(&format!("Use profile {}", profile.name), &touched, || {
+        for change in &changes {
+            match change {
+                ProfileChange::Delay { id, to, .. } => {
+                    let entry = require_entry(id)?;
+                    let delay_changes = EntryChanges {
+                        delay_seconds: Some(to.map_or(0, |d| d.seconds.unwrap_or(1))),
+                        delay_strategy: to.map(|d| d.strategy),
+                        ..Default::default()
+                    };
+                    edit_autostart_entry(&entry, delay_changes)?;
+                }
+                ProfileChange::Enable(id) | ProfileChange::Disable(id) => {
+                    // Looked up again since a delay change may have created
+                    // a user override
+                    let entry = require_entry(id)?;
+                    set_entry_enabled(&entry, matches!(change, ProfileChange::Enable(_)))?;
+                }
+                ProfileChange::Missing(_) => {}
+            }
+        }
+        Ok(())
+    })?;
+
+    let mut preferences = Preferences::load();
+    preferences.active_profile = Some(profile.name.clone());
+    preferences.save()?;
+
+    tracing::info!(
+        "Applied profile {} with {} changes",
+        profile.name,
+        changes.len()
+    );
+
+    Ok((changes, record))
+}
+
+fn require_entry(id: &str) -> Result<AutostartEntry> {
+    find_autostart_entry(id)?.with_context(|| format!("No autostart entry with ID '{}'", id))
+}
+
+fn parse_delay(value: &str) -> Option<Delay> {
+    let (strategy, seconds) = match value.split_once(':') {
+        Some((strategy, seconds)) => (strategy, Some(seconds.parse().ok()?)),
+        None => (value, None),
+    };
+    Some(Delay {
+        strategy: DelayStrategy::from_key(strategy)?,
+        seconds,
+    })
+}
+
+fn format_delay(delay: Delay) -> String {
+    match delay.seconds {
+        Some(seconds) => format!("{}:{}", delay.strategy.key(), seconds),
+        None => delay.strategy.key().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop_entry::parser::parse_desktop_file;
+    use crate::model::EntryOrigin;
+
+    fn entry(id: &str, extra: &str) -> AutostartEntry {
+        let content = format!(
+            "[Desktop Entry]\nType=Application\nName={}\nExec=app\n{}",
+            id, extra
+        );
+        AutostartEntry::new(
+            id.to_string(),
+            PathBuf::from(format!("/tmp/{}.desktop", id)),
+            parse_desktop_file(&content).unwrap(),
+            content,
+            EntryOrigin::User,
+        )
+    }
+
+    #[test]
+    fn test_profile_round_trip() {
+        let dir = std::env::temp_dir().join("onset_test_profiles");
+        std::fs::remove_dir_all(&dir).ok();
+
+        let profile = profile_from_entries(
+            "work",
+            &[
+                entry("chat", "X-GNOME-Autostart-Delay=10\n"),
+                entry("obs", "Hidden=true\n"),
+                entry("panel", "X-KDE-autostart-phase=2\n"),
+            ],
+        );
+        let path = save_profile_in(&dir, &profile).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        let loaded = load_profile_in(&dir, "work").unwrap();
+        let names = list_profiles_in(&dir);
+        let invalid = save_profile_in(
+            &dir,
+            &Profile {
+                name: "../x".to_string(),
+                ..Default::default()
+            },
+        );
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(
+            content,
+            "[Entries]\nchat=enabled\nobs=disabled\npanel=enabled\n\n[Delays]\nchat=gnome_key:10\npanel=kde_phase\n"
+        );
+        assert_eq!(loaded, profile);
+        assert_eq!(names, vec!["work"]);
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn test_plan_profile() {
+        let entries = [
+            entry("chat", "X-GNOME-Autostart-Delay=10\n"),
+            entry("obs", "Hidden=true\n"),
+            entry("mail", ""),
+        ];
+        let mut profile = profile_from_entries("present", &entries);
+        profile.entries.insert(
+            "chat".to_string(),
+            ProfileEntry {
+                enabled: false,
+                delay: None,
+            },
+        );
+        profile.entries.get_mut("obs").unwrap().enabled = true;
+        profile.entries.remove("mail");
+        profile.entries.insert(
+            "gone".to_string(),
+            ProfileEntry {
+                enabled: true,
+                delay: None,
+            },
+        );
+
+        let changes = plan_profile(&profile, &entries);
+
+        assert_eq!(
+            changes,
+            vec![
+                ProfileChange::Delay {
+                    id: "chat".to_string(),
+                    from: Delay::new(DelayStrategy::GnomeKey, 10),
+                    to: None,
+                },
+                ProfileChange::Disable("chat".to_string()),
+                ProfileChange::Missing("gone".to_string()),
+                ProfileChange::Enable("obs".to_string()),
+            ]
+        );
+        assert_eq!(
+            changes[0].to_string(),
+            "Changed delay of chat from 10s (GNOME Delay Key) to no delay"
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use std::fs::File;
+use std::io::Read;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::XDG_PATHS;
+use crate::desktop_entry::exec::{parse_exec, remove_field_codes};
+use crate::model::AutostartEntry;
+use crate::utils::find_binary;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Only the start of longer output is shown; the rest stays in the log.
+const MAX_SHOWN_OUTPUT: u64 = 64 * 1024;
+
+/// Terminal emulators tried for `Terminal=true`, with the arguments that
+/// precede the command.
+const TERMINALS: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &["-e"]),
+    ("kgx", &["--"]),
+    ("ptyxis", &["--"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("alacritty", &["-e"]),
+    ("kitty", &[]),
+    ("foot", &[]),
+    ("xterm", &["-e"]),
+];
+
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Wait for the entry's startup delay first, as at login.
+    pub with_delay: bool,
+    /// How long to wait for the program to exit. Programs still running
+    /// then are left running.
+    pub timeout: Duration,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            with_delay: false,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RunOutput {
+    pub command: Vec<String>,
+    pub pid: u32,
+    /// `None` if the program was still running at the timeout.
+    pub status: Option<ExitStatus>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Where standard output is written, also after the timeout. Standard
+    /// error goes to the `.stderr` file beside it.
+    pub stdout_path: PathBuf,
+}
+
+/// Start an entry the way the session would and collect what it prints.
+pub fn run_entry(entry: &AutostartEntry, options: &RunOptions) -> Result<RunOutput> {
+    let command = launch_command(entry)?;
+
+    if options.with_delay
+        && let Some(seconds) = entry.delay_seconds()
+    {
+        thread::sleep(Duration::from_secs(seconds.into()));
+    }
+
+    let dir = entry.desktop_entry.working_dir.as_deref().map(Path::new);
+    run_command(
+        &command,
+        dir,
+        &XDG_PATHS.onset_state.join("runs").join(&entry.id),
+        options.timeout,
+    )
+}
+
+/// The arguments Exec is run with, inside a terminal for `Terminal=true`.
+pub fn launch_command(entry: &AutostartEntry) -> Result<Vec<String>> {
+    let desktop = &entry.desktop_entry;
+    let mut args: Vec<String> =
+        remove_field_codes(parse_exec(&desktop.exec).context("Invalid command")?)
+            .into_iter()
+            .map(|arg| arg.replace("%%", "%"))
+            .collect();
+    if args.is_empty() {
+        bail!("The command is empty");
+    }
+    if !desktop.environment.is_empty() {
+        let assignments = desktop
+            .environment
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value));
+        args = std::iter::once("env".to_string())
+            .chain(assignments)
+            .chain(args)
+            .collect();
+    }
+
+    if !desktop.terminal {
+        return Ok(args);
+    }
+
+    let mut command = terminal_command().context("No terminal emulator found")?;
+    command.extend(args);
+    Ok(command)
+}
+
+/// "exited with status 1" or "was killed by signal 9".
+pub fn describe_exit(status: ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with status {}", code),
+        (None, Some(signal)) => format!("was killed by signal {}", signal),
+        (None, None) => "exited abnormally".to_string(),
+    }
+}
+
+/// `$TERMINAL`, or the first installed terminal emulator that is known.
+fn terminal_command() -> Option<Vec<String>> {
+    if let Ok(terminal) = std::env::var("TERMINAL")
+        && !terminal.is_empty()
+        && find_binary(&terminal).is_some()
+    {
+        return Some(vec![terminal, "-e".to_string()]);
+    }
+
+    TERMINALS.iter().find_map(|(name, args)| {
+        find_binary(name)?;
+        Some(
+            std::iter::once(name.to_string())
+                .chain(args.iter().map(|a| a.to_string()))
+                .collect(),
+        )
+    })
+}
+
+/// Run `command`, writing its output to `log.stdout` and `log.stderr`.
+/// The program owns these files rather than pipes to onset, so one left
+/// running after the timeout keeps writing there after onset exits.
+fn run_command(
+    command: &[String],
+    dir: Option<&Path>,
+    log: &Path,
+    timeout: Duration,
+) -> Result<RunOutput> {
+    let (program, args) = command.split_first().context("The command is empty")?;
+
+    let stdout_path = log_path(log, "stdout");
+    let stderr_path = log_path(log, "stderr");
+    let stdout = create_log(&stdout_path)?;
+    let stderr = create_log(&stderr_path)?;
+
+    let mut process = Command::new(program);
+    process
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(stderr);
+    if let Some(dir) = dir {
+        process.current_dir(dir);
+    }
+
+    let mut child = process
+        .spawn()
+        .with_context(|| format!("Failed to start {}", program))?;
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline {
+            break None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let pid = child.id();
+    if status.is_none() {
+        // Reap it whenever it exits
+        thread::spawn(move || child.wait());
+    }
+
+    Ok(RunOutput {
+        command: command.to_vec(),
+        pid,
+        status,
+        stdout: read_log(&stdout_path)?,
+        stderr: read_log(&stderr_path)?,
+        stdout_path,
+    })
+}
+
+/// `log` with the stream appended; IDs may contain dots, so this does not
+/// replace an extension.
+fn log_path(log: &Path, stream: &str) -> PathBuf {
+    let mut path = log.as_os_str().to_owned();
+    path.push(".");
+    path.push(stream);
+    PathBuf::from(path)
+}
+
+/// A new, empty log. The previous one is removed rather than truncated, so
+/// a program still running from an earlier run keeps its own file.
+fn create_log(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    match std::fs::remove_file(path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to remove {}", path.display())),
+    }
+    File::create(path).with_context(|| format!("Failed to create {}", path.display()))
+}
+
+/// The output written so far, cut off after [`MAX_SHOWN_OUTPUT`] bytes.
+fn read_log(path: &Path) -> Result<String> {
+    let file = File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut output = Vec::new();
+    file.take(MAX_SHOWN_OUTPUT + 1)
+        .read_to_end(&mut output)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    if output.len() as u64 > MAX_SHOWN_OUTPUT {
+        output.truncate(MAX_SHOWN_OUTPUT as usize);
+        let mut output = String::from_utf8_lossy(&output).into_owned();
+        output.push_str(&format!("\n… (cut off; see {})\n", path.display()));
+        return Ok(output);
+    }
+
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop_entry::parser::parse_desktop_file;
+    use crate::model::EntryOrigin;
+
+    fn command(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_launch_command_unescapes_percent() {
+        let content =
+            "[Desktop Entry]\nType=Application\nName=Vol\nExec=amixer set Master 50%% %U\n";
+        let entry = AutostartEntry::new(
+            "vol".to_string(),
+            PathBuf::from("/home/u/.config/autostart/vol.desktop"),
+            parse_desktop_file(content).unwrap(),
+            content.to_string(),
+            EntryOrigin::User,
+        );
+
+        assert_eq!(
+            launch_command(&entry).unwrap(),
+            command(&["amixer", "set", "Master", "50%"])
+        );
+    }
+
+    #[test]
+    fn test_run_command_captures_output() {
+        let dir = std::env::temp_dir().join("onset_test_run_output");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let output = run_command(
+            &command(&["sh", "-c", "pwd; echo oops >&2; exit 3"]),
+            Some(Path::new("/")),
+            &dir.join("org.example.App"),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+
+        assert_eq!(output.status.and_then(|s| s.code()), Some(3));
+        assert_eq!(output.stdout, "/\n");
+        assert_eq!(output.stderr, "oops\n");
+        assert_eq!(output.stdout_path, dir.join("org.example.App.stdout"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_run_command_timeout() {
+        let dir = std::env::temp_dir().join("onset_test_run_timeout");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let output = run_command(
+            &command(&["sh", "-c", "echo started; exec sleep 5"]),
+            None,
+            &dir.join("app"),
+            Duration::from_millis(300),
+        )
+        .unwrap();
+        Command::new("kill")
+            .arg(output.pid.to_string())
+            .status()
+            .ok();
+
+        assert!(output.status.is_none());
+        assert_eq!(output.stdout, "started\n");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_log_cuts_off_long_output() {
+        let dir = std::env::temp_dir().join("onset_test_run_long_output");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.stdout");
+        std::fs::write(&path, "x".repeat(MAX_SHOWN_OUTPUT as usize + 10)).unwrap();
+
+        let output = read_log(&path).unwrap();
+
+        assert!(output.starts_with(&"x".repeat(MAX_SHOWN_OUTPUT as usize)));
+        assert!(output.ends_with(&format!("(cut off; see {})\n", path.display())));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Runs a program in a separate test process that exits at the timeout,
+    /// as onset does when closed while the program is left running.
+    #[test]
+    fn test_program_outlives_runner() {
+        let dir = std::env::temp_dir().join("onset_test_run_outlives");
+        if let Ok(dir) = std::env::var("ONSET_TEST_RUN_DIR") {
+            let script = "echo started; sleep 1; echo late; echo late >&2; touch done";
+            run_command(
+                &command(&["sh", "-c", script]),
+                Some(Path::new(&dir)),
+                &Path::new(&dir).join("app"),
+                Duration::from_millis(100),
+            )
+            .unwrap();
+            return;
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let runner = Command::new(std::env::current_exe().unwrap())
+            .args(["test_program_outlives_runner", "--test-threads=1"])
+            .env("ONSET_TEST_RUN_DIR", &dir)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(runner.success());
+        assert!(!dir.join("done").exists());
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !dir.join("done").exists() && Instant::now() < deadline {
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        assert!(dir.join("done").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("app.stdout")).unwrap(),
+            "started\nlate\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("app.stderr")).unwrap(),
+            "late\n"
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,360 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::XDG_PATHS;
+use crate::desktop_entry::DesktopDocument;
+use crate::desktop_entry::exec::{parse_exec, remove_field_codes};
+use crate::desktop_entry::writer::write_atomic;
+use crate::model::{AutostartEntry, SystemdUnit};
+use crate::operations::{journal, set_entry_enabled};
+use crate::utils::find_binary;
+
+pub const GRAPHICAL_SESSION_TARGET: &str = "graphical-session.target";
+
+/// Service settings that have no equivalent in a desktop entry.
+#[derive(Debug, Clone, Default)]
+pub struct UnitOptions {
+    /// `Restart=` policy, e.g. `on-failure`.
+    pub restart: Option<String>,
+    /// Units to start after, in addition to the session target.
+    pub after: Vec<String>,
+    /// `MemoryMax=`, e.g. `512M`.
+    pub memory_max: Option<String>,
+    /// `CPUQuota=`, e.g. `50%`.
+    pub cpu_quota: Option<String>,
+}
+
+/// Directory holding the symlinks of units enabled for the session.
+pub fn wants_dir(unit_dir: &Path) -> PathBuf {
+    unit_dir.join(format!("{}.wants", GRAPHICAL_SESSION_TARGET))
+}
+
+/// Name of the unit generated for an entry. The prefix keeps it from
+/// shadowing a unit of the same name shipped by the application.
+fn unit_name_for(id: &str) -> String {
+    let escaped: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("onset-{}.service", escaped)
+}
+
+/// Turn an autostart entry into a user service, enable it and disable the
+/// desktop entry so the program is not started twice. Returns the path of
+/// the unit file. Both steps are a single journal record.
+///
+/// `OnlyShowIn`/`NotShowIn` have no unit equivalent and are not carried
+/// over; entries that run in a terminal cannot be converted.
+pub fn convert_to_systemd_unit(entry: &AutostartEntry, options: &UnitOptions) -> Result<PathBuf> {
+    let dir = &XDG_PATHS.systemd_user;
+    let name = unit_name_for(&entry.id);
+
+    let (unit, _) = journal::record(
+        &format!("Convert {}", entry.desktop_entry.display_name()),
+        &[
+            dir.join(&name),
+            wants_dir(dir).join(&name),
+            entry.user_path(),
+        ],
+        || {
+            convert_in(dir, entry, options, || {
+                set_entry_enabled(entry, false).map(|_| ())
+            })
+        },
+    )?;
+
+    tracing::info!("Converted {} to {}", entry.id, unit.path.display());
+
+    Ok(unit.path)
+}
+
+/// Write and enable the unit, then `disable_entry`. The unit is removed
+/// again when that fails, so that the program is not started twice.
+fn convert_in(
+    dir: &Path,
+    entry: &AutostartEntry,
+    options: &UnitOptions,
+    disable_entry: impl FnOnce() -> Result<()>,
+) -> Result<SystemdUnit> {
+    let unit = write_unit_in(dir, entry, options)?;
+
+    if let Err(e) = disable_entry() {
+        if let Err(cleanup) = remove_unit(&unit) {
+            tracing::error!("Failed to remove {}: {:#}", unit.path.display(), cleanup);
+        }
+        return Err(e);
+    }
+
+    Ok(unit)
+}
+
+fn remove_unit(unit: &SystemdUnit) -> Result<()> {
+    set_unit_enabled(unit, false)?;
+    std::fs::remove_file(&unit.path)
+        .with_context(|| format!("Failed to remove unit: {}", unit.path.display()))
+}
+
+fn write_unit_in(dir: &Path, entry: &AutostartEntry, options: &UnitOptions) -> Result<SystemdUnit> {
+    let name = unit_name_for(&entry.id);
+    let path = dir.join(&name);
+
+    if path.exists() {
+        bail!("Unit already exists: {}", path.display());
+    }
+
+    write_atomic(&path, &unit_file_content(entry, options)?)
+        .with_context(|| format!("Failed to write unit: {}", path.display()))?;
+
+    let unit = SystemdUnit {
+        name,
+        path,
+        description: Some(entry.desktop_entry.display_name().to_string()),
+        exec_start: None,
+        enabled: false,
+    };
+    set_unit_enabled(&unit, true)?;
+
+    Ok(SystemdUnit {
+        enabled: true,
+        ..unit
+    })
+}
+
+pub fn unit_file_content(entry: &AutostartEntry, options: &UnitOptions) -> Result<String> {
+    let desktop = &entry.desktop_entry;
+
+    if desktop.terminal {
+        bail!("{} runs in a terminal and cannot be a service", entry.id);
+    }
+
+    let mut args = remove_field_codes(
+        parse_exec(&desktop.exec).with_context(|| format!("Invalid command for {}", entry.id))?,
+    );
+    if !args[0].starts_with('/') {
+        // The search path of systemd does not include ~/.local/bin and the
+        // like, so resolve the program the way the session would
+        let program = find_binary(&args[0])
+            .with_context(|| format!("Program not found in PATH: {}", args[0]))?;
+        args[0] = program.display().to_string();
+    }
+    let exec_start = args
+        .iter()
+        // Field codes are gone, so the only % sequence left is a literal %%
+        .map(|arg| quote_systemd_arg(&arg.replace("%%", "%")))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut document = DesktopDocument::new();
+
+    document.set("Unit", "Description", desktop.display_name());
+    document.set("Unit", "PartOf", GRAPHICAL_SESSION_TARGET);
+    let mut after = vec![GRAPHICAL_SESSION_TARGET.to_string()];
+    after.extend(options.after.iter().cloned());
+    document.set("Unit", "After", &after.join(" "));
+
+    document.set("Service", "Type", "exec");
+    if let Some(seconds) = entry.delay_seconds() {
+        document.set("Service", "ExecStartPre", &format!("sleep {}", seconds));
+        // The sleep counts towards the start timeout of the unit
+        document.set("Service", "TimeoutStartSec", "infinity");
+    }
+    document.set("Service", "ExecStart", &exec_start);
+    if !desktop.environment.is_empty() {
+        let environment = desktop
+            .environment
+            .iter()
+            .map(|(name, value)| quote_systemd_assignment(name, value))
+            .collect::<Vec<_>>()
+            .join(" ");
+        document.set("Service", "Environment", &environment);
+    }
+    if let Some(ref dir) = desktop.working_dir {
+        document.set("Service", "WorkingDirectory", &dir.replace('%', "%%"));
+    }
+    if let Some(ref restart) = options.restart {
+        document.set("Service", "Restart", restart);
+    }
+    if let Some(ref memory_max) = options.memory_max {
+        document.set("Service", "MemoryMax", memory_max);
+    }
+    if let Some(ref cpu_quota) = options.cpu_quota {
+        document.set("Service", "CPUQuota", cpu_quota);
+    }
+
+    document.set("Install", "WantedBy", GRAPHICAL_SESSION_TARGET);
+
+    Ok(format!(
+        "# Generated by Onset from {}\n{}",
+        entry.path.display(),
+        document
+    ))
+}
+
+/// Quote an argument for a systemd command line, where `%` starts a
+/// specifier and `$` an environment variable.
+fn quote_systemd_arg(arg: &str) -> String {
+    let escaped = arg.replace('%', "%%").replace('$', "$$");
+    let needs_quotes = escaped.is_empty()
+        || escaped.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | ';'));
+
+    if !needs_quotes {
+        return escaped;
+    }
+
+    format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Quote `name=value` for `Environment=`, which expands specifiers but not
+/// variables.
+fn quote_systemd_assignment(name: &str, value: &str) -> String {
+    let value = value
+        .replace('%', "%%")
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    format!("\"{}={}\"", name, value)
+}
+
+/// Enable or disable a unit by creating or removing its symlink in
+/// `graphical-session.target.wants`, as `systemctl --user enable` would.
+/// The change takes effect at the next login or `daemon-reload`.
+pub fn set_unit_enabled(unit: &SystemdUnit, enabled: bool) -> Result<()> {
+    let dir = unit.path.parent().context("Invalid unit path")?;
+    let wants = wants_dir(dir);
+    let link = wants.join(&unit.name);
+    let linked = link.symlink_metadata().is_ok();
+
+    if enabled && !linked {
+        std::fs::create_dir_all(&wants)
+            .with_context(|| format!("Failed to create directory: {}", wants.display()))?;
+        std::os::unix::fs::symlink(&unit.path, &link)
+            .with_context(|| format!("Failed to enable unit: {}", link.display()))?;
+    } else if !enabled && linked {
+        std::fs::remove_file(&link)
+            .with_context(|| format!("Failed to disable unit: {}", link.display()))?;
+    }
+
+    tracing::info!(
+        "Set unit {} to {}",
+        unit.name,
+        if enabled { "enabled" } else { "disabled" }
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop_entry::parser::parse_desktop_file;
+    use crate::model::EntryOrigin;
+
+    fn entry(id: &str, content: &str) -> AutostartEntry {
+        AutostartEntry::new(
+            id.to_string(),
+            PathBuf::from(format!("/home/u/.config/autostart/{}.desktop", id)),
+            parse_desktop_file(content).unwrap(),
+            content.to_string(),
+            EntryOrigin::User,
+        )
+    }
+
+    #[test]
+    fn test_unit_file_content() {
+        let entry = entry(
+            "notes",
+            "[Desktop Entry]\nType=Application\nName=Notes\nExec=/usr/bin/notes --title=\"My \\\\$HOME\" 100%% %U\nX-GNOME-Autostart-Delay=5\n",
+        );
+        let options = UnitOptions {
+            restart: Some("on-failure".to_string()),
+            after: vec!["pipewire.service".to_string()],
+            memory_max: Some("512M".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            unit_file_content(&entry, &options).unwrap(),
+            "# Generated by Onset from /home/u/.config/autostart/notes.desktop
+[Unit]
+Description=Notes
+PartOf=graphical-session.target
+After=graphical-session.target pipewire.service
+
+[Service]
+Type=exec
+ExecStartPre=sleep 5
+TimeoutStartSec=infinity
+ExecStart=/usr/bin/notes \"--title=My $$HOME\" 100%%
+Restart=on-failure
+MemoryMax=512M
+
+[Install]
+WantedBy=graphical-session.target
+"
+        );
+    }
+
+    #[test]
+    fn test_unit_file_content_rejects_terminal_entries() {
+        let entry = entry(
+            "top",
+            "[Desktop Entry]\nType=Application\nName=Top\nExec=/usr/bin/top\nTerminal=true\n",
+        );
+        assert!(unit_file_content(&entry, &UnitOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_write_unit_enables_it() {
+        let dir = std::env::temp_dir().join("onset_test_systemd_write");
+        std::fs::remove_dir_all(&dir).ok();
+
+        let entry = entry(
+            "tray app",
+            "[Desktop Entry]\nType=Application\nName=Tray\nExec=/usr/bin/tray\n",
+        );
+        let unit = write_unit_in(&dir, &entry, &UnitOptions::default()).unwrap();
+        let link = wants_dir(&dir).join("onset-tray_app.service");
+        let link_target = std::fs::read_link(&link).ok();
+        let second = write_unit_in(&dir, &entry, &UnitOptions::default());
+
+        set_unit_enabled(&unit, false).unwrap();
+        let still_linked = link.symlink_metadata().is_ok();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(unit.name, "onset-tray_app.service");
+        assert!(unit.enabled);
+        assert_eq!(link_target, Some(unit.path));
+        assert!(second.is_err());
+        assert!(!still_linked);
+    }
+
+    #[test]
+    fn test_convert_removes_unit_when_disabling_fails() {
+        let dir = std::env::temp_dir().join("onset_test_systemd_convert");
+        std::fs::remove_dir_all(&dir).ok();
+
+        let entry = entry(
+            "tray",
+            "[Desktop Entry]\nType=Application\nName=Tray\nExec=/usr/bin/tray\n",
+        );
+        let result = convert_in(&dir, &entry, &UnitOptions::default(), || {
+            bail!("read-only file system")
+        });
+        let unit_left = dir.join("onset-tray.service").exists();
+        let link_left = wants_dir(&dir)
+            .join("onset-tray.service")
+            .symlink_metadata()
+            .is_ok();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(result.is_err());
+        assert!(!unit_left);
+        assert!(!link_left);
+    }
+}
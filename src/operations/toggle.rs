@@ -2,54 +2,58 @@ use std::path::Path;
 
 use anyhow::{Context, Result};
 
+use crate::desktop_entry::parser::is_hidden_stub;
 use crate::desktop_entry::writer::write_atomic;
+use crate::desktop_entry::{DESKTOP_ENTRY_GROUP, DesktopDocument};
+use crate::model::{AutostartEntry, EntryOrigin};
+use crate::operations::journal::{self, RecordId};
 
-pub fn set_entry_enabled_by_path(path: &Path, enabled: bool) -> Result<()> {
-    let content = std::fs::read_to_string(path)
-        .with_context(|| format!("Failed to read entry: {}", path.display()))?;
-
-    let mut lines: Vec<String> = Vec::new();
-    let mut in_desktop_entry = false;
-    let mut hidden_written = false;
-
-    for line in content.lines() {
-        let trimmed = line.trim();
+/// Enable or disable an entry at its `user_path`, returning the journal
+/// record of the change.
+///
+/// System entries are never touched in place: disabling one writes a
+/// user-level copy with `Hidden=true` that shadows the system file. A
+/// `Hidden=true` stub is filled in with the system file's keys, since
+/// removing `Hidden` alone would leave a file without Name or Exec.
+pub fn set_entry_enabled(entry: &AutostartEntry, enabled: bool) -> Result<Option<RecordId>> {
+    let path = entry.user_path();
+    let description = format!(
+        "{} {}",
+        if enabled { "Enable" } else { "Disable" },
+        entry.desktop_entry.display_name()
+    );
 
-        if trimmed == "[Desktop Entry]" {
-            in_desktop_entry = true;
-            lines.push(line.to_string());
-            continue;
+    let (_, record) = journal::record(&description, std::slice::from_ref(&path), || {
+        let writes_override = entry.origin == EntryOrigin::System
+            || std::fs::read_to_string(&path).is_ok_and(|content| is_hidden_stub(&content));
+        if writes_override {
+            write_atomic(&path, &entry.raw_content)
+                .with_context(|| format!("Failed to create user override: {}", path.display()))?;
+            tracing::info!(
+                "Created user override {} for {}",
+                path.display(),
+                entry.path.display()
+            );
         }
 
-        if trimmed.starts_with('[') {
-            if in_desktop_entry && !enabled && !hidden_written {
-                lines.push("Hidden=true".to_string());
-                hidden_written = true;
-            }
-            in_desktop_entry = false;
-            lines.push(line.to_string());
-            continue;
-        }
+        set_entry_enabled_by_path(&path, enabled)
+    })?;
 
-        if in_desktop_entry
-            && let Some((key, _)) = trimmed.split_once('=')
-            && key.trim() == "Hidden"
-        {
-            if !enabled {
-                lines.push("Hidden=true".to_string());
-                hidden_written = true;
-            }
-            continue;
-        }
+    Ok(record)
+}
 
-        lines.push(line.to_string());
-    }
+pub fn set_entry_enabled_by_path(path: &Path, enabled: bool) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read entry: {}", path.display()))?;
 
-    if in_desktop_entry && !enabled && !hidden_written {
-        lines.push("Hidden=true".to_string());
+    let mut document = DesktopDocument::parse(&content);
+    if enabled {
+        document.remove(DESKTOP_ENTRY_GROUP, "Hidden");
+    } else {
+        document.set(DESKTOP_ENTRY_GROUP, "Hidden", "true");
     }
 
-    let new_content = lines.join("\n") + "\n";
+    let new_content = document.to_string();
 
     write_atomic(path, &new_content)
         .with_context(|| format!("Failed to update entry: {}", path.display()))?;
@@ -62,3 +66,29 @@ pub fn set_entry_enabled_by_path(path: &Path, enabled: bool) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_preserves_unknown_keys() {
+        let dir = std::env::temp_dir().join("onset_test_toggle");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("phase.desktop");
+        let content = "[Desktop Entry]\nType=Application\nName=Panel\nExec=panel\nX-GNOME-Autostart-Phase=Panel\n# trailing comment\n";
+        std::fs::write(&path, content).unwrap();
+
+        set_entry_enabled_by_path(&path, false).unwrap();
+        let disabled = std::fs::read_to_string(&path).unwrap();
+        set_entry_enabled_by_path(&path, true).unwrap();
+        let enabled = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(
+            disabled,
+            content.replace("Panel\n#", "Panel\nHidden=true\n#")
+        );
+        assert_eq!(enabled, content);
+    }
+}
@@ -0,0 +1,211 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use gtk4::glib;
+
+use crate::config::XDG_PATHS;
+use crate::discovery::TRASH_INFO_GROUP;
+use crate::model::TrashedEntry;
+use crate::operations::journal;
+use crate::utils::percent_encode_path;
+
+/// Move a file to the user's trash following the freedesktop.org Trash
+/// specification, so that file managers can restore it as well. Returns
+/// the path of the file inside the trash.
+///
+/// Within a journal record the trashed copy is noted as created, so undoing
+/// the removal does not leave it behind in the trash.
+pub fn move_to_trash(path: &Path) -> Result<PathBuf> {
+    move_to_trash_in(&XDG_PATHS.trash, path)
+}
+
+fn move_to_trash_in(trash: &Path, path: &Path) -> Result<PathBuf> {
+    let files_dir = trash.join("files");
+    let info_dir = trash.join("info");
+    for dir in [&files_dir, &info_dir] {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    }
+
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("Invalid file name")?;
+    let (stem, extension) = file_name.rsplit_once('.').unwrap_or((file_name, ""));
+
+    let info = format!(
+        "[{}]\nPath={}\nDeletionDate={}\n",
+        TRASH_INFO_GROUP,
+        percent_encode_path(path),
+        deletion_date()?
+    );
+
+    // The info file is created first and exclusively; it reserves the name
+    // against other programs trashing a file with the same name
+    for attempt in 1.. {
+        let trash_name = match attempt {
+            1 => file_name.to_string(),
+            n => format!("{}.{}.{}", stem, n, extension),
+        };
+        let info_path = info_dir.join(format!("{}.trashinfo", trash_name));
+        let trash_path = files_dir.join(&trash_name);
+
+        if trash_path.symlink_metadata().is_ok() {
+            continue;
+        }
+
+        let mut info_file = match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", info_path.display()));
+            }
+        };
+
+        let moved = info_file
+            .write_all(info.as_bytes())
+            .map_err(anyhow::Error::from)
+            .and_then(|_| move_file(path, &trash_path));
+        if let Err(e) = moved {
+            std::fs::remove_file(&info_path).ok();
+            return Err(e).with_context(|| format!("Failed to trash {}", path.display()));
+        }
+
+        journal::note_created(&info_path);
+        journal::note_created(&trash_path);
+
+        tracing::info!("Moved {} to {}", path.display(), trash_path.display());
+
+        return Ok(trash_path);
+    }
+
+    unreachable!()
+}
+
+/// Put a trashed entry back where it was removed from.
+pub fn restore_trashed_entry(entry: &TrashedEntry) -> Result<()> {
+    if entry.original_path.exists() {
+        bail!(
+            "Cannot restore {}: {} already exists",
+            entry.name,
+            entry.original_path.display()
+        );
+    }
+
+    if let Some(parent) = entry.original_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    move_file(&entry.trash_path, &entry.original_path)
+        .with_context(|| format!("Failed to restore {}", entry.original_path.display()))?;
+    std::fs::remove_file(&entry.info_path).ok();
+
+    tracing::info!("Restored {}", entry.original_path.display());
+
+    Ok(())
+}
+
+/// Permanently delete a trashed entry.
+pub fn purge_trashed_entry(entry: &TrashedEntry) -> Result<()> {
+    match std::fs::remove_file(&entry.trash_path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e)
+                .with_context(|| format!("Failed to delete {}", entry.trash_path.display()));
+        }
+        _ => {}
+    }
+    std::fs::remove_file(&entry.info_path)
+        .with_context(|| format!("Failed to delete {}", entry.info_path.display()))?;
+
+    tracing::info!("Purged {} from the trash", entry.original_path.display());
+
+    Ok(())
+}
+
+/// Rename, falling back to copying when the trash is on another file
+/// system.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    std::fs::copy(from, to)
+        .with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    std::fs::remove_file(from).with_context(|| format!("Failed to remove {}", from.display()))
+}
+
+/// Current local time as `YYYY-MM-DDThh:mm:ss`, as the specification asks.
+fn deletion_date() -> Result<String> {
+    let now = glib::DateTime::now_local().context("Failed to read the local time")?;
+    Ok(now
+        .format("%Y-%m-%dT%H:%M:%S")
+        .context("Failed to format the deletion date")?
+        .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trashed(trash: &Path, trash_path: PathBuf, original_path: &Path) -> TrashedEntry {
+        let name = trash_path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        TrashedEntry {
+            id: "app".to_string(),
+            name: "App".to_string(),
+            original_path: original_path.to_path_buf(),
+            info_path: trash.join("info").join(format!("{}.trashinfo", name)),
+            trash_path,
+            deleted_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_trash_restore_and_purge() {
+        let dir = std::env::temp_dir().join("onset_test_trash");
+        std::fs::remove_dir_all(&dir).ok();
+        let trash = dir.join("Trash");
+        let autostart = dir.join("auto start");
+        std::fs::create_dir_all(&autostart).unwrap();
+        let original = autostart.join("app.desktop");
+
+        std::fs::write(&original, "first").unwrap();
+        let first = move_to_trash_in(&trash, &original).unwrap();
+        std::fs::write(&original, "second").unwrap();
+        let second = move_to_trash_in(&trash, &original).unwrap();
+        let info = std::fs::read_to_string(trash.join("info/app.2.desktop.trashinfo")).unwrap();
+
+        restore_trashed_entry(&trashed(&trash, first.clone(), &original)).unwrap();
+        let restored = std::fs::read_to_string(&original).unwrap();
+        let restore_again = restore_trashed_entry(&trashed(&trash, second.clone(), &original));
+        purge_trashed_entry(&trashed(&trash, second.clone(), &original)).unwrap();
+        let leftovers = std::fs::read_dir(trash.join("files")).unwrap().count()
+            + std::fs::read_dir(trash.join("info")).unwrap().count();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(first, trash.join("files/app.desktop"));
+        assert_eq!(second, trash.join("files/app.2.desktop"));
+        assert!(info.contains("/auto%20start/app.desktop\n"));
+        assert_eq!(restored, "first");
+        assert!(restore_again.is_err());
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_deletion_date_format() {
+        let date = deletion_date().unwrap();
+
+        assert_eq!(date.len(), "2024-02-29T12:34:56".len());
+        assert_eq!(date.as_bytes()[10], b'T');
+    }
+}
@@ -139,12 +139,12 @@ impl AppChooserDialog {
 
     fn create_app_row(app: &Application) -> adw::ActionRow {
         let row = adw::ActionRow::builder()
-            .title(&app.name)
+            .title(app.display_name())
             .activatable(true)
             .use_markup(false)
             .build();
 
-        if let Some(ref comment) = app.comment {
+        if let Some(comment) = app.display_comment() {
             row.set_subtitle(comment);
         }
 
@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::path::PathBuf;
 
 use gtk4::glib;
@@ -6,27 +7,42 @@ use libadwaita as adw;
 use libadwaita::prelude::*;
 
 use crate::desktop_entry::EffectiveState;
-use crate::model::AutostartEntry;
+use crate::desktop_entry::fix::Fix;
+use crate::desktop_entry::lint::Severity;
+use crate::model::{AutostartEntry, BrokenEntry, EntryOrigin, TrashedEntry};
+use crate::operations::history::{LaunchResult, SessionReport};
+use crate::operations::journal::RecordId;
+use crate::utils::format_age;
 
-pub fn create_autostart_row<E, D>(
+use super::desktop_preview::preview_desktop;
+
+/// `report` is the last session report, for the result of the entry at
+/// the last login. `on_toggle` gets a message for the change and its
+/// journal record.
+pub fn create_autostart_row<E, D, R, T>(
     entry: &AutostartEntry,
+    report: Option<&SessionReport>,
     on_edit: E,
     on_delete: D,
+    on_run: R,
+    on_toggle: T,
 ) -> adw::ActionRow
 where
     E: Fn(PathBuf, String) + 'static,
     D: Fn(PathBuf, String) + 'static,
+    R: Fn(PathBuf) + 'static,
+    T: Fn(String, Option<RecordId>) + 'static,
 {
     let row = adw::ActionRow::builder()
-        .title(&entry.desktop_entry.name)
+        .title(entry.desktop_entry.display_name())
         .activatable(true)
         .use_markup(false)
         .build();
 
-    if let Some(ref comment) = entry.desktop_entry.comment {
+    if let Some(comment) = entry.desktop_entry.display_comment() {
         row.set_subtitle(comment);
     } else {
-        row.set_subtitle(&entry.base_exec());
+        row.set_subtitle(&entry.desktop_entry.exec);
     }
 
     let icon_name = entry
@@ -43,17 +59,30 @@ where
 
     let toggle = gtk4::Switch::builder()
         .valign(gtk4::Align::Center)
-        .active(entry.effective_state == EffectiveState::Enabled)
+        .active(entry.effective_state.may_start())
         .build();
 
-    let entry_path = entry.path.clone();
-    let entry_id = entry.id.clone();
+    let toggle_entry = RefCell::new(entry.clone());
     let toggle_row = row.clone();
 
     toggle.connect_state_set(move |_, state| {
-        match crate::operations::set_entry_enabled_by_path(&entry_path, state) {
-            Ok(_) => {
+        let mut toggle_entry = toggle_entry.borrow_mut();
+        let entry_id = toggle_entry.id.clone();
+        match crate::operations::set_entry_enabled(&toggle_entry, state) {
+            Ok(record) => {
                 tracing::info!("Toggled {} to {}", entry_id, state);
+                if toggle_entry.origin == EntryOrigin::System {
+                    toggle_entry.path = toggle_entry.user_path();
+                    toggle_entry.origin = EntryOrigin::UserOverridesSystem;
+                }
+                on_toggle(
+                    format!(
+                        "{} {}",
+                        if state { "Enabled" } else { "Disabled" },
+                        toggle_entry.desktop_entry.display_name()
+                    ),
+                    record,
+                );
                 if state {
                     toggle_row.remove_css_class("dim-label");
                 } else {
@@ -74,11 +103,21 @@ where
         .valign(gtk4::Align::Center)
         .build();
 
-    if let Some(delay) = entry.delay_seconds() {
+    if let Some(delay) = entry.desktop_entry.delay {
+        let (label, tooltip) = match delay.seconds {
+            Some(seconds) => (
+                format!("{}s", seconds),
+                format!("{} second delay ({})", seconds, delay.strategy),
+            ),
+            None => (
+                "Late".to_string(),
+                format!("Starts after the desktop ({})", delay.strategy),
+            ),
+        };
         let delay_label = gtk4::Label::builder()
-            .label(format!("{}s", delay))
+            .label(label)
             .css_classes(vec!["dim-label", "caption"])
-            .tooltip_text(format!("{} second delay", delay))
+            .tooltip_text(tooltip)
             .build();
 
         let delay_icon = gtk4::Image::builder()
@@ -97,16 +136,54 @@ where
         info_box.append(&delay_box);
     }
 
+    if entry.origin != EntryOrigin::User {
+        let origin_label = gtk4::Label::builder()
+            .label(entry.origin.to_string())
+            .css_classes(vec!["dim-label", "caption"])
+            .tooltip_text(entry.path.display().to_string())
+            .build();
+        info_box.append(&origin_label);
+    }
+
     match entry.effective_state {
         EffectiveState::EnvironmentExcluded => {
             let env_icon = gtk4::Image::builder()
                 .icon_name("computer-symbolic")
                 .pixel_size(16)
-                .tooltip_text("Excluded for current desktop environment")
+                .tooltip_text(match preview_desktop() {
+                    Some(desktop) => format!("Not started on {}", desktop.join(":")),
+                    None => "Excluded for current desktop environment".to_string(),
+                })
                 .css_classes(vec!["warning"])
                 .build();
             info_box.append(&env_icon);
         }
+        EffectiveState::ConditionUnmet | EffectiveState::Conditional => {
+            let condition = entry
+                .desktop_entry
+                .autostart_condition
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            let (icon_name, tooltip) = if entry.effective_state == EffectiveState::Conditional {
+                (
+                    "dialog-question-symbolic",
+                    format!("Starts only if {}", condition),
+                )
+            } else {
+                (
+                    "dialog-warning-symbolic",
+                    format!("Not started: {}", condition),
+                )
+            };
+            let condition_icon = gtk4::Image::builder()
+                .icon_name(icon_name)
+                .pixel_size(16)
+                .tooltip_text(tooltip)
+                .css_classes(vec!["warning"])
+                .build();
+            info_box.append(&condition_icon);
+        }
         EffectiveState::TryExecFailed => {
             let warning_icon = gtk4::Image::builder()
                 .icon_name("dialog-warning-symbolic")
@@ -119,6 +196,31 @@ where
         _ => {}
     }
 
+    if let Some(report) = report
+        && let Some(launch) = report.launch(&entry.id)
+    {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (icon_name, css_class) = match launch.result {
+            LaunchResult::Skipped => ("action-unavailable-symbolic", "dim-label"),
+            result if result.is_ok() => ("emblem-ok-symbolic", "dim-label"),
+            _ => ("dialog-error-symbolic", "error"),
+        };
+        let launch_icon = gtk4::Image::builder()
+            .icon_name(icon_name)
+            .pixel_size(16)
+            .tooltip_text(format!(
+                "Last login ({}): {}",
+                format_age(now.saturating_sub(report.timestamp)),
+                launch
+            ))
+            .css_classes(vec![css_class])
+            .build();
+        info_box.append(&launch_icon);
+    }
+
     row.add_suffix(&info_box);
     row.add_suffix(&toggle);
 
@@ -126,6 +228,18 @@ where
         row.add_css_class("dim-label");
     }
 
+    let run_button = gtk4::Button::builder()
+        .icon_name("media-playback-start-symbolic")
+        .valign(gtk4::Align::Center)
+        .css_classes(vec!["flat", "circular"])
+        .tooltip_text("Run now")
+        .build();
+
+    let run_path = entry.path.clone();
+    run_button.connect_clicked(move |_| on_run(run_path.clone()));
+
+    row.add_suffix(&run_button);
+
     let edit_button = gtk4::Button::builder()
         .icon_name("document-edit-symbolic")
         .valign(gtk4::Align::Center)
@@ -141,15 +255,23 @@ where
 
     row.add_suffix(&edit_button);
 
+    // System files cannot be deleted; they can only be disabled through a
+    // user override, and removing an override restores the system entry.
+    let (delete_icon, delete_tooltip) = match entry.origin {
+        EntryOrigin::User => ("user-trash-symbolic", "Delete entry"),
+        EntryOrigin::UserOverridesSystem => ("edit-undo-symbolic", "Revert to system entry"),
+        EntryOrigin::System => return row,
+    };
+
     let delete_button = gtk4::Button::builder()
-        .icon_name("user-trash-symbolic")
+        .icon_name(delete_icon)
         .valign(gtk4::Align::Center)
         .css_classes(vec!["flat", "circular"])
-        .tooltip_text("Delete entry")
+        .tooltip_text(delete_tooltip)
         .build();
 
     let delete_path = entry.path.clone();
-    let delete_name = entry.desktop_entry.name.clone();
+    let delete_name = entry.desktop_entry.display_name().to_string();
     delete_button.connect_clicked(move |_| {
         on_delete(delete_path.clone(), delete_name.clone());
     });
@@ -158,3 +280,123 @@ where
 
     row
 }
+
+pub fn create_removed_row<R, P>(entry: &TrashedEntry, on_restore: R, on_purge: P) -> adw::ActionRow
+where
+    R: Fn(TrashedEntry) + 'static,
+    P: Fn(TrashedEntry) + 'static,
+{
+    let row = adw::ActionRow::builder()
+        .title(&entry.name)
+        .use_markup(false)
+        .build();
+
+    // DeletionDate is YYYY-MM-DDThh:mm:ss; seconds are noise here
+    let deleted_at = entry.deleted_at.replacen('T', " ", 1);
+    row.set_subtitle(&format!(
+        "Removed {}",
+        deleted_at.get(..16).unwrap_or(&deleted_at)
+    ));
+    row.set_tooltip_text(Some(&entry.original_path.display().to_string()));
+
+    let restore_button = gtk4::Button::builder()
+        .icon_name("edit-undo-symbolic")
+        .valign(gtk4::Align::Center)
+        .css_classes(vec!["flat", "circular"])
+        .tooltip_text("Restore entry")
+        .build();
+
+    let restore_entry = entry.clone();
+    restore_button.connect_clicked(move |_| {
+        on_restore(restore_entry.clone());
+    });
+
+    let purge_button = gtk4::Button::builder()
+        .icon_name("user-trash-full-symbolic")
+        .valign(gtk4::Align::Center)
+        .css_classes(vec!["flat", "circular"])
+        .tooltip_text("Delete permanently")
+        .build();
+
+    let purge_entry = entry.clone();
+    purge_button.connect_clicked(move |_| {
+        on_purge(purge_entry.clone());
+    });
+
+    row.add_suffix(&restore_button);
+    row.add_suffix(&purge_button);
+
+    row
+}
+
+/// Row for a file that is skipped at login, expanding to its diagnostics.
+/// A fix button is shown when `fixes` can repair some of them.
+pub fn create_broken_row<F>(entry: &BrokenEntry, fixes: &[Fix], on_fix: F) -> adw::ExpanderRow
+where
+    F: Fn() + 'static,
+{
+    let row = adw::ExpanderRow::builder()
+        .title(format!("{}.desktop", entry.id))
+        .use_markup(false)
+        .build();
+
+    if let Some(first) = entry.diagnostics.first() {
+        row.set_subtitle(&first.message);
+    }
+    row.set_tooltip_text(Some(&entry.path.display().to_string()));
+
+    let icon = gtk4::Image::builder()
+        .icon_name("dialog-error-symbolic")
+        .pixel_size(32)
+        .css_classes(vec!["error"])
+        .build();
+    row.add_prefix(&icon);
+
+    if entry.origin != EntryOrigin::User {
+        let origin_label = gtk4::Label::builder()
+            .label(entry.origin.to_string())
+            .css_classes(vec!["dim-label", "caption"])
+            .valign(gtk4::Align::Center)
+            .build();
+        row.add_suffix(&origin_label);
+    }
+
+    if !fixes.is_empty() {
+        let tooltip: Vec<String> = fixes.iter().map(ToString::to_string).collect();
+        let fix_button = gtk4::Button::builder()
+            .label("Fix")
+            .valign(gtk4::Align::Center)
+            .tooltip_text(tooltip.join("\n"))
+            .build();
+        fix_button.connect_clicked(move |_| on_fix());
+        row.add_suffix(&fix_button);
+    }
+
+    for diagnostic in &entry.diagnostics {
+        let location = match diagnostic.line {
+            Some(line) => format!("Line {} · {}", line, diagnostic.check.name()),
+            None => diagnostic.check.name().to_string(),
+        };
+        let diagnostic_row = adw::ActionRow::builder()
+            .title(&diagnostic.message)
+            .subtitle(location)
+            .use_markup(false)
+            .build();
+
+        let (icon_name, css_class) = match diagnostic.severity {
+            Severity::Error => ("dialog-error-symbolic", "error"),
+            Severity::Warning => ("dialog-warning-symbolic", "warning"),
+        };
+        let severity_icon = gtk4::Image::builder()
+            .icon_name(icon_name)
+            .pixel_size(16)
+            .css_classes(vec![css_class])
+            .tooltip_text(diagnostic.severity.to_string())
+            .build();
+        diagnostic_row.add_prefix(&severity_icon);
+
+        row.add_row(&diagnostic_row);
+    }
+
+    row
+}
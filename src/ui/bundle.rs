@@ -0,0 +1,151 @@
+use gtk4::gio;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+use crate::operations::bundle::{
+    Bundle, ImportMode, ImportPlan, apply_import, export_bundle, plan_import,
+};
+
+use super::window::undo_toast;
+
+pub fn export_to_file(window: &adw::ApplicationWindow, toast_overlay: &adw::ToastOverlay) {
+    let dialog = gtk4::FileDialog::builder()
+        .title("Export Autostart Entries")
+        .initial_name("onset-bundle.json")
+        .modal(true)
+        .build();
+
+    let toast_overlay = toast_overlay.clone();
+    dialog.save(Some(window), gio::Cancellable::NONE, move |result| {
+        let Ok(file) = result else {
+            return;
+        };
+        let Some(path) = file.path() else {
+            return;
+        };
+
+        let message = match export_bundle()
+            .and_then(|bundle| bundle.to_json())
+            .and_then(|json| Ok(std::fs::write(&path, json + "\n")?))
+        {
+            Ok(()) => format!("Exported to {}", path.display()),
+            Err(e) => {
+                tracing::error!("Failed to export to {}: {:#}", path.display(), e);
+                format!("Failed to export: {}", e)
+            }
+        };
+        toast_overlay.add_toast(adw::Toast::new(&message));
+    });
+}
+
+pub fn import_from_file(window: &adw::ApplicationWindow, toast_overlay: &adw::ToastOverlay) {
+    let dialog = gtk4::FileDialog::builder()
+        .title("Import Autostart Entries")
+        .modal(true)
+        .build();
+
+    let window_clone = window.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.open(Some(window), gio::Cancellable::NONE, move |result| {
+        let Ok(file) = result else {
+            return;
+        };
+        let Some(path) = file.path() else {
+            return;
+        };
+
+        let plans = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Bundle::from_json(&json))
+            .and_then(|bundle| {
+                let merge = plan_import(&bundle, ImportMode::Merge)?;
+                let replace = plan_import(&bundle, ImportMode::Replace)?;
+                Ok((bundle, merge, replace))
+            });
+
+        match plans {
+            Ok((bundle, merge, replace)) => {
+                show_preview(&window_clone, &toast_overlay, bundle, merge, replace)
+            }
+            Err(e) => {
+                tracing::error!("Failed to read bundle {}: {:#}", path.display(), e);
+                toast_overlay.add_toast(adw::Toast::new(&format!("Failed to import: {:#}", e)));
+            }
+        }
+    });
+}
+
+/// Ask how to apply the bundle, listing what each mode would change.
+fn show_preview(
+    window: &adw::ApplicationWindow,
+    toast_overlay: &adw::ToastOverlay,
+    bundle: Bundle,
+    merge: ImportPlan,
+    replace: ImportPlan,
+) {
+    if !replace.has_changes() {
+        toast_overlay.add_toast(adw::Toast::new("Nothing to import"));
+        return;
+    }
+
+    let mut body = Vec::new();
+    if !merge.additions.is_empty() {
+        body.push(format!("New: {}", merge.additions.join(", ")));
+    }
+    if !merge.conflicts.is_empty() {
+        body.push(format!(
+            "Overwritten with the imported version: {}",
+            merge.conflicts.join(", ")
+        ));
+    }
+    if !replace.removals.is_empty() {
+        body.push(format!(
+            "Replacing also moves these to the trash: {}",
+            replace.removals.join(", ")
+        ));
+    }
+
+    let dialog = adw::MessageDialog::builder()
+        .heading("Import Autostart Entries?")
+        .body(body.join("\n\n"))
+        .transient_for(window)
+        .modal(true)
+        .build();
+
+    dialog.add_response("cancel", "Cancel");
+    if merge.has_changes() {
+        dialog.add_response("merge", "Merge");
+        dialog.set_response_appearance("merge", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("merge"));
+    }
+    if !replace.removals.is_empty() {
+        dialog.add_response("replace", "Replace");
+        dialog.set_response_appearance("replace", adw::ResponseAppearance::Destructive);
+    }
+    dialog.set_close_response("cancel");
+
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(None, move |_, response| {
+        let plan = match response {
+            "merge" => &merge,
+            "replace" => &replace,
+            _ => return,
+        };
+
+        let count = plan.additions.len() + plan.conflicts.len();
+        match apply_import(&bundle, plan) {
+            Ok(record) => toast_overlay.add_toast(undo_toast(
+                &format!("Imported {} entries", count),
+                record,
+                &toast_overlay,
+            )),
+            Err(e) => {
+                tracing::error!("Failed to import bundle: {:#}", e);
+                toast_overlay.add_toast(adw::Toast::new(&format!("Failed to import: {}", e)));
+            }
+        }
+    });
+
+    dialog.present();
+}
@@ -0,0 +1,143 @@
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+use crate::config::XDG_PATHS;
+use crate::desktop_entry::AutostartCondition;
+
+const KINDS: [&str; 5] = [
+    "Always",
+    "If a File Exists",
+    "Unless a File Exists",
+    "If a GSettings Key Is True",
+    "Custom",
+];
+const KIND_ALWAYS: u32 = 0;
+const KIND_IF_EXISTS: u32 = 1;
+const KIND_UNLESS_EXISTS: u32 = 2;
+const KIND_GSETTINGS: u32 = 3;
+const KIND_CUSTOM: u32 = 4;
+
+/// Rows editing `AutostartCondition` and `X-GNOME-AutoRestart`.
+pub struct ConditionRows {
+    kind_row: adw::ComboRow,
+    argument_row: adw::EntryRow,
+    restart_row: adw::SwitchRow,
+    initial: (Option<AutostartCondition>, bool),
+}
+
+impl ConditionRows {
+    pub fn new(condition: Option<&AutostartCondition>, auto_restart: bool) -> Self {
+        let (kind, argument) = match condition {
+            None => (KIND_ALWAYS, String::new()),
+            Some(AutostartCondition::IfExists(file)) => (KIND_IF_EXISTS, file.clone()),
+            Some(AutostartCondition::UnlessExists(file)) => (KIND_UNLESS_EXISTS, file.clone()),
+            Some(AutostartCondition::GSettings { schema, key }) => {
+                (KIND_GSETTINGS, format!("{} {}", schema, key))
+            }
+            Some(AutostartCondition::Other(condition)) => (KIND_CUSTOM, condition.clone()),
+        };
+
+        let kind_row = adw::ComboRow::builder()
+            .title("Start Condition")
+            .model(&gtk4::StringList::new(&KINDS))
+            .selected(kind)
+            .build();
+
+        let argument_row = adw::EntryRow::builder().text(&argument).build();
+
+        let restart_row = adw::SwitchRow::builder()
+            .title("Restart When It Exits")
+            .subtitle("Only supported by GNOME")
+            .active(auto_restart)
+            .build();
+
+        let mut rows = ConditionRows {
+            kind_row,
+            argument_row,
+            restart_row,
+            initial: Default::default(),
+        };
+        rows.initial = (rows.condition(), auto_restart);
+
+        let kind_row = rows.kind_row.clone();
+        let argument_row = rows.argument_row.clone();
+        let update = move || {
+            let kind = kind_row.selected();
+            argument_row.set_visible(kind != KIND_ALWAYS);
+            argument_row.set_title(match kind {
+                KIND_IF_EXISTS | KIND_UNLESS_EXISTS => "File in the Config Directory",
+                KIND_GSETTINGS => "Schema and Key",
+                _ => "Condition",
+            });
+            let condition = condition_from(kind, &argument_row.text());
+            kind_row.set_subtitle(&describe(condition.as_ref()));
+        };
+
+        let update_clone = update.clone();
+        rows.kind_row
+            .connect_selected_notify(move |_| update_clone());
+        let update_clone = update.clone();
+        rows.argument_row.connect_changed(move |_| update_clone());
+        update();
+
+        rows
+    }
+
+    pub fn add_to(&self, group: &adw::PreferencesGroup) {
+        group.add(&self.kind_row);
+        group.add(&self.argument_row);
+        group.add(&self.restart_row);
+    }
+
+    pub fn condition(&self) -> Option<AutostartCondition> {
+        condition_from(self.kind_row.selected(), &self.argument_row.text())
+    }
+
+    pub fn auto_restart(&self) -> bool {
+        self.restart_row.is_active()
+    }
+
+    /// The new condition, if it differs from what the entry had.
+    pub fn changed_condition(&self) -> Option<Option<AutostartCondition>> {
+        let condition = self.condition();
+        (condition != self.initial.0).then_some(condition)
+    }
+
+    pub fn changed_auto_restart(&self) -> Option<bool> {
+        let auto_restart = self.auto_restart();
+        (auto_restart != self.initial.1).then_some(auto_restart)
+    }
+}
+
+fn condition_from(kind: u32, argument: &str) -> Option<AutostartCondition> {
+    let argument = argument.trim();
+    if argument.is_empty() {
+        return None;
+    }
+
+    match kind {
+        KIND_IF_EXISTS => Some(AutostartCondition::IfExists(argument.to_string())),
+        KIND_UNLESS_EXISTS => Some(AutostartCondition::UnlessExists(argument.to_string())),
+        KIND_GSETTINGS => Some(AutostartCondition::parse(&format!(
+            "GSettings {}",
+            argument
+        ))),
+        KIND_CUSTOM => Some(AutostartCondition::parse(argument)),
+        _ => None,
+    }
+}
+
+/// Whether the condition holds right now, for the conditions that can be
+/// checked outside the session.
+fn describe(condition: Option<&AutostartCondition>) -> String {
+    let Some(condition) = condition else {
+        return "Starts at every login".to_string();
+    };
+
+    match condition.is_met(&XDG_PATHS.config_home) {
+        Some(true) => "Met now, so it starts".to_string(),
+        Some(false) => "Not met now, so it does not start".to_string(),
+        None => "Checked by the session at login".to_string(),
+    }
+}
@@ -0,0 +1,151 @@
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+use crate::config::get_current_desktop;
+use crate::desktop_entry::KNOWN_DESKTOPS;
+
+const MODES: [&str; 3] = ["All Desktops", "Only Selected", "All but Selected"];
+const MODE_ALL: u32 = 0;
+const MODE_ONLY: u32 = 1;
+const MODE_EXCEPT: u32 = 2;
+
+/// Rows choosing the desktops an entry starts on, written as `OnlyShowIn`
+/// or `NotShowIn`. Only one of the two is produced, so the lists can never
+/// contradict each other.
+pub struct DesktopPicker {
+    mode_row: adw::ComboRow,
+    desktops_row: adw::ExpanderRow,
+    checks: Vec<(String, gtk4::CheckButton)>,
+    initial: (Vec<String>, Vec<String>),
+}
+
+impl DesktopPicker {
+    pub fn new(only_show_in: &[String], not_show_in: &[String]) -> Self {
+        // OnlyShowIn decides when both are set, so it is the one shown
+        let (mode, selected) = if !only_show_in.is_empty() {
+            (MODE_ONLY, only_show_in)
+        } else if !not_show_in.is_empty() {
+            (MODE_EXCEPT, not_show_in)
+        } else {
+            (MODE_ALL, &[][..])
+        };
+
+        let mode_row = adw::ComboRow::builder()
+            .title("Desktops")
+            .model(&gtk4::StringList::new(&MODES))
+            .selected(mode)
+            .build();
+
+        let desktops_row = adw::ExpanderRow::builder()
+            .title("Selected Desktops")
+            .sensitive(mode != MODE_ALL)
+            .build();
+
+        // Keep names from the file that are not in the list
+        let mut names: Vec<String> = KNOWN_DESKTOPS.iter().map(|d| d.to_string()).collect();
+        for name in selected {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+
+        let checks: Vec<(String, gtk4::CheckButton)> = names
+            .into_iter()
+            .map(|name| {
+                let check = gtk4::CheckButton::builder()
+                    .active(selected.contains(&name))
+                    .build();
+                let row = adw::ActionRow::builder().title(&name).build();
+                row.add_prefix(&check);
+                row.set_activatable_widget(Some(&check));
+                desktops_row.add_row(&row);
+                (name, check)
+            })
+            .collect();
+
+        let mut picker = DesktopPicker {
+            mode_row,
+            desktops_row,
+            checks,
+            initial: Default::default(),
+        };
+        // Compared against what the rows show, so an untouched picker
+        // leaves a file with both lists alone
+        picker.initial = picker.lists();
+
+        let mode_row = picker.mode_row.clone();
+        let desktops_row = picker.desktops_row.clone();
+        let checks = picker.checks.clone();
+        let update = move || {
+            desktops_row.set_sensitive(mode_row.selected() != MODE_ALL);
+            mode_row.set_subtitle(&describe(mode_row.selected(), &selected_names(&checks)));
+        };
+
+        let update_clone = update.clone();
+        picker
+            .mode_row
+            .connect_selected_notify(move |_| update_clone());
+        for (_, check) in &picker.checks {
+            let update = update.clone();
+            check.connect_toggled(move |_| update());
+        }
+        update();
+
+        picker
+    }
+
+    pub fn add_to(&self, group: &adw::PreferencesGroup) {
+        group.add(&self.mode_row);
+        group.add(&self.desktops_row);
+    }
+
+    /// `OnlyShowIn` and `NotShowIn` for the current selection. Nothing
+    /// selected means every desktop.
+    pub fn lists(&self) -> (Vec<String>, Vec<String>) {
+        let selected = selected_names(&self.checks);
+        match self.mode_row.selected() {
+            MODE_ONLY => (selected, Vec::new()),
+            MODE_EXCEPT => (Vec::new(), selected),
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// The new lists, if the selection differs from what the entry had.
+    pub fn changed_lists(&self) -> Option<(Vec<String>, Vec<String>)> {
+        let lists = self.lists();
+        (lists != self.initial).then_some(lists)
+    }
+}
+
+fn selected_names(checks: &[(String, gtk4::CheckButton)]) -> Vec<String> {
+    checks
+        .iter()
+        .filter(|(_, check)| check.is_active())
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Summary of the selection, pointing out when it excludes the desktop
+/// that is running now.
+fn describe(mode: u32, selected: &[String]) -> String {
+    if mode == MODE_ALL || selected.is_empty() {
+        return "Starts on every desktop".to_string();
+    }
+
+    let current = get_current_desktop();
+    let on_current = current.iter().any(|d| selected.contains(d));
+    let list = selected.join(", ");
+
+    let (summary, excluded) = if mode == MODE_ONLY {
+        (format!("Only on {}", list), !on_current)
+    } else {
+        (format!("Not on {}", list), on_current)
+    };
+
+    if excluded && !current.is_empty() {
+        format!("{}; does not start on this desktop", summary)
+    } else {
+        summary
+    }
+}
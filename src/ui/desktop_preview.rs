@@ -0,0 +1,88 @@
+use std::cell::RefCell;
+
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+
+use crate::config::{get_current_desktop, parse_desktop_list};
+use crate::desktop_entry::KNOWN_DESKTOPS;
+
+const PREVIEW_ICON: &str = "computer-symbolic";
+
+thread_local! {
+    /// Desktop the list is shown for; `None` is the running session.
+    static PREVIEW_DESKTOP: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// The desktop selected with "Preview as", if any.
+pub fn preview_desktop() -> Option<Vec<String>> {
+    PREVIEW_DESKTOP.with_borrow(|desktop| desktop.clone())
+}
+
+/// Header-bar menu showing the entries as they would start in another
+/// desktop session. Adds the `win.preview-desktop` action to `window`;
+/// `on_change` is called to recompute the list.
+pub fn build_desktop_preview<F>(window: &adw::ApplicationWindow, on_change: F) -> gtk4::MenuButton
+where
+    F: Fn() + 'static,
+{
+    let button = gtk4::MenuButton::builder()
+        .icon_name(PREVIEW_ICON)
+        .tooltip_text("Preview as Desktop")
+        .menu_model(&build_menu())
+        .build();
+
+    let action = gio::SimpleAction::new_stateful(
+        "preview-desktop",
+        Some(glib::VariantTy::STRING),
+        &"".to_variant(),
+    );
+
+    let button_clone = button.clone();
+    action.connect_activate(move |action, parameter| {
+        let Some(name) = parameter.and_then(|p| p.str()) else {
+            return;
+        };
+
+        let desktop = (!name.is_empty()).then(|| parse_desktop_list(name));
+        PREVIEW_DESKTOP.set(desktop);
+        action.set_state(&name.to_variant());
+
+        if name.is_empty() {
+            button_clone.set_icon_name(PREVIEW_ICON);
+        } else {
+            button_clone.set_label(&format!("As {}", name));
+        }
+
+        on_change();
+    });
+    window.add_action(&action);
+
+    button
+}
+
+fn build_menu() -> gio::Menu {
+    let current = get_current_desktop();
+    let current_label = if current.is_empty() {
+        "Current Session".to_string()
+    } else {
+        format!("Current Session ({})", current.join(":"))
+    };
+
+    let session = gio::Menu::new();
+    let item = gio::MenuItem::new(Some(&current_label), None);
+    item.set_action_and_target_value(Some("win.preview-desktop"), Some(&"".to_variant()));
+    session.append_item(&item);
+
+    let desktops = gio::Menu::new();
+    for name in KNOWN_DESKTOPS {
+        let item = gio::MenuItem::new(Some(name), None);
+        item.set_action_and_target_value(Some("win.preview-desktop"), Some(&name.to_variant()));
+        desktops.append_item(&item);
+    }
+
+    let menu = gio::Menu::new();
+    menu.append_section(None, &session);
+    menu.append_section(Some("Preview As"), &desktops);
+    menu
+}
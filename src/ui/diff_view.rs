@@ -0,0 +1,18 @@
+/// Scrollable, monospace view of a unified diff.
+pub fn diff_view(diff: &str) -> gtk4::ScrolledWindow {
+    let label = gtk4::Label::builder()
+        .label(diff)
+        .xalign(0.0)
+        .yalign(0.0)
+        .selectable(true)
+        .css_classes(vec!["monospace"])
+        .build();
+
+    gtk4::ScrolledWindow::builder()
+        .child(&label)
+        .min_content_height(160)
+        .max_content_height(320)
+        .propagate_natural_height(true)
+        .css_classes(vec!["card"])
+        .build()
+}
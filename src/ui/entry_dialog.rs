@@ -1,15 +1,38 @@
+use std::cell::Cell;
+use std::path::Path;
+use std::rc::Rc;
+
 use gtk4::prelude::*;
+use gtk4::{gio, glib};
 use libadwaita as adw;
 use libadwaita::prelude::*;
 
-use crate::desktop_entry::{CreateOptions, EntryChanges};
+use crate::desktop_entry::lint::{Severity, lint_desktop_file};
+use crate::desktop_entry::locale::current_locale;
+use crate::desktop_entry::writer::{id_from_name, normalize_exec};
+use crate::desktop_entry::{CreateOptions, DelayStrategy, EntryChanges, unified_diff};
 use crate::model::AutostartEntry;
-use crate::operations::delay::unwrap_delay;
+use crate::operations::delay::preferred_delay_strategy;
+use crate::operations::preview_edit;
+
+use super::condition_rows::ConditionRows;
+use super::desktop_picker::DesktopPicker;
+use super::diff_view::diff_view;
+use super::environment_rows::EnvironmentRows;
+use super::icon_row::IconRow;
+use super::working_dir_row::WorkingDirRow;
 
 pub struct EntryDialog {
     window: adw::Window,
 }
 
+/// What the edit dialog saves: changed fields, or the whole file when it
+/// was edited as text.
+pub enum EntryEdit {
+    Changes(Box<EntryChanges>),
+    Source(String),
+}
+
 impl EntryDialog {
     pub fn new_for_create<F>(parent: &adw::ApplicationWindow, on_save: F) -> Self
     where
@@ -40,12 +63,16 @@ impl EntryDialog {
 
         let comment_row = adw::EntryRow::builder().title("Comment").build();
 
+        let icon_row = IconRow::new(None);
+
         let delay_row = adw::SpinRow::builder()
             .title("Startup Delay")
             .subtitle("Seconds to wait before starting")
             .adjustment(&gtk4::Adjustment::new(0.0, 0.0, 300.0, 1.0, 10.0, 0.0))
             .build();
 
+        let strategy_row = delay_strategy_row(preferred_delay_strategy(), &delay_row);
+
         let terminal_row = adw::SwitchRow::builder().title("Run in Terminal").build();
 
         let preferences_group = adw::PreferencesGroup::builder()
@@ -55,9 +82,23 @@ impl EntryDialog {
         preferences_group.add(&name_row);
         preferences_group.add(&command_row);
         preferences_group.add(&comment_row);
+        icon_row.add_to(&preferences_group);
         preferences_group.add(&delay_row);
+        preferences_group.add(&strategy_row);
         preferences_group.add(&terminal_row);
 
+        let working_dir_row = WorkingDirRow::new(None);
+        working_dir_row.add_to(&preferences_group);
+
+        let environment_rows = EnvironmentRows::new(&[]);
+        environment_rows.add_to(&preferences_group);
+
+        let desktop_picker = DesktopPicker::new(&[], &[]);
+        desktop_picker.add_to(&preferences_group);
+
+        let condition_rows = ConditionRows::new(None, false);
+        condition_rows.add_to(&preferences_group);
+
         let content_box = gtk4::Box::builder()
             .orientation(gtk4::Orientation::Vertical)
             .margin_start(12)
@@ -67,7 +108,19 @@ impl EntryDialog {
             .spacing(12)
             .build();
 
+        let command_error = command_error_label();
+
         content_box.append(&preferences_group);
+        content_box.append(&command_error);
+
+        connect_command_validation(&command_row, &command_error, None);
+        {
+            let command_error = command_error.clone();
+            let save_button = save_button.clone();
+            command_row.connect_changed(move |_| {
+                save_button.set_sensitive(!command_error.is_visible());
+            });
+        }
 
         let toolbar_view = adw::ToolbarView::new();
         toolbar_view.add_top_bar(&header_bar);
@@ -95,17 +148,15 @@ impl EntryDialog {
                 let command = command_row_clone.text().to_string();
                 let comment = comment_row_clone.text().to_string();
                 let delay = delay_row_clone.value() as u32;
+                let delay_strategy = selected_strategy(&strategy_row);
                 let terminal = terminal_row_clone.is_active();
 
                 if name.is_empty() || command.is_empty() {
                     return;
                 }
 
-                let id = name
-                    .to_lowercase()
-                    .chars()
-                    .map(|c| if c.is_alphanumeric() { c } else { '-' })
-                    .collect::<String>();
+                let id = id_from_name(&name);
+                let (only_show_in, not_show_in) = desktop_picker.lists();
 
                 let options = CreateOptions {
                     comment: if comment.is_empty() {
@@ -113,8 +164,16 @@ impl EntryDialog {
                     } else {
                         Some(comment)
                     },
+                    icon: icon_row.icon(),
                     delay_seconds: delay,
+                    delay_strategy: Some(delay_strategy),
                     terminal,
+                    working_dir: working_dir_row.working_dir(),
+                    environment: environment_rows.environment(),
+                    only_show_in,
+                    not_show_in,
+                    autostart_condition: condition_rows.condition(),
+                    auto_restart: condition_rows.auto_restart(),
                     ..Default::default()
                 };
 
@@ -132,7 +191,7 @@ impl EntryDialog {
         on_save: F,
     ) -> Self
     where
-        F: Fn(EntryChanges) + 'static,
+        F: Fn(EntryEdit) + 'static,
     {
         let window = adw::Window::builder()
             .title("Edit Autostart Entry")
@@ -150,14 +209,54 @@ impl EntryDialog {
             .css_classes(vec!["suggested-action"])
             .build();
 
+        let source_button = gtk4::ToggleButton::builder()
+            .icon_name("text-x-generic-symbolic")
+            .tooltip_text("Edit Source")
+            .build();
+
         header_bar.pack_start(&cancel_button);
         header_bar.pack_end(&save_button);
-
-        let (base_exec, current_delay) = unwrap_delay(&entry.desktop_entry.exec);
+        header_bar.pack_end(&source_button);
+
+        let base_exec = entry.desktop_entry.exec.clone();
+        let current_delay = entry.desktop_entry.delay;
+        // Strategies without a duration are shown as a one second delay, so
+        // that zero keeps meaning "no delay"
+        let initial_delay = current_delay.map_or(0, |d| d.seconds.unwrap_or(1));
+        let initial_strategy = current_delay
+            .map(|d| d.strategy)
+            .unwrap_or_else(preferred_delay_strategy);
+
+        // Translations are edited for the locale the entry is already shown
+        // in, or the user's language when the entry has no translation yet.
+        let desktop = &entry.desktop_entry;
+        let translation_locale = desktop
+            .localized_name
+            .as_ref()
+            .or(desktop.localized_comment.as_ref())
+            .map(|l| l.locale.clone())
+            .or_else(|| current_locale().map(|l| l.lang.clone()));
+        let has_translation =
+            desktop.localized_name.is_some() || desktop.localized_comment.is_some();
+
+        let default_texts = (
+            desktop.name.clone(),
+            desktop.comment.clone().unwrap_or_default(),
+        );
+        let localized_texts = (
+            desktop.display_name().to_string(),
+            desktop.display_comment().unwrap_or_default().to_string(),
+        );
+
+        let (initial_name, initial_comment) = if has_translation {
+            localized_texts.clone()
+        } else {
+            default_texts.clone()
+        };
 
         let name_row = adw::EntryRow::builder()
             .title("Name")
-            .text(&entry.desktop_entry.name)
+            .text(&initial_name)
             .build();
 
         let command_row = adw::EntryRow::builder()
@@ -167,14 +266,43 @@ impl EntryDialog {
 
         let comment_row = adw::EntryRow::builder()
             .title("Comment")
-            .text(entry.desktop_entry.comment.as_deref().unwrap_or(""))
+            .text(&initial_comment)
+            .build();
+
+        let icon_row = IconRow::new(desktop.icon.as_deref());
+
+        let translation_row = adw::SwitchRow::builder()
+            .title("Edit Translation")
+            .subtitle(format!(
+                "Change the name and comment shown for the \"{}\" locale",
+                translation_locale.as_deref().unwrap_or_default()
+            ))
+            .active(has_translation)
+            .visible(translation_locale.is_some())
             .build();
 
+        {
+            let name_row = name_row.clone();
+            let comment_row = comment_row.clone();
+            let default_texts = default_texts.clone();
+            let localized_texts = localized_texts.clone();
+
+            translation_row.connect_active_notify(move |row| {
+                let (name, comment) = if row.is_active() {
+                    &localized_texts
+                } else {
+                    &default_texts
+                };
+                name_row.set_text(name);
+                comment_row.set_text(comment);
+            });
+        }
+
         let delay_row = adw::SpinRow::builder()
             .title("Startup Delay")
             .subtitle("Seconds to wait before starting")
             .adjustment(&gtk4::Adjustment::new(
-                current_delay.unwrap_or(0) as f64,
+                initial_delay as f64,
                 0.0,
                 300.0,
                 1.0,
@@ -183,6 +311,8 @@ impl EntryDialog {
             ))
             .build();
 
+        let strategy_row = delay_strategy_row(initial_strategy, &delay_row);
+
         let terminal_row = adw::SwitchRow::builder()
             .title("Run in Terminal")
             .active(entry.desktop_entry.terminal)
@@ -195,9 +325,25 @@ impl EntryDialog {
         preferences_group.add(&name_row);
         preferences_group.add(&command_row);
         preferences_group.add(&comment_row);
+        preferences_group.add(&translation_row);
+        icon_row.add_to(&preferences_group);
         preferences_group.add(&delay_row);
+        preferences_group.add(&strategy_row);
         preferences_group.add(&terminal_row);
 
+        let working_dir_row = WorkingDirRow::new(desktop.working_dir.as_deref());
+        working_dir_row.add_to(&preferences_group);
+
+        let environment_rows = EnvironmentRows::new(&desktop.environment);
+        environment_rows.add_to(&preferences_group);
+
+        let desktop_picker = DesktopPicker::new(&desktop.only_show_in, &desktop.not_show_in);
+        desktop_picker.add_to(&preferences_group);
+
+        let condition_rows =
+            ConditionRows::new(desktop.autostart_condition.as_ref(), desktop.auto_restart);
+        condition_rows.add_to(&preferences_group);
+
         let content_box = gtk4::Box::builder()
             .orientation(gtk4::Orientation::Vertical)
             .margin_start(12)
@@ -207,14 +353,73 @@ impl EntryDialog {
             .spacing(12)
             .build();
 
+        let command_error = command_error_label();
+
         content_box.append(&preferences_group);
+        content_box.append(&command_error);
+
+        // The existing command is accepted as-is even if it does not follow
+        // the spec, so that other fields can still be changed.
+        connect_command_validation(&command_row, &command_error, Some(base_exec.clone()));
+
+        let removed_banner = adw::Banner::builder()
+            .title("This entry was removed by another program")
+            .build();
+
+        let (source_box, source_buffer, source_valid) = source_editor(&entry.raw_content);
+
+        let stack = gtk4::Stack::new();
+        stack.add_named(&content_box, Some("form"));
+        stack.add_named(&source_box, Some("source"));
+
+        // The two modes are separate drafts; saving uses the visible one
+        {
+            let stack = stack.clone();
+            source_button.connect_toggled(move |button| {
+                stack.set_visible_child_name(if button.is_active() { "source" } else { "form" });
+            });
+        }
 
         let toolbar_view = adw::ToolbarView::new();
         toolbar_view.add_top_bar(&header_bar);
-        toolbar_view.set_content(Some(&content_box));
+        toolbar_view.add_top_bar(&removed_banner);
+        toolbar_view.set_content(Some(&stack));
 
         window.set_content(Some(&toolbar_view));
 
+        watch_for_removal(&window, &entry.path, &removed_banner);
+
+        // Saving needs the file to still exist and the visible draft to be
+        // valid. The handlers below run after the ones that track each.
+        let update_save = {
+            let save_button = save_button.clone();
+            let source_button = source_button.clone();
+            let source_valid = source_valid.clone();
+            let command_error = command_error.clone();
+            let removed_banner = removed_banner.clone();
+            Rc::new(move || {
+                let valid = if source_button.is_active() {
+                    source_valid.get()
+                } else {
+                    !command_error.is_visible()
+                };
+                save_button.set_sensitive(valid && !removed_banner.is_revealed());
+            })
+        };
+        {
+            let update_save = update_save.clone();
+            command_row.connect_changed(move |_| update_save());
+        }
+        {
+            let update_save = update_save.clone();
+            source_button.connect_toggled(move |_| update_save());
+        }
+        {
+            let update_save = update_save.clone();
+            source_buffer.connect_changed(move |_| update_save());
+        }
+        removed_banner.connect_revealed_notify(move |_| update_save());
+
         {
             let window_clone = window.clone();
             cancel_button.connect_clicked(move |_| {
@@ -222,42 +427,105 @@ impl EntryDialog {
             });
         }
 
-        let original_name = entry.desktop_entry.name.clone();
         let original_exec = base_exec.clone();
-        let original_comment = entry.desktop_entry.comment.clone();
-        let original_delay = current_delay;
+        let original_delay = initial_delay;
         let original_terminal = entry.desktop_entry.terminal;
+        let entry = entry.clone();
+        let on_save = Rc::new(on_save);
 
         {
             let window_clone = window.clone();
 
             save_button.connect_clicked(move |_| {
+                if source_button.is_active() {
+                    if !source_valid.get() {
+                        return;
+                    }
+                    let content = source_buffer
+                        .text(
+                            &source_buffer.start_iter(),
+                            &source_buffer.end_iter(),
+                            false,
+                        )
+                        .to_string();
+                    let diff = unified_diff(
+                        &entry.raw_content,
+                        &content,
+                        &entry.path.display().to_string(),
+                        &entry.user_path().display().to_string(),
+                    );
+                    if diff.is_empty() {
+                        window_clone.close();
+                        return;
+                    }
+
+                    let on_save = on_save.clone();
+                    let window = window_clone.clone();
+                    confirm_changes(&window_clone, &entry.user_path(), &diff, move || {
+                        on_save(EntryEdit::Source(content.clone()));
+                        window.close();
+                    });
+                    return;
+                }
+
                 let new_name = name_row.text().to_string();
                 let new_exec = command_row.text().to_string();
                 let new_comment = comment_row.text().to_string();
                 let new_delay = delay_row.value() as u32;
+                let new_strategy = selected_strategy(&strategy_row);
                 let new_terminal = terminal_row.is_active();
 
                 let mut changes = EntryChanges::default();
 
-                if new_name != original_name {
+                let (original_name, original_comment) = if translation_row.is_active() {
+                    changes.locale = translation_locale.clone();
+                    &localized_texts
+                } else {
+                    &default_texts
+                };
+
+                if &new_name != original_name {
                     changes.name = Some(new_name);
                 }
                 if new_exec != original_exec {
                     changes.exec = Some(new_exec);
                 }
-                if Some(&new_comment) != original_comment.as_ref() {
+                if &new_comment != original_comment {
                     changes.comment = Some(new_comment);
                 }
-                if Some(new_delay) != original_delay {
+                if new_delay != original_delay {
                     changes.delay_seconds = Some(new_delay);
                 }
+                if new_strategy != initial_strategy {
+                    changes.delay_strategy = Some(new_strategy);
+                }
                 if new_terminal != original_terminal {
                     changes.terminal = Some(new_terminal);
                 }
+                changes.icon = icon_row.changed_icon();
+                changes.working_dir = working_dir_row.changed_working_dir();
+                changes.environment = environment_rows.changed_environment();
+                if let Some((only_show_in, not_show_in)) = desktop_picker.changed_lists() {
+                    changes.only_show_in = Some(only_show_in);
+                    changes.not_show_in = Some(not_show_in);
+                }
+                changes.autostart_condition = condition_rows.changed_condition();
+                changes.auto_restart = condition_rows.changed_auto_restart();
+
+                // Errors are left to `on_save`, which reports them
+                let diff = preview_edit(&entry, changes.clone()).unwrap_or_default();
+                if diff.is_empty() {
+                    on_save(EntryEdit::Changes(Box::new(changes)));
+                    window_clone.close();
+                    return;
+                }
 
-                on_save(changes);
-                window_clone.close();
+                let on_save = on_save.clone();
+                let window = window_clone.clone();
+                confirm_changes(&window_clone, &entry.user_path(), &diff, move || {
+                    on_save(EntryEdit::Changes(Box::new(changes.clone())));
+                    window.close();
+                });
             });
         }
 
@@ -268,3 +536,206 @@ impl EntryDialog {
         self.window.present();
     }
 }
+
+/// Text view of the file, linted while it is typed. The returned flag is
+/// false while the linter reports errors.
+fn source_editor(content: &str) -> (gtk4::Box, gtk4::TextBuffer, Rc<Cell<bool>>) {
+    let buffer = gtk4::TextBuffer::new(None);
+    buffer.set_text(content);
+
+    let text_view = gtk4::TextView::builder()
+        .buffer(&buffer)
+        .monospace(true)
+        .top_margin(6)
+        .bottom_margin(6)
+        .left_margin(6)
+        .right_margin(6)
+        .build();
+
+    let scrolled_window = gtk4::ScrolledWindow::builder()
+        .child(&text_view)
+        .min_content_height(320)
+        .vexpand(true)
+        .css_classes(vec!["card"])
+        .build();
+
+    let problems_label = gtk4::Label::builder()
+        .css_classes(vec!["caption"])
+        .xalign(0.0)
+        .wrap(true)
+        .selectable(true)
+        .visible(false)
+        .build();
+
+    let source_box = gtk4::Box::builder()
+        .orientation(gtk4::Orientation::Vertical)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .spacing(12)
+        .build();
+    source_box.append(&scrolled_window);
+    source_box.append(&problems_label);
+
+    let valid = Rc::new(Cell::new(show_source_problems(content, &problems_label)));
+    let valid_clone = valid.clone();
+    buffer.connect_changed(move |buffer| {
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+        valid_clone.set(show_source_problems(&text, &problems_label));
+    });
+
+    (source_box, buffer, valid)
+}
+
+/// List what the linter finds in `content`; false if there are errors.
+fn show_source_problems(content: &str, label: &gtk4::Label) -> bool {
+    let diagnostics = lint_desktop_file(content);
+    let has_errors = diagnostics.iter().any(|d| d.severity == Severity::Error);
+
+    let lines: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+    label.set_label(&lines.join("\n"));
+    label.set_visible(!lines.is_empty());
+    if has_errors {
+        label.add_css_class("error");
+    } else {
+        label.remove_css_class("error");
+    }
+
+    !has_errors
+}
+
+/// Show the diff of an edit and call `on_confirm` if it is accepted.
+fn confirm_changes<F>(parent: &adw::Window, path: &Path, diff: &str, on_confirm: F)
+where
+    F: Fn() + 'static,
+{
+    let dialog = adw::MessageDialog::builder()
+        .heading("Save Changes?")
+        .body(format!("{} will be changed as follows.", path.display()))
+        .extra_child(&diff_view(diff))
+        .transient_for(parent)
+        .modal(true)
+        .build();
+
+    dialog.add_response("cancel", "Keep Editing");
+    dialog.add_response("save", "Save");
+    dialog.set_response_appearance("save", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+
+    dialog.connect_response(Some("save"), move |_, _| on_confirm());
+
+    dialog.present();
+}
+
+fn delay_strategy_row(selected: DelayStrategy, delay_row: &adw::SpinRow) -> adw::ComboRow {
+    let labels: Vec<String> = DelayStrategy::ALL.iter().map(|s| s.to_string()).collect();
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+
+    let row = adw::ComboRow::builder()
+        .title("Delay Method")
+        .model(&gtk4::StringList::new(&labels))
+        .selected(
+            DelayStrategy::ALL
+                .iter()
+                .position(|&s| s == selected)
+                .unwrap_or_default() as u32,
+        )
+        .build();
+
+    update_delay_subtitle(delay_row, selected);
+
+    let delay_row = delay_row.clone();
+    row.connect_selected_notify(move |row| {
+        update_delay_subtitle(&delay_row, selected_strategy(row));
+    });
+
+    row
+}
+
+fn selected_strategy(row: &adw::ComboRow) -> DelayStrategy {
+    DelayStrategy::ALL
+        .get(row.selected() as usize)
+        .copied()
+        .unwrap_or_default()
+}
+
+fn update_delay_subtitle(delay_row: &adw::SpinRow, strategy: DelayStrategy) {
+    delay_row.set_subtitle(if strategy.supports_seconds() {
+        "Seconds to wait before starting"
+    } else {
+        "Any value above zero starts it after the desktop is ready"
+    });
+}
+
+/// Reveal `banner` while the file being edited does not exist, e.g. after
+/// `onset rm` or a file manager deleted it.
+fn watch_for_removal(window: &adw::Window, path: &Path, banner: &adw::Banner) {
+    let monitor = match gio::File::for_path(path)
+        .monitor_file(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+    {
+        Ok(monitor) => monitor,
+        Err(e) => {
+            tracing::warn!("Cannot watch {}: {}", path.display(), e);
+            return;
+        }
+    };
+
+    let path = path.to_path_buf();
+    let banner = banner.clone();
+
+    // Atomic writes replace the file, so check whether it still exists
+    // rather than relying on the event type
+    monitor.connect_changed(move |_, _, _, _| {
+        let exists = path.exists();
+        if banner.is_revealed() == exists {
+            banner.set_revealed(!exists);
+        }
+    });
+
+    window.connect_close_request(move |_| {
+        monitor.cancel();
+        glib::Propagation::Proceed
+    });
+}
+
+fn command_error_label() -> gtk4::Label {
+    gtk4::Label::builder()
+        .css_classes(vec!["error", "caption"])
+        .xalign(0.0)
+        .wrap(true)
+        .visible(false)
+        .build()
+}
+
+/// Validate the command while it is typed, showing the problem below the
+/// settings; the label stays visible until it is fixed.
+fn connect_command_validation(
+    command_row: &adw::EntryRow,
+    error_label: &gtk4::Label,
+    accepted: Option<String>,
+) {
+    let error_label = error_label.clone();
+
+    command_row.connect_changed(move |row| {
+        let text = row.text();
+        let error = if text.is_empty() || accepted.as_deref() == Some(text.as_str()) {
+            None
+        } else {
+            normalize_exec(&text).err()
+        };
+
+        match error {
+            Some(error) => {
+                row.add_css_class("error");
+                error_label.set_label(&format!("{:#}", error));
+                error_label.set_visible(true);
+            }
+            None => {
+                row.remove_css_class("error");
+                error_label.set_visible(false);
+            }
+        }
+    });
+}
@@ -0,0 +1,165 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+use crate::desktop_entry::exec::is_env_name;
+
+type Variables = Rc<RefCell<Vec<(gtk4::Box, gtk4::Entry, gtk4::Entry)>>>;
+
+/// Table of the variables the command is started with, written as an
+/// `env NAME=value` prefix of Exec.
+pub struct EnvironmentRows {
+    expander: adw::ExpanderRow,
+    variables: Variables,
+    initial: Vec<(String, String)>,
+}
+
+impl EnvironmentRows {
+    pub fn new(environment: &[(String, String)]) -> Self {
+        let expander = adw::ExpanderRow::builder()
+            .title("Environment Variables")
+            .build();
+
+        let add_row = adw::ActionRow::builder()
+            .title("Add Variable")
+            .activatable(true)
+            .build();
+        add_row.add_prefix(&gtk4::Image::from_icon_name("list-add-symbolic"));
+        expander.add_row(&add_row);
+
+        let variables: Variables = Rc::new(RefCell::new(Vec::new()));
+        for (name, value) in environment {
+            add_variable(&expander, &variables, name, value);
+        }
+        update_subtitle(&expander, &variables);
+
+        let expander_clone = expander.clone();
+        let variables_clone = variables.clone();
+        add_row.connect_activated(move |_| {
+            let name_entry = add_variable(&expander_clone, &variables_clone, "", "");
+            name_entry.grab_focus();
+        });
+
+        EnvironmentRows {
+            expander,
+            variables,
+            initial: environment.to_vec(),
+        }
+    }
+
+    pub fn add_to(&self, group: &adw::PreferencesGroup) {
+        group.add(&self.expander);
+    }
+
+    /// Variables in table order; rows without a name are skipped.
+    pub fn environment(&self) -> Vec<(String, String)> {
+        read_variables(&self.variables)
+    }
+
+    /// The new variables, if they differ from what the entry had.
+    pub fn changed_environment(&self) -> Option<Vec<(String, String)>> {
+        let environment = self.environment();
+        (environment != self.initial).then_some(environment)
+    }
+}
+
+/// Append a row for one variable and return its name entry.
+fn add_variable(
+    expander: &adw::ExpanderRow,
+    variables: &Variables,
+    name: &str,
+    value: &str,
+) -> gtk4::Entry {
+    let name_entry = gtk4::Entry::builder()
+        .text(name)
+        .placeholder_text("NAME")
+        .width_chars(12)
+        .build();
+    let value_entry = gtk4::Entry::builder()
+        .text(value)
+        .placeholder_text("value")
+        .hexpand(true)
+        .build();
+    let remove_button = gtk4::Button::builder()
+        .icon_name("list-remove-symbolic")
+        .valign(gtk4::Align::Center)
+        .css_classes(vec!["flat", "circular"])
+        .tooltip_text("Remove Variable")
+        .build();
+
+    let row_box = gtk4::Box::builder()
+        .orientation(gtk4::Orientation::Horizontal)
+        .spacing(6)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(6)
+        .margin_bottom(6)
+        .build();
+    row_box.append(&name_entry);
+    row_box.append(&gtk4::Label::new(Some("=")));
+    row_box.append(&value_entry);
+    row_box.append(&remove_button);
+
+    expander.add_row(&row_box);
+    variables
+        .borrow_mut()
+        .push((row_box.clone(), name_entry.clone(), value_entry.clone()));
+
+    {
+        let expander = expander.clone();
+        let variables = variables.clone();
+        name_entry.connect_changed(move |entry| {
+            let name = entry.text();
+            if name.is_empty() || is_env_name(&name) {
+                entry.remove_css_class("error");
+            } else {
+                entry.add_css_class("error");
+            }
+            update_subtitle(&expander, &variables);
+        });
+    }
+    {
+        let expander = expander.clone();
+        let variables = variables.clone();
+        value_entry.connect_changed(move |_| update_subtitle(&expander, &variables));
+    }
+    {
+        let expander = expander.clone();
+        let variables = variables.clone();
+        let row_box = row_box.clone();
+        remove_button.connect_clicked(move |_| {
+            variables.borrow_mut().retain(|(b, _, _)| b != &row_box);
+            // Rows added to an expander are wrapped in a list box row
+            if let Some(parent) = row_box.parent() {
+                expander.remove(&parent);
+            }
+            update_subtitle(&expander, &variables);
+        });
+    }
+
+    name_entry
+}
+
+fn read_variables(variables: &Variables) -> Vec<(String, String)> {
+    variables
+        .borrow()
+        .iter()
+        .map(|(_, name, value)| (name.text().trim().to_string(), value.text().to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+fn update_subtitle(expander: &adw::ExpanderRow, variables: &Variables) {
+    let names: Vec<String> = read_variables(variables)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    if names.is_empty() {
+        expander.set_subtitle("None");
+    } else {
+        expander.set_subtitle(&names.join(", "));
+    }
+}
@@ -0,0 +1,59 @@
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+use crate::operations::fix::{FixPlan, apply_fix};
+
+use super::diff_view::diff_view;
+use super::window::undo_toast;
+
+/// Show what a fix changes and write it only once confirmed.
+pub fn show_fix_preview(
+    window: &adw::ApplicationWindow,
+    toast_overlay: &adw::ToastOverlay,
+    plan: FixPlan,
+) {
+    let file_name = plan
+        .target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut body: Vec<String> = plan.applied.iter().map(ToString::to_string).collect();
+    if plan.source != plan.target {
+        body.push(format!(
+            "The fixed copy is saved as {} and overrides the system file.",
+            plan.target.display()
+        ));
+    }
+
+    let scrolled_window = diff_view(&plan.diff());
+
+    let dialog = adw::MessageDialog::builder()
+        .heading(format!("Fix {}?", file_name))
+        .body(body.join("\n"))
+        .extra_child(&scrolled_window)
+        .transient_for(window)
+        .modal(true)
+        .build();
+
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("fix", "Fix");
+    dialog.set_response_appearance("fix", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("fix"));
+    dialog.set_close_response("cancel");
+
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(Some("fix"), move |_, _| match apply_fix(&plan) {
+        Ok(record) => toast_overlay.add_toast(undo_toast(
+            &format!("Fixed {}", file_name),
+            record,
+            &toast_overlay,
+        )),
+        Err(e) => {
+            tracing::error!("Failed to fix {}: {:#}", plan.source.display(), e);
+            toast_overlay.add_toast(adw::Toast::new(&format!("Failed to fix: {}", e)));
+        }
+    });
+
+    dialog.present();
+}
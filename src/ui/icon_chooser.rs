@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Browse the icons of the current theme and pass the chosen name to
+/// `on_choose`.
+pub fn show_icon_chooser<F>(parent: Option<&gtk4::Window>, on_choose: F)
+where
+    F: Fn(String) + 'static,
+{
+    let window = adw::Window::builder()
+        .title("Choose Icon")
+        .default_width(520)
+        .default_height(560)
+        .modal(true)
+        .build();
+    window.set_transient_for(parent);
+
+    let header_bar = adw::HeaderBar::new();
+
+    let search_entry = gtk4::SearchEntry::builder()
+        .placeholder_text("Search icons...")
+        .hexpand(true)
+        .build();
+
+    let mut names: Vec<String> = gtk4::IconTheme::for_display(&WidgetExt::display(&window))
+        .icon_names()
+        .into_iter()
+        .map(String::from)
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    let name_refs: Vec<&str> = names.iter().map(String::as_str).collect();
+    let model = gtk4::StringList::new(&name_refs);
+
+    let query = Rc::new(RefCell::new(String::new()));
+    let query_clone = query.clone();
+    let filter = gtk4::CustomFilter::new(move |item| {
+        let query = query_clone.borrow();
+        query.is_empty()
+            || item
+                .downcast_ref::<gtk4::StringObject>()
+                .is_some_and(|name| name.string().to_lowercase().contains(query.as_str()))
+    });
+    let filter_model = gtk4::FilterListModel::new(Some(model), Some(filter.clone()));
+
+    search_entry.connect_search_changed(move |entry| {
+        *query.borrow_mut() = entry.text().trim().to_lowercase();
+        filter.changed(gtk4::FilterChange::Different);
+    });
+
+    let factory = gtk4::SignalListItemFactory::new();
+    factory.connect_setup(|_, item| {
+        let Some(item) = item.downcast_ref::<gtk4::ListItem>() else {
+            return;
+        };
+        let image = gtk4::Image::builder().pixel_size(32).build();
+        let label = gtk4::Label::builder()
+            .ellipsize(gtk4::pango::EllipsizeMode::Middle)
+            .max_width_chars(12)
+            .css_classes(vec!["caption"])
+            .build();
+        let item_box = gtk4::Box::builder()
+            .orientation(gtk4::Orientation::Vertical)
+            .spacing(6)
+            .margin_top(6)
+            .margin_bottom(6)
+            .build();
+        item_box.append(&image);
+        item_box.append(&label);
+        item.set_child(Some(&item_box));
+    });
+    factory.connect_bind(|_, item| {
+        let Some(item) = item.downcast_ref::<gtk4::ListItem>() else {
+            return;
+        };
+        let Some(name) = item.item().and_downcast::<gtk4::StringObject>() else {
+            return;
+        };
+        let Some(item_box) = item.child() else {
+            return;
+        };
+        let name = name.string();
+        item_box.set_tooltip_text(Some(&name));
+        if let Some(image) = item_box.first_child().and_downcast::<gtk4::Image>() {
+            image.set_icon_name(Some(&name));
+        }
+        if let Some(label) = item_box.last_child().and_downcast::<gtk4::Label>() {
+            label.set_label(&name);
+        }
+    });
+
+    let grid_view = gtk4::GridView::builder()
+        .model(&gtk4::NoSelection::new(Some(filter_model.clone())))
+        .factory(&factory)
+        .max_columns(6)
+        .single_click_activate(true)
+        .build();
+
+    let scrolled_window = gtk4::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .vexpand(true)
+        .child(&grid_view)
+        .css_classes(vec!["card"])
+        .build();
+
+    let content_box = gtk4::Box::builder()
+        .orientation(gtk4::Orientation::Vertical)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .spacing(12)
+        .build();
+    content_box.append(&search_entry);
+    content_box.append(&scrolled_window);
+
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&header_bar);
+    toolbar_view.set_content(Some(&content_box));
+    window.set_content(Some(&toolbar_view));
+
+    let window_clone = window.clone();
+    grid_view.connect_activate(move |_, position| {
+        let Some(name) = filter_model
+            .item(position)
+            .and_downcast::<gtk4::StringObject>()
+        else {
+            return;
+        };
+        on_choose(name.string().to_string());
+        window_clone.close();
+    });
+
+    window.present();
+    search_entry.grab_focus();
+}
@@ -0,0 +1,114 @@
+use gtk4::gio;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+use super::icon_chooser::show_icon_chooser;
+
+/// Shown when an entry has no icon, as in the list.
+const FALLBACK_ICON: &str = "application-x-executable-symbolic";
+
+/// Row editing `Icon`: a theme icon name or the path of an image file,
+/// typed, picked from the theme or chosen as a file.
+pub struct IconRow {
+    row: adw::EntryRow,
+    initial: String,
+}
+
+impl IconRow {
+    pub fn new(icon: Option<&str>) -> Self {
+        let row = adw::EntryRow::builder()
+            .title("Icon")
+            .text(icon.unwrap_or_default())
+            .build();
+
+        let preview = gtk4::Image::builder().pixel_size(32).build();
+        row.add_prefix(&preview);
+
+        let theme_button = gtk4::Button::builder()
+            .icon_name("view-grid-symbolic")
+            .valign(gtk4::Align::Center)
+            .css_classes(vec!["flat"])
+            .tooltip_text("Choose from Icon Theme")
+            .build();
+        let file_button = gtk4::Button::builder()
+            .icon_name("folder-open-symbolic")
+            .valign(gtk4::Align::Center)
+            .css_classes(vec!["flat"])
+            .tooltip_text("Choose Image File")
+            .build();
+        row.add_suffix(&theme_button);
+        row.add_suffix(&file_button);
+
+        let row_clone = row.clone();
+        theme_button.connect_clicked(move |_| {
+            let parent = row_clone.root().and_downcast::<gtk4::Window>();
+            let row = row_clone.clone();
+            show_icon_chooser(parent.as_ref(), move |name| row.set_text(&name));
+        });
+
+        let row_clone = row.clone();
+        file_button.connect_clicked(move |_| choose_file(&row_clone));
+
+        update_preview(&preview, &row.text());
+        row.connect_changed(move |row| update_preview(&preview, &row.text()));
+
+        IconRow {
+            row,
+            initial: icon.unwrap_or_default().to_string(),
+        }
+    }
+
+    pub fn add_to(&self, group: &adw::PreferencesGroup) {
+        group.add(&self.row);
+    }
+
+    pub fn icon(&self) -> Option<String> {
+        let text = self.row.text();
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    /// The new icon, if it differs from what the entry had; empty removes
+    /// it.
+    pub fn changed_icon(&self) -> Option<String> {
+        let icon = self.icon().unwrap_or_default();
+        (icon != self.initial).then_some(icon)
+    }
+}
+
+fn update_preview(preview: &gtk4::Image, icon: &str) {
+    let icon = icon.trim();
+    if icon.starts_with('/') {
+        preview.set_from_file(Some(icon));
+    } else if icon.is_empty() {
+        preview.set_icon_name(Some(FALLBACK_ICON));
+    } else {
+        preview.set_icon_name(Some(icon));
+    }
+}
+
+fn choose_file(row: &adw::EntryRow) {
+    let filter = gtk4::FileFilter::new();
+    filter.set_name(Some("Images"));
+    filter.add_pixbuf_formats();
+    let filters = gio::ListStore::new::<gtk4::FileFilter>();
+    filters.append(&filter);
+
+    let dialog = gtk4::FileDialog::builder()
+        .title("Choose Icon File")
+        .filters(&filters)
+        .modal(true)
+        .build();
+
+    let parent = row.root().and_downcast::<gtk4::Window>();
+    let row = row.clone();
+    dialog.open(parent.as_ref(), gio::Cancellable::NONE, move |result| {
+        let Ok(file) = result else {
+            return;
+        };
+        if let Some(path) = file.path() {
+            row.set_text(&path.display().to_string());
+        }
+    });
+}
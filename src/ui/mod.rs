@@ -1,6 +1,18 @@
 mod app_chooser;
 mod autostart_row;
+mod bundle;
+mod condition_rows;
+mod desktop_picker;
+mod desktop_preview;
+mod diff_view;
 mod entry_dialog;
+mod environment_rows;
+mod fix_preview;
+mod icon_chooser;
+mod icon_row;
+mod profiles;
+mod run_log;
 mod window;
+mod working_dir_row;
 
 pub use window::MainWindow;
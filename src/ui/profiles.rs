@@ -0,0 +1,164 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+use crate::config::Preferences;
+use crate::operations::profile::{
+    ProfileChange, apply_profile, capture_profile, list_profiles, load_profile, save_profile,
+};
+
+use super::window::undo_toast;
+
+/// Number of changes spelled out in the toast after switching profiles.
+const MAX_LISTED_CHANGES: usize = 2;
+
+/// Header-bar button showing the active profile, with a menu to switch
+/// profiles or save the current entries as a new one. Adds the
+/// `win.profile` and `win.save-profile` actions to `window`.
+pub fn build_profile_switcher(
+    window: &adw::ApplicationWindow,
+    toast_overlay: &adw::ToastOverlay,
+) -> gtk4::MenuButton {
+    let active = Preferences::load().active_profile;
+
+    let button = gtk4::MenuButton::builder()
+        .label(active.as_deref().unwrap_or("No Profile"))
+        .tooltip_text("Autostart Profile")
+        .menu_model(&build_menu())
+        .build();
+
+    let switch = gio::SimpleAction::new_stateful(
+        "profile",
+        Some(glib::VariantTy::STRING),
+        &active.unwrap_or_default().to_variant(),
+    );
+
+    let button_clone = button.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    switch.connect_activate(move |action, parameter| {
+        let Some(name) = parameter.and_then(|p| p.str()) else {
+            return;
+        };
+
+        match load_profile(name).and_then(|profile| apply_profile(&profile)) {
+            Ok((changes, record)) => {
+                action.set_state(&name.to_variant());
+                button_clone.set_label(name);
+                toast_overlay_clone.add_toast(undo_toast(
+                    &describe_switch(name, &changes),
+                    record,
+                    &toast_overlay_clone,
+                ));
+            }
+            Err(e) => {
+                tracing::error!("Failed to switch to profile {}: {:#}", name, e);
+                toast_overlay_clone
+                    .add_toast(adw::Toast::new(&format!("Failed to switch profile: {}", e)));
+            }
+        }
+    });
+    window.add_action(&switch);
+
+    let save = gio::SimpleAction::new("save-profile", None);
+    let window_clone = window.clone();
+    let button_clone = button.clone();
+    let toast_overlay_clone = toast_overlay.clone();
+    save.connect_activate(move |_, _| {
+        show_save_dialog(&window_clone, &button_clone, &toast_overlay_clone);
+    });
+    window.add_action(&save);
+
+    button
+}
+
+fn build_menu() -> gio::Menu {
+    let profiles = gio::Menu::new();
+    for name in list_profiles() {
+        let item = gio::MenuItem::new(Some(&name), None);
+        item.set_action_and_target_value(Some("win.profile"), Some(&name.to_variant()));
+        profiles.append_item(&item);
+    }
+
+    let actions = gio::Menu::new();
+    actions.append(Some("Save Current as Profile…"), Some("win.save-profile"));
+
+    let menu = gio::Menu::new();
+    menu.append_section(None, &profiles);
+    menu.append_section(None, &actions);
+    menu
+}
+
+fn show_save_dialog(
+    window: &adw::ApplicationWindow,
+    button: &gtk4::MenuButton,
+    toast_overlay: &adw::ToastOverlay,
+) {
+    let name_entry = gtk4::Entry::builder()
+        .placeholder_text("Profile name")
+        .activates_default(true)
+        .build();
+
+    let dialog = adw::MessageDialog::builder()
+        .heading("Save Profile")
+        .body("Remembers which entries are enabled and their delays. A profile with the same name is replaced.")
+        .extra_child(&name_entry)
+        .transient_for(window)
+        .modal(true)
+        .build();
+
+    dialog.add_response("cancel", "Cancel");
+    dialog.add_response("save", "Save");
+    dialog.set_response_appearance("save", adw::ResponseAppearance::Suggested);
+    dialog.set_default_response(Some("save"));
+    dialog.set_close_response("cancel");
+    dialog.set_response_enabled("save", false);
+
+    let dialog_clone = dialog.clone();
+    name_entry.connect_changed(move |entry| {
+        dialog_clone.set_response_enabled("save", !entry.text().trim().is_empty());
+    });
+
+    let button = button.clone();
+    let toast_overlay = toast_overlay.clone();
+    dialog.connect_response(Some("save"), move |_, _| {
+        let name = name_entry.text().trim().to_string();
+        match capture_profile(&name).and_then(|profile| save_profile(&profile)) {
+            Ok(_) => {
+                button.set_menu_model(Some(&build_menu()));
+                toast_overlay.add_toast(adw::Toast::new(&format!("Saved profile {}", name)));
+            }
+            Err(e) => {
+                tracing::error!("Failed to save profile {}: {:#}", name, e);
+                toast_overlay.add_toast(adw::Toast::new(&format!("Failed to save profile: {}", e)));
+            }
+        }
+    });
+
+    dialog.present();
+}
+
+/// "Switched to work: Enabled chat, Disabled obs and 3 more"
+fn describe_switch(name: &str, changes: &[ProfileChange]) -> String {
+    if changes.is_empty() {
+        return format!("Switched to {}; nothing changed", name);
+    }
+
+    let listed: Vec<String> = changes
+        .iter()
+        .take(MAX_LISTED_CHANGES)
+        .map(ToString::to_string)
+        .collect();
+    let more = changes.len().saturating_sub(MAX_LISTED_CHANGES);
+
+    if more == 0 {
+        format!("Switched to {}: {}", name, listed.join(", "))
+    } else {
+        format!(
+            "Switched to {}: {} and {} more",
+            name,
+            listed.join(", "),
+            more
+        )
+    }
+}
@@ -0,0 +1,187 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib};
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+use crate::desktop_entry::exec::quote_exec;
+use crate::model::AutostartEntry;
+use crate::operations::run::{RunOptions, RunOutput, describe_exit, run_entry};
+
+/// Start `entry` now and show its output in a window once it exited or
+/// the timeout passed. For an entry with a startup delay, the window offers
+/// to run it again after the delay, as at login.
+pub fn show_run_log(parent: &adw::ApplicationWindow, entry: AutostartEntry) {
+    let name = entry.desktop_entry.display_name().to_string();
+
+    let window = adw::Window::builder()
+        .title(format!("Run {}", name))
+        .default_width(560)
+        .default_height(400)
+        .transient_for(parent)
+        .build();
+
+    let header_bar = adw::HeaderBar::new();
+
+    let spinner = gtk4::Spinner::builder().spinning(true).build();
+    header_bar.pack_start(&spinner);
+
+    let run_again_button = gtk4::Button::builder()
+        .label("Run Again")
+        .sensitive(false)
+        .build();
+    header_bar.pack_end(&run_again_button);
+
+    let delay_check = gtk4::CheckButton::builder()
+        .label("Wait for Delay")
+        .visible(false)
+        .build();
+    if let Some(seconds) = entry.delay_seconds() {
+        delay_check.set_tooltip_text(Some(&format!(
+            "Wait {}s before starting, as at login",
+            seconds
+        )));
+        delay_check.set_visible(true);
+    }
+    header_bar.pack_end(&delay_check);
+
+    let status_label = gtk4::Label::builder()
+        .label("Running…")
+        .xalign(0.0)
+        .wrap(true)
+        .build();
+
+    let buffer = gtk4::TextBuffer::new(None);
+    let text_view = gtk4::TextView::builder()
+        .buffer(&buffer)
+        .editable(false)
+        .monospace(true)
+        .top_margin(6)
+        .bottom_margin(6)
+        .left_margin(6)
+        .right_margin(6)
+        .build();
+
+    let scrolled_window = gtk4::ScrolledWindow::builder()
+        .child(&text_view)
+        .vexpand(true)
+        .css_classes(vec!["card"])
+        .build();
+
+    let content_box = gtk4::Box::builder()
+        .orientation(gtk4::Orientation::Vertical)
+        .margin_start(12)
+        .margin_end(12)
+        .margin_top(12)
+        .margin_bottom(12)
+        .spacing(12)
+        .build();
+    content_box.append(&status_label);
+    content_box.append(&scrolled_window);
+
+    let toolbar_view = adw::ToolbarView::new();
+    toolbar_view.add_top_bar(&header_bar);
+    toolbar_view.set_content(Some(&content_box));
+    window.set_content(Some(&toolbar_view));
+
+    window.present();
+
+    let run_again_button_clone = run_again_button.clone();
+    let start = move |options: RunOptions| {
+        start_run(
+            entry.clone(),
+            options,
+            &spinner,
+            &status_label,
+            &buffer,
+            &run_again_button_clone,
+        )
+    };
+    start(RunOptions::default());
+
+    run_again_button.connect_clicked(move |_| {
+        start(RunOptions {
+            with_delay: delay_check.is_active(),
+            ..Default::default()
+        })
+    });
+}
+
+fn start_run(
+    entry: AutostartEntry,
+    options: RunOptions,
+    spinner: &gtk4::Spinner,
+    status_label: &gtk4::Label,
+    buffer: &gtk4::TextBuffer,
+    run_again_button: &gtk4::Button,
+) {
+    let name = entry.desktop_entry.display_name().to_string();
+    let timeout = options.timeout;
+
+    match entry.delay_seconds() {
+        Some(seconds) if options.with_delay => {
+            status_label.set_label(&format!("Waiting {}s before starting…", seconds))
+        }
+        _ => status_label.set_label("Running…"),
+    }
+    status_label.remove_css_class("error");
+    buffer.set_text("");
+    spinner.set_visible(true);
+    spinner.set_spinning(true);
+    run_again_button.set_sensitive(false);
+
+    let spinner = spinner.clone();
+    let status_label = status_label.clone();
+    let buffer = buffer.clone();
+    let run_again_button = run_again_button.clone();
+    glib::spawn_future_local(async move {
+        let result = gio::spawn_blocking(move || run_entry(&entry, &options)).await;
+
+        spinner.set_spinning(false);
+        spinner.set_visible(false);
+        run_again_button.set_sensitive(true);
+
+        match result {
+            Ok(Ok(output)) => {
+                status_label.set_label(&describe_output(&output, timeout.as_secs()));
+                buffer.set_text(&format_log(&output));
+            }
+            Ok(Err(e)) => {
+                tracing::error!("Failed to run {}: {:#}", name, e);
+                status_label.set_label(&format!("Failed to start: {:#}", e));
+                status_label.add_css_class("error");
+            }
+            Err(_) => {
+                status_label.set_label("Failed to start: the runner stopped unexpectedly");
+                status_label.add_css_class("error");
+            }
+        }
+    });
+}
+
+fn describe_output(output: &RunOutput, timeout_secs: u64) -> String {
+    match output.status {
+        None => format!(
+            "Still running after {}s (PID {}); it was left running and writes to {}",
+            timeout_secs,
+            output.pid,
+            output.stdout_path.display()
+        ),
+        Some(status) if status.success() => "Exited successfully".to_string(),
+        Some(status) => format!("The program {}", describe_exit(status)),
+    }
+}
+
+fn format_log(output: &RunOutput) -> String {
+    let mut log = format!("$ {}\n", quote_exec(&output.command));
+    if !output.stdout.is_empty() {
+        log.push_str(&output.stdout);
+    }
+    if !output.stderr.is_empty() {
+        log.push_str("\n── stderr ──\n");
+        log.push_str(&output.stderr);
+    }
+    if output.stdout.is_empty() && output.stderr.is_empty() {
+        log.push_str("(no output)\n");
+    }
+    log
+}
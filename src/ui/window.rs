@@ -1,19 +1,42 @@
 use std::cell::RefCell;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::Duration;
 
 use gtk4::prelude::*;
+use gtk4::{gio, glib};
 use libadwaita as adw;
 use libadwaita::prelude::*;
 
-use crate::desktop_entry::CreateOptions;
-use crate::discovery::{discover_applications, discover_autostart_entries};
-use crate::model::{Application, AutostartEntry};
-use crate::operations::create_autostart_entry;
+use crate::config::{Preferences, XDG_PATHS};
+use crate::desktop_entry::fix::Fix;
+use crate::desktop_entry::{CreateOptions, DelayStrategy};
+use crate::discovery::{
+    diff_entries, discover_applications, discover_autostart_entries, discover_broken_entries,
+    discover_trashed_entries,
+};
+use crate::model::{Application, AutostartEntry, EntryOrigin};
+use crate::operations::fix::plan_fix;
+use crate::operations::history::{SessionReport, last_session_report};
+use crate::operations::journal::{RecordId, undo};
+use crate::operations::trash::{purge_trashed_entry, restore_trashed_entry};
+use crate::operations::{create_autostart_entry, delete_autostart_entry};
 
 use super::app_chooser::AppChooserDialog;
-use super::autostart_row::create_autostart_row;
-use super::entry_dialog::EntryDialog;
+use super::autostart_row::{create_autostart_row, create_broken_row, create_removed_row};
+use super::bundle::{export_to_file, import_from_file};
+use super::desktop_preview::{build_desktop_preview, preview_desktop};
+use super::entry_dialog::{EntryDialog, EntryEdit};
+use super::fix_preview::show_fix_preview;
+use super::profiles::build_profile_switcher;
+use super::run_log::show_run_log;
+
+/// How long the autostart directories must be quiet before the list is
+/// refreshed.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Number of trashed entries shown below the list.
+const MAX_RECENTLY_REMOVED: usize = 5;
 
 pub struct MainWindow {
     pub window: adw::ApplicationWindow,
@@ -22,6 +45,10 @@ pub struct MainWindow {
     list_box: gtk4::ListBox,
     stack: gtk4::Stack,
     toast_overlay: adw::ToastOverlay,
+    broken_group: adw::PreferencesGroup,
+    broken_list: gtk4::ListBox,
+    removed_group: adw::PreferencesGroup,
+    removed_list: gtk4::ListBox,
 }
 
 impl MainWindow {
@@ -36,6 +63,8 @@ impl MainWindow {
         let entries: Rc<RefCell<Vec<AutostartEntry>>> = Rc::new(RefCell::new(Vec::new()));
         let applications: Rc<RefCell<Vec<Application>>> = Rc::new(RefCell::new(Vec::new()));
 
+        let toast_overlay = adw::ToastOverlay::new();
+
         let header_bar = adw::HeaderBar::new();
 
         let add_button = gtk4::Button::builder()
@@ -53,7 +82,17 @@ impl MainWindow {
             .tooltip_text("About")
             .build();
 
+        let menu_button = gtk4::MenuButton::builder()
+            .icon_name("open-menu-symbolic")
+            .tooltip_text("Main Menu")
+            .menu_model(&Self::build_menu())
+            .build();
+
+        let profile_button = build_profile_switcher(&window, &toast_overlay);
+
         header_bar.pack_start(&refresh_button);
+        header_bar.pack_start(&profile_button);
+        header_bar.pack_end(&menu_button);
         header_bar.pack_end(&add_button);
         header_bar.pack_end(&about_button);
 
@@ -97,10 +136,34 @@ impl MainWindow {
             .spacing(12)
             .build();
 
+        let broken_list = gtk4::ListBox::builder()
+            .selection_mode(gtk4::SelectionMode::None)
+            .css_classes(vec!["boxed-list"])
+            .build();
+
+        let broken_group = adw::PreferencesGroup::builder()
+            .title("Broken Entries")
+            .description("These files are skipped at login")
+            .visible(false)
+            .build();
+        broken_group.add(&broken_list);
+
+        let removed_list = gtk4::ListBox::builder()
+            .selection_mode(gtk4::SelectionMode::None)
+            .css_classes(vec!["boxed-list"])
+            .build();
+
+        let removed_group = adw::PreferencesGroup::builder()
+            .title("Recently Removed")
+            .visible(false)
+            .build();
+        removed_group.add(&removed_list);
+
         content_box.append(&search_bar);
         content_box.append(&stack);
+        content_box.append(&broken_group);
+        content_box.append(&removed_group);
 
-        let toast_overlay = adw::ToastOverlay::new();
         toast_overlay.set_child(Some(&content_box));
 
         let toolbar_view = adw::ToolbarView::new();
@@ -116,10 +179,37 @@ impl MainWindow {
             list_box: list_box.clone(),
             stack: stack.clone(),
             toast_overlay: toast_overlay.clone(),
+            broken_group: broken_group.clone(),
+            broken_list: broken_list.clone(),
+            removed_group,
+            removed_list,
         };
 
         main_window.load_entries();
         main_window.load_applications();
+        main_window.setup_actions();
+        main_window.watch_autostart_dirs();
+        main_window.watch_trash();
+
+        {
+            let window_clone = window.clone();
+            let entries_clone = entries.clone();
+            let list_box_clone = list_box.clone();
+            let stack_clone = stack.clone();
+            let toast_overlay_clone = toast_overlay.clone();
+
+            let preview_button = build_desktop_preview(&window, move || {
+                Self::refresh_entries(
+                    &window_clone,
+                    &entries_clone,
+                    &list_box_clone,
+                    &stack_clone,
+                    &toast_overlay_clone,
+                    false,
+                );
+            });
+            header_bar.pack_start(&preview_button);
+        }
 
         {
             let window_clone = window.clone();
@@ -137,6 +227,12 @@ impl MainWindow {
                     &toast_overlay_clone,
                     true,
                 );
+                Self::refresh_broken(
+                    &window_clone,
+                    &toast_overlay_clone,
+                    &broken_group,
+                    &broken_list,
+                );
             });
         }
 
@@ -179,35 +275,227 @@ impl MainWindow {
         }
 
         {
+            // Rows are matched by index, so the filter is run again whenever
+            // the entries change
             let entries_clone = entries.clone();
-            let list_box_clone = list_box.clone();
+            let search_entry_clone = search_entry.clone();
+            list_box.set_filter_func(move |row| {
+                let query = search_entry_clone.text().to_lowercase();
+                query.is_empty()
+                    || entries_clone
+                        .borrow()
+                        .get(row.index() as usize)
+                        .is_none_or(|entry| entry.matches_search(&query))
+            });
 
-            search_entry.connect_search_changed(move |entry| {
-                let query = entry.text().to_string().to_lowercase();
-                Self::filter_list(&entries_clone, &list_box_clone, &query);
+            let list_box_weak = list_box.downgrade();
+            search_entry.connect_search_changed(move |_| {
+                if let Some(list_box) = list_box_weak.upgrade() {
+                    list_box.invalidate_filter();
+                }
             });
         }
 
         window
     }
 
-    fn load_entries(&self) {
-        match discover_autostart_entries() {
-            Ok(discovered) => {
-                *self.entries.borrow_mut() = discovered;
-                self.populate_list();
+    fn build_menu() -> gio::Menu {
+        let strategies = gio::Menu::new();
+        strategies.append(Some("Automatic"), Some("win.delay-strategy::auto"));
+        for strategy in DelayStrategy::ALL {
+            strategies.append(
+                Some(&strategy.to_string()),
+                Some(&format!("win.delay-strategy::{}", strategy.key())),
+            );
+        }
 
-                if self.entries.borrow().is_empty() {
-                    self.stack.set_visible_child_name("empty");
-                } else {
-                    self.stack.set_visible_child_name("list");
+        let bundle = gio::Menu::new();
+        bundle.append(Some("Import Entries…"), Some("win.import"));
+        bundle.append(Some("Export Entries…"), Some("win.export"));
+
+        let menu = gio::Menu::new();
+        menu.append_section(None, &bundle);
+        menu.append_section(Some("Default Delay Method"), &strategies);
+        menu
+    }
+
+    fn setup_actions(&self) {
+        let current = Preferences::load()
+            .delay_strategy
+            .map_or("auto", |s| s.key());
+
+        let delay_strategy = gio::SimpleAction::new_stateful(
+            "delay-strategy",
+            Some(glib::VariantTy::STRING),
+            &current.to_variant(),
+        );
+
+        let toast_overlay = self.toast_overlay.clone();
+        delay_strategy.connect_activate(move |action, parameter| {
+            let Some(key) = parameter.and_then(|p| p.str()) else {
+                return;
+            };
+
+            let mut preferences = Preferences::load();
+            preferences.delay_strategy = DelayStrategy::from_key(key);
+            match preferences.save() {
+                Ok(()) => action.set_state(&key.to_variant()),
+                Err(e) => {
+                    tracing::error!("Failed to save preferences: {}", e);
+                    toast_overlay.add_toast(adw::Toast::new(&format!(
+                        "Failed to save preference: {}",
+                        e
+                    )));
                 }
             }
-            Err(e) => {
-                tracing::error!("Failed to discover autostart entries: {}", e);
-                self.show_toast(&format!("Failed to load entries: {}", e));
-            }
+        });
+
+        self.window.add_action(&delay_strategy);
+
+        let import = gio::SimpleAction::new("import", None);
+        let window = self.window.clone();
+        let toast_overlay = self.toast_overlay.clone();
+        import.connect_activate(move |_, _| import_from_file(&window, &toast_overlay));
+        self.window.add_action(&import);
+
+        let export = gio::SimpleAction::new("export", None);
+        let window = self.window.clone();
+        let toast_overlay = self.toast_overlay.clone();
+        export.connect_activate(move |_, _| export_to_file(&window, &toast_overlay));
+        self.window.add_action(&export);
+    }
+
+    fn load_entries(&self) {
+        Self::refresh_entries(
+            &self.window,
+            &self.entries,
+            &self.list_box,
+            &self.stack,
+            &self.toast_overlay,
+            false,
+        );
+        Self::refresh_broken(
+            &self.window,
+            &self.toast_overlay,
+            &self.broken_group,
+            &self.broken_list,
+        );
+    }
+
+    /// Refresh the list whenever another program (or the CLI) changes the
+    /// autostart directories.
+    fn watch_autostart_dirs(&self) {
+        let dirs: Vec<PathBuf> = std::iter::once(&XDG_PATHS.user_autostart)
+            .chain(&XDG_PATHS.system_autostart)
+            .cloned()
+            .collect();
+
+        let window = self.window.clone();
+        let entries = self.entries.clone();
+        let list_box = self.list_box.clone();
+        let stack = self.stack.clone();
+        let toast_overlay = self.toast_overlay.clone();
+        let broken_group = self.broken_group.clone();
+        let broken_list = self.broken_list.clone();
+
+        watch_directories(&self.window, &dirs, move || {
+            Self::refresh_entries(&window, &entries, &list_box, &stack, &toast_overlay, false);
+            Self::refresh_broken(&window, &toast_overlay, &broken_group, &broken_list);
+        });
+    }
+
+    fn refresh_broken(
+        window: &adw::ApplicationWindow,
+        toast_overlay: &adw::ToastOverlay,
+        broken_group: &adw::PreferencesGroup,
+        broken_list: &gtk4::ListBox,
+    ) {
+        let broken = discover_broken_entries().unwrap_or_else(|e| {
+            tracing::warn!("Failed to check autostart files: {}", e);
+            Vec::new()
+        });
+
+        while let Some(child) = broken_list.first_child() {
+            broken_list.remove(&child);
+        }
+
+        for entry in &broken {
+            let target = XDG_PATHS
+                .user_autostart
+                .join(format!("{}.desktop", entry.id));
+            let plan = plan_fix(&entry.path, &target, &Fix::DEFAULT).unwrap_or_else(|e| {
+                tracing::warn!("Cannot plan fixes for {}: {:#}", entry.path.display(), e);
+                None
+            });
+            let fixes = plan.as_ref().map_or(Vec::new(), |p| p.applied.clone());
+
+            let window = window.clone();
+            let toast_overlay = toast_overlay.clone();
+            let row = create_broken_row(entry, &fixes, move || {
+                if let Some(plan) = plan.clone() {
+                    show_fix_preview(&window, &toast_overlay, plan);
+                }
+            });
+            broken_list.append(&row);
+        }
+
+        broken_group.set_visible(!broken.is_empty());
+    }
+
+    fn watch_trash(&self) {
+        let removed_group = self.removed_group.clone();
+        let removed_list = self.removed_list.clone();
+        let toast_overlay = self.toast_overlay.clone();
+
+        Self::refresh_removed(&removed_group, &removed_list, &toast_overlay);
+
+        watch_directories(&self.window, &[XDG_PATHS.trash.join("info")], move || {
+            Self::refresh_removed(&removed_group, &removed_list, &toast_overlay);
+        });
+    }
+
+    fn refresh_removed(
+        removed_group: &adw::PreferencesGroup,
+        removed_list: &gtk4::ListBox,
+        toast_overlay: &adw::ToastOverlay,
+    ) {
+        let trashed = discover_trashed_entries().unwrap_or_else(|e| {
+            tracing::warn!("Failed to read the trash: {}", e);
+            Vec::new()
+        });
+
+        while let Some(child) = removed_list.first_child() {
+            removed_list.remove(&child);
         }
+
+        for trashed_entry in trashed.iter().take(MAX_RECENTLY_REMOVED) {
+            let restore_toast_overlay = toast_overlay.clone();
+            let purge_toast_overlay = toast_overlay.clone();
+
+            let row = create_removed_row(
+                trashed_entry,
+                move |entry| {
+                    let message = match restore_trashed_entry(&entry) {
+                        Ok(()) => format!("Restored {}", entry.name),
+                        Err(e) => {
+                            tracing::error!("Failed to restore {}: {:#}", entry.id, e);
+                            format!("Failed to restore: {}", e)
+                        }
+                    };
+                    restore_toast_overlay.add_toast(adw::Toast::new(&message));
+                },
+                move |entry| {
+                    if let Err(e) = purge_trashed_entry(&entry) {
+                        tracing::error!("Failed to purge {}: {:#}", entry.id, e);
+                        purge_toast_overlay
+                            .add_toast(adw::Toast::new(&format!("Failed to delete: {}", e)));
+                    }
+                },
+            );
+            removed_list.append(&row);
+        }
+
+        removed_group.set_visible(!trashed.is_empty());
     }
 
     fn load_applications(&self) {
@@ -221,55 +509,8 @@ impl MainWindow {
         }
     }
 
-    fn populate_list(&self) {
-        while let Some(child) = self.list_box.first_child() {
-            self.list_box.remove(&child);
-        }
-
-        let entries = self.entries.borrow();
-        let entries_clone = self.entries.clone();
-        let list_box_clone = self.list_box.clone();
-        let stack_clone = self.stack.clone();
-        let toast_overlay_clone = self.toast_overlay.clone();
-        let window_clone = self.window.clone();
-
-        for entry in entries.iter() {
-            let entries_for_edit = entries_clone.clone();
-            let list_box_for_edit = list_box_clone.clone();
-            let stack_for_edit = stack_clone.clone();
-            let toast_overlay_for_edit = toast_overlay_clone.clone();
-            let window_for_edit = window_clone.clone();
-
-            let entries_for_delete = entries_clone.clone();
-            let list_box_for_delete = list_box_clone.clone();
-            let toast_overlay_for_delete = toast_overlay_clone.clone();
-
-            let row = create_autostart_row(
-                entry,
-                move |path, _id| {
-                    Self::handle_edit(
-                        path,
-                        &window_for_edit,
-                        &entries_for_edit,
-                        &list_box_for_edit,
-                        Some(&stack_for_edit),
-                        &toast_overlay_for_edit,
-                    );
-                },
-                move |path, id| {
-                    Self::handle_delete(
-                        path,
-                        &id,
-                        &entries_for_delete,
-                        &list_box_for_delete,
-                        &toast_overlay_for_delete,
-                    );
-                },
-            );
-            self.list_box.append(&row);
-        }
-    }
-
+    /// Reread the autostart directories and replace only the rows of
+    /// entries that were added, removed or changed on disk.
     fn refresh_entries(
         window: &adw::ApplicationWindow,
         entries: &Rc<RefCell<Vec<AutostartEntry>>>,
@@ -279,62 +520,51 @@ impl MainWindow {
         show_toast: bool,
     ) {
         match discover_autostart_entries() {
-            Ok(discovered) => {
-                *entries.borrow_mut() = discovered;
+            Ok(mut discovered) => {
+                if let Some(desktop) = preview_desktop() {
+                    for entry in &mut discovered {
+                        entry.effective_state = entry.compute_effective_state(&desktop);
+                    }
+                }
 
-                while let Some(child) = list_box.first_child() {
-                    list_box.remove(&child);
+                let diff = diff_entries(&entries.borrow(), &discovered);
+                if !diff.is_empty() {
+                    tracing::debug!(
+                        "Removing {} rows, inserting {}",
+                        diff.removed.len(),
+                        diff.inserted.len()
+                    );
                 }
 
-                let entries_ref = entries.borrow();
-                let entries_clone = entries.clone();
-                let list_box_clone = list_box.clone();
-                let toast_overlay_clone = toast_overlay.clone();
-                let stack_clone = stack.clone();
-                let window_clone = window.clone();
-
-                for entry in entries_ref.iter() {
-                    let entries_for_edit = entries_clone.clone();
-                    let list_box_for_edit = list_box_clone.clone();
-                    let toast_overlay_for_edit = toast_overlay_clone.clone();
-                    let window_for_edit = window_clone.clone();
-                    let stack_for_edit = stack_clone.clone();
-
-                    let entries_for_delete = entries_clone.clone();
-                    let list_box_for_delete = list_box_clone.clone();
-                    let toast_overlay_for_delete = toast_overlay_clone.clone();
-
-                    let row = create_autostart_row(
-                        entry,
-                        move |path, _id| {
-                            MainWindow::handle_edit(
-                                path,
-                                &window_for_edit,
-                                &entries_for_edit,
-                                &list_box_for_edit,
-                                Some(&stack_for_edit),
-                                &toast_overlay_for_edit,
-                            );
-                        },
-                        move |path, id| {
-                            MainWindow::handle_delete(
-                                path,
-                                &id,
-                                &entries_for_delete,
-                                &list_box_for_delete,
-                                &toast_overlay_for_delete,
-                            );
-                        },
+                for &index in &diff.removed {
+                    if let Some(row) = list_box.row_at_index(index as i32) {
+                        list_box.remove(&row);
+                    }
+                }
+
+                let report = last_session_report();
+                for &index in &diff.inserted {
+                    let row = Self::create_row(
+                        &discovered[index],
+                        report.as_ref(),
+                        window,
+                        entries,
+                        list_box,
+                        stack,
+                        toast_overlay,
                     );
-                    list_box.append(&row);
+                    list_box.insert(&row, index as i32);
                 }
 
-                if entries_ref.is_empty() {
+                if discovered.is_empty() {
                     stack.set_visible_child_name("empty");
                 } else {
                     stack.set_visible_child_name("list");
                 }
 
+                *entries.borrow_mut() = discovered;
+                list_box.invalidate_filter();
+
                 if show_toast {
                     let toast = adw::Toast::new("Entries refreshed");
                     toast_overlay.add_toast(toast);
@@ -348,6 +578,73 @@ impl MainWindow {
         }
     }
 
+    fn create_row(
+        entry: &AutostartEntry,
+        report: Option<&SessionReport>,
+        window: &adw::ApplicationWindow,
+        entries: &Rc<RefCell<Vec<AutostartEntry>>>,
+        list_box: &gtk4::ListBox,
+        stack: &gtk4::Stack,
+        toast_overlay: &adw::ToastOverlay,
+    ) -> adw::ActionRow {
+        let entries_for_edit = entries.clone();
+        let list_box_for_edit = list_box.clone();
+        let toast_overlay_for_edit = toast_overlay.clone();
+        let window_for_edit = window.clone();
+        let stack_for_edit = stack.clone();
+
+        let window_for_delete = window.clone();
+        let entries_for_delete = entries.clone();
+        let list_box_for_delete = list_box.clone();
+        let stack_for_delete = stack.clone();
+        let toast_overlay_for_delete = toast_overlay.clone();
+
+        let window_for_run = window.clone();
+        let entries_for_run = entries.clone();
+
+        let toast_overlay_for_toggle = toast_overlay.clone();
+
+        create_autostart_row(
+            entry,
+            report,
+            move |path, _id| {
+                MainWindow::handle_edit(
+                    path,
+                    &window_for_edit,
+                    &entries_for_edit,
+                    &list_box_for_edit,
+                    Some(&stack_for_edit),
+                    &toast_overlay_for_edit,
+                );
+            },
+            move |path, id| {
+                MainWindow::handle_delete(
+                    path,
+                    &id,
+                    &window_for_delete,
+                    &entries_for_delete,
+                    &list_box_for_delete,
+                    &stack_for_delete,
+                    &toast_overlay_for_delete,
+                );
+            },
+            move |path| {
+                let entry = entries_for_run
+                    .borrow()
+                    .iter()
+                    .find(|e| e.path == path)
+                    .cloned();
+                if let Some(entry) = entry {
+                    show_run_log(&window_for_run, entry);
+                }
+            },
+            move |message, record| {
+                let toast = undo_toast(&message, record, &toast_overlay_for_toggle);
+                toast_overlay_for_toggle.add_toast(toast);
+            },
+        )
+    }
+
     fn show_add_dialog(
         window: &adw::ApplicationWindow,
         entries: &Rc<RefCell<Vec<AutostartEntry>>>,
@@ -370,11 +667,14 @@ impl MainWindow {
                 let options = CreateOptions {
                     icon: app.icon.clone(),
                     comment: app.comment.clone(),
+                    localized_name: app.localized_name.clone(),
+                    localized_comment: app.localized_comment.clone(),
+                    environment: app.environment.clone(),
                     ..Default::default()
                 };
 
                 match create_autostart_entry(&app.id, &app.name, &app.exec, options) {
-                    Ok(_) => {
+                    Ok((_, record)) => {
                         Self::refresh_entries(
                             &window_clone,
                             &entries_clone,
@@ -383,7 +683,11 @@ impl MainWindow {
                             &toast_overlay_clone,
                             false,
                         );
-                        let toast = adw::Toast::new(&format!("Added {}", app.name));
+                        let toast = undo_toast(
+                            &format!("Added {}", app.display_name()),
+                            record,
+                            &toast_overlay_clone,
+                        );
                         toast_overlay_clone.add_toast(toast);
                     }
                     Err(e) => {
@@ -429,7 +733,7 @@ impl MainWindow {
 
         let dialog = EntryDialog::new_for_create(window, move |id, name, exec, options| {
             match create_autostart_entry(&id, &name, &exec, options) {
-                Ok(_) => {
+                Ok((_, record)) => {
                     MainWindow::refresh_entries(
                         &window_clone,
                         &entries_clone,
@@ -438,7 +742,8 @@ impl MainWindow {
                         &toast_overlay_clone,
                         false,
                     );
-                    let toast = adw::Toast::new(&format!("Created {}", name));
+                    let toast =
+                        undo_toast(&format!("Created {}", name), record, &toast_overlay_clone);
                     toast_overlay_clone.add_toast(toast);
                 }
                 Err(e) => {
@@ -452,40 +757,6 @@ impl MainWindow {
         dialog.present();
     }
 
-    fn filter_list(
-        entries: &Rc<RefCell<Vec<AutostartEntry>>>,
-        list_box: &gtk4::ListBox,
-        query: &str,
-    ) {
-        let entries_ref = entries.borrow();
-        let mut index = 0;
-
-        let mut child = list_box.first_child();
-        while let Some(widget) = child {
-            if let Some(entry) = entries_ref.get(index) {
-                let matches = query.is_empty()
-                    || entry.desktop_entry.name.to_lowercase().contains(query)
-                    || entry
-                        .desktop_entry
-                        .comment
-                        .as_ref()
-                        .map(|c| c.to_lowercase().contains(query))
-                        .unwrap_or(false)
-                    || entry.desktop_entry.exec.to_lowercase().contains(query);
-
-                widget.set_visible(matches);
-            }
-
-            child = widget.next_sibling();
-            index += 1;
-        }
-    }
-
-    fn show_toast(&self, message: &str) {
-        let toast = adw::Toast::new(message);
-        self.toast_overlay.add_toast(toast);
-    }
-
     fn handle_edit(
         path: PathBuf,
         window: &adw::ApplicationWindow,
@@ -503,8 +774,8 @@ impl MainWindow {
             let stack_clone = stack.cloned();
             let toast_overlay_clone = toast_overlay.clone();
 
-            let dialog = EntryDialog::new_for_edit(window, &entry, move |changes| {
-                use crate::operations::edit_autostart_entry;
+            let dialog = EntryDialog::new_for_edit(window, &entry, move |edit| {
+                use crate::operations::{edit_autostart_entry, edit_autostart_source};
 
                 // Load the current entry again - drop borrow before refresh
                 let current_entry = {
@@ -516,9 +787,17 @@ impl MainWindow {
                 };
 
                 if let Some(current_entry) = current_entry {
-                    let entry_name = current_entry.desktop_entry.name.clone();
-                    match edit_autostart_entry(&current_entry, changes) {
-                        Ok(_) => {
+                    let entry_name = current_entry.desktop_entry.display_name().to_string();
+                    let result = match edit {
+                        EntryEdit::Changes(changes) => {
+                            edit_autostart_entry(&current_entry, *changes)
+                        }
+                        EntryEdit::Source(content) => {
+                            edit_autostart_source(&current_entry, &content)
+                        }
+                    };
+                    match result {
+                        Ok(record) => {
                             if let Some(ref stack) = stack_clone {
                                 MainWindow::refresh_entries(
                                     &window_clone,
@@ -529,7 +808,11 @@ impl MainWindow {
                                     false,
                                 );
                             }
-                            let toast = adw::Toast::new(&format!("Updated {}", entry_name));
+                            let toast = undo_toast(
+                                &format!("Updated {}", entry_name),
+                                record,
+                                &toast_overlay_clone,
+                            );
                             toast_overlay_clone.add_toast(toast);
                         }
                         Err(e) => {
@@ -548,13 +831,29 @@ impl MainWindow {
     fn handle_delete(
         path: PathBuf,
         name: &str,
+        window: &adw::ApplicationWindow,
         entries: &Rc<RefCell<Vec<AutostartEntry>>>,
         list_box: &gtk4::ListBox,
+        stack: &gtk4::Stack,
         toast_overlay: &adw::ToastOverlay,
     ) {
-        match std::fs::remove_file(&path) {
-            Ok(_) => {
-                tracing::info!("Deleted autostart entry: {}", path.display());
+        let Some(entry) = entries.borrow().iter().find(|e| e.path == path).cloned() else {
+            return;
+        };
+
+        match delete_autostart_entry(&entry) {
+            Ok(record) => {
+                if entry.origin == EntryOrigin::UserOverridesSystem {
+                    // The system entry becomes visible again
+                    Self::refresh_entries(window, entries, list_box, stack, toast_overlay, false);
+                    let toast = undo_toast(
+                        &format!("Reverted {} to system entry", name),
+                        record,
+                        toast_overlay,
+                    );
+                    toast_overlay.add_toast(toast);
+                    return;
+                }
 
                 let delete_index = { entries.borrow().iter().position(|e| e.path == path) };
                 entries.borrow_mut().retain(|e| e.path != path);
@@ -565,7 +864,11 @@ impl MainWindow {
                     list_box.remove(&row);
                 }
 
-                let toast = adw::Toast::new(&format!("Deleted {}", name));
+                let toast = undo_toast(
+                    &format!("Moved {} to the trash", name),
+                    record,
+                    toast_overlay,
+                );
                 toast_overlay.add_toast(toast);
             }
             Err(e) => {
@@ -576,3 +879,79 @@ impl MainWindow {
         }
     }
 }
+
+/// Toast for a change, offering to revert the journal record it wrote. No
+/// Undo button is shown when the change was not recorded. The list picks up
+/// the restored files through the directory monitors.
+pub fn undo_toast(
+    message: &str,
+    record: Option<RecordId>,
+    toast_overlay: &adw::ToastOverlay,
+) -> adw::Toast {
+    let toast = adw::Toast::new(message);
+
+    let Some(record) = record else {
+        return toast;
+    };
+
+    let toast_overlay = toast_overlay.clone();
+    let message = message.to_string();
+    toast.set_button_label(Some("Undo"));
+    toast.connect_button_clicked(move |_| {
+        if let Err(e) = undo(record) {
+            tracing::error!("Failed to undo \"{}\": {:#}", message, e);
+            toast_overlay.add_toast(adw::Toast::new(&format!("Failed to undo: {}", e)));
+        }
+    });
+
+    toast
+}
+
+/// Call `on_change` whenever something changes in one of `dirs`, for as
+/// long as `window` exists. Bursts of events, like those caused by an
+/// atomic write, are coalesced into a single call.
+fn watch_directories(
+    window: &adw::ApplicationWindow,
+    dirs: &[PathBuf],
+    on_change: impl Fn() + 'static,
+) {
+    let on_change = Rc::new(on_change);
+    let pending: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+
+    let monitors: Vec<gio::FileMonitor> = dirs
+        .iter()
+        .filter_map(|dir| {
+            gio::File::for_path(dir)
+                .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)
+                .inspect_err(|e| tracing::warn!("Cannot watch {}: {}", dir.display(), e))
+                .ok()
+        })
+        .collect();
+
+    for monitor in &monitors {
+        let pending = pending.clone();
+        let on_change = on_change.clone();
+
+        monitor.connect_changed(move |_, _, _, _| {
+            if let Some(source) = pending.borrow_mut().take() {
+                source.remove();
+            }
+
+            let pending_clone = pending.clone();
+            let on_change = on_change.clone();
+            let source = glib::timeout_add_local_once(WATCH_DEBOUNCE, move || {
+                pending_clone.borrow_mut().take();
+                on_change();
+            });
+            *pending.borrow_mut() = Some(source);
+        });
+    }
+
+    // The monitors stop reporting once dropped, so they live as long as the
+    // window
+    window.connect_destroy(move |_| {
+        for monitor in &monitors {
+            monitor.cancel();
+        }
+    });
+}
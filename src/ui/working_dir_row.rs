@@ -0,0 +1,84 @@
+use gtk4::gio;
+use gtk4::prelude::*;
+use libadwaita as adw;
+use libadwaita::prelude::*;
+
+/// Row editing `Path`, typed or picked with a folder chooser.
+pub struct WorkingDirRow {
+    row: adw::EntryRow,
+    initial: Option<String>,
+}
+
+impl WorkingDirRow {
+    pub fn new(working_dir: Option<&str>) -> Self {
+        let row = adw::EntryRow::builder()
+            .title("Working Directory")
+            .text(working_dir.unwrap_or_default())
+            .build();
+
+        let choose_button = gtk4::Button::builder()
+            .icon_name("folder-open-symbolic")
+            .valign(gtk4::Align::Center)
+            .css_classes(vec!["flat"])
+            .tooltip_text("Choose Folder")
+            .build();
+        row.add_suffix(&choose_button);
+
+        let row_clone = row.clone();
+        choose_button.connect_clicked(move |_| choose_folder(&row_clone));
+
+        // The session does not expand `~` or resolve relative directories
+        row.connect_changed(|row| {
+            let text = row.text();
+            if text.is_empty() || text.starts_with('/') {
+                row.remove_css_class("error");
+            } else {
+                row.add_css_class("error");
+            }
+        });
+
+        WorkingDirRow {
+            row,
+            initial: working_dir.map(String::from),
+        }
+    }
+
+    pub fn add_to(&self, group: &adw::PreferencesGroup) {
+        group.add(&self.row);
+    }
+
+    pub fn working_dir(&self) -> Option<String> {
+        let text = self.row.text();
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    /// The new directory, if it differs from what the entry had.
+    pub fn changed_working_dir(&self) -> Option<Option<String>> {
+        let working_dir = self.working_dir();
+        (working_dir != self.initial).then_some(working_dir)
+    }
+}
+
+fn choose_folder(row: &adw::EntryRow) {
+    let dialog = gtk4::FileDialog::builder()
+        .title("Choose Working Directory")
+        .modal(true)
+        .build();
+
+    let text = row.text();
+    if text.starts_with('/') {
+        dialog.set_initial_folder(Some(&gio::File::for_path(text.as_str())));
+    }
+
+    let parent = row.root().and_downcast::<gtk4::Window>();
+    let row = row.clone();
+    dialog.select_folder(parent.as_ref(), gio::Cancellable::NONE, move |result| {
+        let Ok(folder) = result else {
+            return;
+        };
+        if let Some(path) = folder.path() {
+            row.set_text(&path.display().to_string());
+        }
+    });
+}
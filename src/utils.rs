@@ -1,11 +1,112 @@
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::config::XDG_PATHS;
+
+/// Names of all installed icons, without extension, from every theme.
+static INSTALLED_ICONS: LazyLock<HashSet<String>> = LazyLock::new(|| {
+    let mut names = HashSet::new();
+    for dir in &XDG_PATHS.icon_dirs {
+        collect_icon_names(dir, 0, &mut names);
+    }
+    names
+});
+
+/// Icon files are at most `<theme>/<size>/<context>/` deep.
+const ICON_DIR_DEPTH: usize = 3;
 
 pub fn binary_exists(binary: &str) -> bool {
+    find_binary(binary).is_some()
+}
+
+/// Resolve a program name the way `execvp` does: absolute paths are taken
+/// as-is, anything else is looked up in `$PATH`.
+pub fn find_binary(binary: &str) -> Option<PathBuf> {
     if binary.starts_with('/') {
-        Path::new(binary).exists()
-    } else {
-        std::env::var_os("PATH")
-            .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(binary).exists()))
-            .unwrap_or(false)
+        let path = Path::new(binary);
+        return path.exists().then(|| path.to_path_buf());
+    }
+
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(binary))
+        .find(|path| path.exists())
+}
+
+/// "5 min ago" for an age in seconds.
+pub fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", seconds / 60),
+        3600..86400 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
+
+/// Whether an `Icon` value can be resolved: an absolute path that exists
+/// or a name found in any icon theme or pixmap directory. Themes are not
+/// resolved individually, so an icon of another theme counts as found.
+pub fn icon_exists(icon: &str) -> bool {
+    if icon.starts_with('/') {
+        return Path::new(icon).exists();
     }
+    INSTALLED_ICONS.contains(icon)
+}
+
+fn collect_icon_names(dir: &Path, depth: usize, names: &mut HashSet<String>) {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < ICON_DIR_DEPTH {
+                collect_icon_names(&path, depth + 1, names);
+            }
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("png" | "svg" | "xpm")
+        ) && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+        {
+            names.insert(stem.to_string());
+        }
+    }
+}
+
+/// Escape a path for use in a URI, as required for the `Path` key of
+/// `.trashinfo` files.
+pub fn percent_encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_encoded_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
 }